@@ -0,0 +1,82 @@
+/*
+ * C API of the PIE inference engine. Link against the cdylib built by `cargo build --release`
+ * (libpie.so, libpie.dylib or pie.dll).
+ *
+ * Ownership:
+ * - pie_kb_new and pie_engine_new return handles the caller owns and frees with pie_kb_free and pie_engine_free.
+ *   pie_engine_new takes over the knowledge base: its handle must not be used or freed afterwards.
+ * - pie_engine_knowledge_base lends the engine's knowledge base. It stays valid until the engine is freed and must
+ *   not be freed itself.
+ * - pie_engine_query returns a result set the caller frees with pie_query_free. The strings it hands out belong to
+ *   the result set and stay valid until it is freed.
+ * - Strings passed in are NUL-terminated UTF-8 and only read during the call.
+ *
+ * Errors: functions that can fail return PIE_OK or a negative PIE_ERROR_* code and never abort the process;
+ * pie_last_error describes the last failure on the calling thread. Handles are not thread-safe: use each one from
+ * one thread at a time.
+ */
+#ifndef PIE_H
+#define PIE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define PIE_OK 0
+/* pie_query_next has no more results. */
+#define PIE_DONE 1
+#define PIE_ERROR_NULL_ARGUMENT (-1)
+#define PIE_ERROR_INVALID_UTF8 (-2)
+/* The text could not be parsed or loaded: a syntax error, an unsafe rule or a type declaration mismatch. */
+#define PIE_ERROR_INVALID (-3)
+#define PIE_ERROR_NOT_FOUND (-4)
+/* An internal error; the handles involved are still safe to free. */
+#define PIE_ERROR_PANIC (-5)
+
+typedef struct PieKnowledgeBase PieKnowledgeBase;
+typedef struct PieEngine PieEngine;
+typedef struct PieQuery PieQuery;
+
+/* The message of the last call that failed on this thread, or "". Valid until the next failure. */
+const char *pie_last_error(void);
+
+PieKnowledgeBase *pie_kb_new(void);
+void pie_kb_free(PieKnowledgeBase *knowledge_base);
+/* Loads knowledge-base text in PIE syntax; name (may be NULL) is the file recorded for it. All or nothing. */
+int pie_kb_load_text(PieKnowledgeBase *knowledge_base, const char *text, const char *name);
+/* Adds facts from JSON as `--facts-json` reads it: an array of records (arrays of arguments, or objects with the
+ * same keys) of predicate, or with predicate NULL an object of predicates with their arrays. All or nothing. */
+int pie_kb_import_json(PieKnowledgeBase *knowledge_base, const char *json, const char *predicate);
+/* Adds a given fact such as "parent(ama, osei)", checked against the type declarations. */
+int pie_kb_add_fact(PieKnowledgeBase *knowledge_base, const char *fact);
+/* Removes a given fact and clears the derived facts; PIE_ERROR_NOT_FOUND if it is not a given fact. */
+int pie_kb_remove_fact(PieKnowledgeBase *knowledge_base, const char *fact);
+
+/* Takes over the knowledge base. Returns NULL if it is NULL. */
+PieEngine *pie_engine_new(PieKnowledgeBase *knowledge_base);
+void pie_engine_free(PieEngine *engine);
+/* The engine's knowledge base, lent until the engine is freed. */
+PieKnowledgeBase *pie_engine_knowledge_base(PieEngine *engine);
+/* Derives every fact that follows from the knowledge base. */
+int pie_engine_infer(PieEngine *engine);
+/* Proves a goal such as "grandparent(ama, kofi)" by backward chaining. */
+int pie_engine_prove(PieEngine *engine, const char *goal, bool *proven);
+/* The known facts matching a pattern such as "parent(x?, osei)"; infer first to include derived facts. */
+int pie_engine_query(PieEngine *engine, const char *pattern, PieQuery **result);
+
+/* Moves to the next match and stores its fact; PIE_DONE when there are no more. */
+int pie_query_next(PieQuery *query, const char **fact);
+/* The number of variables bound by the current match. */
+size_t pie_query_binding_count(const PieQuery *query);
+/* The name (without '?') and value of a variable bound by the current match. */
+int pie_query_binding(const PieQuery *query, size_t index, const char **name, const char **value);
+void pie_query_free(PieQuery *query);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,254 @@
+"""Python bindings for the PIE inference engine, calling the C API of the shared library through ctypes.
+
+    kb = KnowledgeBase()
+    kb.add_facts([Fact("parent", "ama", "osei"), "parent(osei, kofi)"])
+    kb.add_rule(Rule("parent(x?, y?) & parent(y?, z?)", "grandparent(x?, z?)"))
+    engine = InferenceEngine(kb)
+    engine.infer()
+    engine.query("grandparent(x?, y?)")   # [Match(fact=Fact('grandparent', 'ama', 'kofi'), bindings={...})]
+
+Knowledge bases and engines are not thread-safe: use each from one thread at a time.
+"""
+
+import ctypes
+import json
+from typing import NamedTuple
+
+from . import _native
+from ._native import library
+
+__all__ = ["Fact", "Rule", "Match", "KnowledgeBase", "InferenceEngine", "PieError"]
+
+
+class PieError(Exception):
+    """A failed call, with the status code of the C API in `code`."""
+
+    def __init__(self, code, message):
+        super().__init__(message)
+        self.code = code
+
+
+def _check(code):
+    if code < 0:
+        raise PieError(code, library.pie_last_error().decode())
+    return code
+
+
+def _encode(text):
+    return None if text is None else str(text).encode()
+
+
+def _value(text):
+    """An argument as written by PIE: integers become int, atoms and variables stay str."""
+    try:
+        return int(text)
+    except ValueError:
+        return text
+
+
+class Fact:
+    """A fact such as `parent(ama, osei)`: a predicate name, arguments that are atoms (str), integers (int) or
+    variables (str ending in `?`), and whether it is negated."""
+
+    __slots__ = ("predicate", "arguments", "negative")
+
+    def __init__(self, predicate, *arguments, negative=False):
+        self.predicate = predicate
+        self.arguments = tuple(arguments)
+        self.negative = negative
+
+    @classmethod
+    def parse(cls, text):
+        text = text.strip().rstrip(".").strip()
+        negative = text.startswith("!")
+        text = text.removeprefix("!")
+        if "(" not in text:
+            return cls(text, negative=negative)
+        if not text.endswith(")"):
+            raise ValueError(f"Invalid fact '{text}'")
+        predicate, arguments = text[:-1].split("(", 1)
+        return cls(predicate.strip(), *(_value(argument.strip()) for argument in _split_arguments(arguments)), negative=negative)
+
+    def negated(self):
+        return Fact(self.predicate, *self.arguments, negative=not self.negative)
+
+    def __str__(self):
+        sign = "!" if self.negative else ""
+        if not self.arguments:
+            return f"{sign}{self.predicate}"
+        return f"{sign}{self.predicate}({', '.join(str(argument) for argument in self.arguments)})"
+
+    def __repr__(self):
+        arguments = "".join(f", {argument!r}" for argument in self.arguments)
+        return f"Fact({self.predicate!r}{arguments}{', negative=True' if self.negative else ''})"
+
+    def __eq__(self, other):
+        if not isinstance(other, Fact):
+            return NotImplemented
+        return (self.predicate, self.arguments, self.negative) == (other.predicate, other.arguments, other.negative)
+
+    def __hash__(self):
+        return hash((self.predicate, self.arguments, self.negative))
+
+
+def _split_arguments(text):
+    """Splits arguments at the commas that are not inside a nested term, so `g(a, b), c` is two arguments."""
+    arguments, depth, start = [], 0, 0
+    for index, character in enumerate(text):
+        if character == "(":
+            depth += 1
+        elif character == ")":
+            depth -= 1
+        elif character == "," and depth == 0:
+            arguments.append(text[start:index])
+            start = index + 1
+    arguments.append(text[start:])
+    return arguments
+
+
+def _fact(fact):
+    return fact if isinstance(fact, Fact) else Fact.parse(fact)
+
+
+class Rule:
+    """A rule `antecedents -> consequent`. The antecedents are PIE text such as `parent(x?, y?) & !adopted(y?)`,
+    or a list of facts that must all hold."""
+
+    __slots__ = ("antecedents", "consequent")
+
+    def __init__(self, antecedents, consequent):
+        if not isinstance(antecedents, str):
+            antecedents = " & ".join(str(antecedent) for antecedent in antecedents)
+        self.antecedents = antecedents.strip()
+        self.consequent = _fact(consequent)
+
+    @classmethod
+    def parse(cls, text):
+        antecedents, separator, consequent = text.strip().rstrip(".").rpartition("->")
+        if not separator:
+            raise ValueError(f"Invalid rule '{text}': expected antecedents -> consequent")
+        return cls(antecedents, consequent)
+
+    def __str__(self):
+        return f"{self.antecedents} -> {self.consequent}"
+
+    def __repr__(self):
+        return f"Rule({self.antecedents!r}, {str(self.consequent)!r})"
+
+    def __eq__(self, other):
+        if not isinstance(other, Rule):
+            return NotImplemented
+        return (self.antecedents, self.consequent) == (other.antecedents, other.consequent)
+
+    def __hash__(self):
+        return hash((self.antecedents, self.consequent))
+
+
+class Match(NamedTuple):
+    """A fact matching a query pattern, with the values of the pattern's variables by name (without `?`)."""
+
+    fact: Fact
+    bindings: dict
+
+
+class _Handle:
+    """Owns a pointer from the C API and frees it when garbage collected."""
+
+    def __init__(self, pointer, free):
+        if not pointer:
+            raise PieError(_native.ERROR_NULL_ARGUMENT, library.pie_last_error().decode())
+        self.pointer = pointer
+        self.free = free
+
+    def release(self):
+        pointer, self.pointer = self.pointer, None
+        return pointer
+
+    def __del__(self):
+        if self.pointer:
+            self.free(self.pointer)
+
+
+class KnowledgeBase:
+    """Given facts, rules and type declarations. Every method that adds something adds all of it or, raising
+    `PieError`, nothing."""
+
+    def __init__(self, text=None):
+        self._owner = _Handle(library.pie_kb_new(), library.pie_kb_free)
+        self._pointer = self._owner.pointer
+        self._engine = None
+        if text is not None:
+            self.load(text)
+
+    def load(self, text, name=None):
+        """Loads PIE source text; `name` is the file reported in errors."""
+        _check(library.pie_kb_load_text(self._pointer, _encode(text), _encode(name)))
+
+    def add_fact(self, fact):
+        _check(library.pie_kb_add_fact(self._pointer, _encode(_fact(fact))))
+
+    def add_facts(self, facts):
+        """Adds an iterable of facts, given as `Fact`s or text."""
+        self.load("".join(f"{_fact(fact)}.\n" for fact in facts))
+
+    def add_records(self, records, predicate=None):
+        """Adds facts from plain data as `--facts-json` reads JSON: a list of records of `predicate`, each a list of
+        arguments or a dict whose values are the arguments in key order, or without `predicate` a dict of
+        predicates with their lists of records. Strings become atoms, so `"Human Resources"` is
+        `Human_Resources`."""
+        _check(library.pie_kb_import_json(self._pointer, _encode(json.dumps(records)), _encode(predicate)))
+
+    def remove_fact(self, fact):
+        """Removes a given fact, raising `KeyError` if it is not one. The derived facts are cleared; infer again."""
+        try:
+            _check(library.pie_kb_remove_fact(self._pointer, _encode(_fact(fact))))
+        except PieError as error:
+            if error.code == _native.ERROR_NOT_FOUND:
+                raise KeyError(str(_fact(fact))) from error
+            raise
+
+    def add_rule(self, rule):
+        """Adds a `Rule` or rule text such as `a(x?) -> b(x?)`."""
+        rule = rule if isinstance(rule, Rule) else Rule.parse(rule)
+        self.load(f"{rule}.")
+
+
+class InferenceEngine:
+    """Forward and backward chaining over a knowledge base. The engine takes over the knowledge base, which stays
+    usable through the engine and `engine.knowledge_base` but cannot be given to another engine."""
+
+    def __init__(self, knowledge_base=None):
+        knowledge_base = KnowledgeBase() if knowledge_base is None else knowledge_base
+        if knowledge_base._engine is not None:
+            raise ValueError("The knowledge base already belongs to an InferenceEngine")
+        self._owner = _Handle(library.pie_engine_new(knowledge_base._pointer), library.pie_engine_free)
+        knowledge_base._owner.release()
+        knowledge_base._owner = self._owner
+        knowledge_base._pointer = library.pie_engine_knowledge_base(self._owner.pointer)
+        knowledge_base._engine = self
+        self.knowledge_base = knowledge_base
+
+    def infer(self):
+        """Derives every fact that follows from the knowledge base."""
+        _check(library.pie_engine_infer(self._owner.pointer))
+
+    def prove(self, goal):
+        """Whether a goal such as `grandparent(ama, kofi)` can be proven by backward chaining."""
+        proven = ctypes.c_bool()
+        _check(library.pie_engine_prove(self._owner.pointer, _encode(_fact(goal)), ctypes.byref(proven)))
+        return proven.value
+
+    def query(self, pattern):
+        """The known facts matching a pattern such as `parent(x?, osei)`. Infer first to include derived facts."""
+        result = ctypes.c_void_p()
+        _check(library.pie_engine_query(self._owner.pointer, _encode(_fact(pattern)), ctypes.byref(result)))
+        query = _Handle(result.value, library.pie_query_free)
+        matches = []
+        fact, name, value = ctypes.c_char_p(), ctypes.c_char_p(), ctypes.c_char_p()
+        while _check(library.pie_query_next(query.pointer, ctypes.byref(fact))) == _native.OK:
+            bindings = {}
+            for index in range(library.pie_query_binding_count(query.pointer)):
+                _check(library.pie_query_binding(query.pointer, index, ctypes.byref(name), ctypes.byref(value)))
+                bindings[name.value.decode()] = _value(value.value.decode())
+            matches.append(Match(Fact.parse(fact.value.decode()), bindings))
+        return matches
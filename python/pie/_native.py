@@ -0,0 +1,65 @@
+"""Loads the PIE shared library and declares the C API of include/pie.h."""
+
+import ctypes
+import os
+import sys
+from pathlib import Path
+
+OK = 0
+DONE = 1
+ERROR_NULL_ARGUMENT = -1
+ERROR_INVALID_UTF8 = -2
+ERROR_INVALID = -3
+ERROR_NOT_FOUND = -4
+ERROR_PANIC = -5
+
+if sys.platform == "win32":
+    LIBRARY_NAME = "pie.dll"
+elif sys.platform == "darwin":
+    LIBRARY_NAME = "libpie.dylib"
+else:
+    LIBRARY_NAME = "libpie.so"
+
+
+def library_path():
+    """The library named by $PIE_LIBRARY, or else the release or debug build of this repository."""
+    if "PIE_LIBRARY" in os.environ:
+        return Path(os.environ["PIE_LIBRARY"])
+    target = Path(__file__).resolve().parents[2] / "target"
+    for profile in ("release", "debug"):
+        path = target / profile / LIBRARY_NAME
+        if path.exists():
+            return path
+    raise ImportError(f"{LIBRARY_NAME} not found in {target}, run `cargo build --release` or set PIE_LIBRARY")
+
+
+def load():
+    library = ctypes.CDLL(str(library_path()))
+    pointer, string, status = ctypes.c_void_p, ctypes.c_char_p, ctypes.c_int
+    signatures = {
+        "pie_last_error": ([], string),
+        "pie_kb_new": ([], pointer),
+        "pie_kb_free": ([pointer], None),
+        "pie_kb_load_text": ([pointer, string, string], status),
+        "pie_kb_import_json": ([pointer, string, string], status),
+        "pie_kb_add_fact": ([pointer, string], status),
+        "pie_kb_remove_fact": ([pointer, string], status),
+        "pie_engine_new": ([pointer], pointer),
+        "pie_engine_free": ([pointer], None),
+        "pie_engine_knowledge_base": ([pointer], pointer),
+        "pie_engine_infer": ([pointer], status),
+        "pie_engine_prove": ([pointer, string, ctypes.POINTER(ctypes.c_bool)], status),
+        "pie_engine_query": ([pointer, string, ctypes.POINTER(pointer)], status),
+        "pie_query_next": ([pointer, ctypes.POINTER(string)], status),
+        "pie_query_binding_count": ([pointer], ctypes.c_size_t),
+        "pie_query_binding": ([pointer, ctypes.c_size_t, ctypes.POINTER(string), ctypes.POINTER(string)], status),
+        "pie_query_free": ([pointer], None),
+    }
+    for name, (arguments, result) in signatures.items():
+        function = getattr(library, name)
+        function.argtypes = arguments
+        function.restype = result
+    return library
+
+
+library = load()
@@ -0,0 +1,90 @@
+import pytest
+
+from pie import Fact, InferenceEngine, KnowledgeBase, Match, PieError, Rule
+
+FAMILY = """
+parent(ama, osei).
+parent(osei, kofi).
+parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).
+"""
+
+
+@pytest.fixture
+def engine():
+    return InferenceEngine(KnowledgeBase(FAMILY))
+
+
+def test_fact_parse_and_format():
+    fact = Fact.parse("employee(john, engineering, 50000)")
+    assert fact == Fact("employee", "john", "engineering", 50000)
+    assert str(fact) == "employee(john, engineering, 50000)"
+    assert str(Fact.parse("!raining").negated()) == "raining"
+    assert Fact.parse("!raining").negative
+    assert Fact.parse("owns(ama, car(red, 4))") == Fact("owns", "ama", "car(red, 4)")
+    with pytest.raises(ValueError):
+        Fact.parse("parent(x")
+
+
+def test_rule_parse_and_format():
+    rule = Rule.parse("parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).")
+    assert rule == Rule([Fact("parent", "x?", "y?"), Fact("parent", "y?", "z?")], "grandparent(x?, z?)")
+    assert str(rule) == "parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?)"
+
+
+def test_infer_and_query(engine):
+    assert engine.query("grandparent(x?, y?)") == []
+    engine.infer()
+    assert engine.query("grandparent(x?, y?)") == [Match(Fact("grandparent", "ama", "kofi"), {"x": "ama", "y": "kofi"})]
+    assert [match.fact for match in engine.query("parent(x?, kofi)")] == [Fact("parent", "osei", "kofi")]
+
+
+def test_prove(engine):
+    assert engine.prove("grandparent(ama, kofi)")
+    assert not engine.prove(Fact("grandparent", "kofi", "ama"))
+
+
+def test_facts_from_lists():
+    knowledge_base = KnowledgeBase()
+    knowledge_base.add_facts([Fact("parent", "ama", "osei"), "parent(osei, kofi)"])
+    knowledge_base.add_rule(Rule("parent(x?, y?) & parent(y?, z?)", "grandparent(x?, z?)"))
+    engine = InferenceEngine(knowledge_base)
+    engine.infer()
+    assert engine.prove("grandparent(ama, kofi)")
+
+
+def test_records_from_lists_and_dicts():
+    knowledge_base = KnowledgeBase(":- type employee(atom, atom, int).\nemployee(x?, d?, s?) & s? > 50000 -> senior(x?).")
+    knowledge_base.add_records([{"name": "John", "department": "Human Resources", "salary": 60000}], predicate="employee")
+    knowledge_base.add_records({"employee": [["mary", "engineering", 40000]], "manager": [["john", "mary"]]})
+    engine = InferenceEngine(knowledge_base)
+    engine.infer()
+    assert engine.query("senior(x?)") == [Match(Fact("senior", "John"), {"x": "John"})]
+    assert engine.query("employee(John, d?, s?)")[0].bindings == {"d": "Human_Resources", "s": 60000}
+    with pytest.raises(PieError, match="should be atom"):
+        knowledge_base.add_records([["bob", 50000, "sales"]], predicate="employee")
+    assert engine.query("employee(bob, d?, s?)") == []
+
+
+def test_changes_through_the_engine(engine):
+    engine.knowledge_base.add_fact("parent(kofi, esi)")
+    engine.infer()
+    assert engine.prove("grandparent(osei, esi)")
+    engine.knowledge_base.remove_fact(Fact("parent", "kofi", "esi"))
+    engine.infer()
+    assert engine.query("grandparent(osei, y?)") == []
+    with pytest.raises(KeyError):
+        engine.knowledge_base.remove_fact("parent(kofi, esi)")
+
+
+def test_errors():
+    knowledge_base = KnowledgeBase()
+    with pytest.raises(PieError, match="notes.pie:1") as error:
+        knowledge_base.load("a & -> b.", name="notes.pie")
+    assert error.value.code == -3
+    with pytest.raises(PieError, match="negative"):
+        knowledge_base.add_fact("!parent(ama, osei)")
+    with pytest.raises(PieError, match="predicate"):
+        knowledge_base.add_records([[1]], predicate="not a name")
+    InferenceEngine(knowledge_base)
+    with pytest.raises(ValueError):
+        InferenceEngine(knowledge_base)
@@ -0,0 +1,726 @@
+use std::{io::{BufRead, IsTerminal, Write}, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::Duration};
+
+use crate::datalog;
+use crate::event_log::{read_log, replay, EventLog, LogEntry};
+use crate::export::{Export, ExportFormat};
+use crate::formatter::{format_antecedents, format_files, format_knowledge_base, FormattedFiles};
+use crate::graph::{dependency_graph, derivation_graph};
+use crate::hypothesis::Hypothesis;
+use crate::import::DataSource;
+use crate::inference_engine::{Explanation, InferenceEngine, QueryMatch};
+use crate::json::Json;
+use crate::knowledge_base::*;
+use crate::linter::{lint, LintWarning};
+use crate::loader::{load_sources, Loader};
+use crate::output::{explanation_report, fact_fields, fact_record, query_report, Format, Report};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::profile::Profiler;
+use crate::repl::{default_history_path, Repl};
+use crate::storage;
+use crate::trace::{parse_kinds, Sink, Tracer};
+use crate::watcher::Watcher;
+
+pub const DEFAULT_FILE: &str = "./examples/default.txt";
+
+/// Exit statuses of the subcommands, as with `grep`: the answer is yes, the answer is no, or something went wrong.
+pub const EXIT_TRUE: i32 = 0;
+pub const EXIT_FALSE: i32 = 1;
+pub const EXIT_ERROR: i32 = 2;
+
+/// A command with its usage and the description `pie --help` gives for it.
+pub struct CommandHelp {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str
+}
+
+/// The subcommands `run` takes, in the order of `pie --help`.
+pub const COMMANDS: [CommandHelp; 15] = [
+    CommandHelp { name: "infer", usage: "infer <path>...", description: "Print the facts derived from the knowledge base" },
+    CommandHelp { name: "prove", usage: "prove <goal> <path>...", description: "Prove a fact by backward chaining; exits with 1 if it is false" },
+    CommandHelp {
+        name: "query",
+        usage: "query <pattern> <path>...",
+        description: "Infer, then print the facts matching a pattern such as parent(x?, kofi) and the variable bindings; exits with 1 if nothing matches"
+    },
+    CommandHelp { name: "explain", usage: "explain <goal> <path>...", description: "Infer, then show the rules and facts a derived fact follows from" },
+    CommandHelp { name: "check", usage: "check <path>...", description: "Load the files and report errors and lint warnings; exits with 1 on errors" },
+    CommandHelp {
+        name: "repl",
+        usage: "repl [--history <path>] <path>...",
+        description: "Enter interactive query mode; type :help for the commands to assert and retract facts, add rules, load files and list facts, or 'quit' to exit"
+    },
+    CommandHelp {
+        name: "save",
+        usage: "save <file> <path>...",
+        description: "Infer, then save the knowledge base and derived facts to a binary file that can be given as the first path of the other commands"
+    },
+    CommandHelp { name: "load", usage: "load <file>", description: "Show the derived facts of a saved knowledge base without inferring again" },
+    CommandHelp {
+        name: "export",
+        usage: "export [--to pie|datalog|json|csv] [--output <path>] [--predicate <name>] [--all] <path>...",
+        description: "Infer, then write the derived facts (with --all also the given ones) grouped by predicate; csv writes one file per predicate into the --output directory"
+    },
+    CommandHelp {
+        name: "graph",
+        usage: "graph [--derivation <fact>] <path>...",
+        description: "Print the predicate dependency graph in Graphviz DOT, negated dependencies dashed, or with --derivation the facts and rule firings a fact follows from"
+    },
+    CommandHelp {
+        name: "convert",
+        usage: "convert [--to pie|datalog] [--output <path>] <path>...",
+        description: "Write the knowledge base in PIE or Datalog syntax (.pl, .dl and .datalog files are read as Datalog); by default in the other syntax of the first file"
+    },
+    CommandHelp {
+        name: "replay",
+        usage: "replay <log> [--until <n>] [--at <time>] [--save <file>]",
+        description: "Rebuild the knowledge base from an event log written with --log, up to the n-th event or a time such as 2026-10-18T09:30, and optionally save it"
+    },
+    CommandHelp {
+        name: "lint",
+        usage: "lint <path>...",
+        description: "Report undefined or unused predicates, rules that can never fire, singleton variables, capitalised constants and duplicate facts; exits with 1 if there are any"
+    },
+    CommandHelp {
+        name: "fmt",
+        usage: "fmt [--check] <path>...",
+        description: "Rewrite files in the canonical layout; with --check only report files that are not formatted and exit with 1 if there are any"
+    },
+    CommandHelp {
+        name: "watch",
+        usage: "watch [--interval <ms>] <path>...",
+        description: "Re-run inference whenever a file changes and print the derived facts that were added (+) or removed (-), polling every 500ms"
+    }
+];
+
+/// How often `watch` looks for changed files unless `--interval` says otherwise.
+const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Whether `run` takes the command.
+pub fn is_command(name: &str) -> bool {
+    COMMANDS.iter().any(|command: &CommandHelp| command.name == name)
+}
+
+/// Options shared by the subcommands: `[<goal>] [<path>...] [--file <path>] [--format <format>] [--debug]
+/// [--trace pretty|jsonl|count] [--trace-events <kind>,...] [--profile] [--assume <fact>]...
+/// [--facts-csv <predicate>=<path>]... [--facts-json [<predicate>=]<path>]... [--log <path>] [--log-source <tag>]`,
+/// `--history <path>` for `repl`, and `save` takes the file to save to before the paths. `replay` takes
+/// `[--until <n>] [--at <time>] [--save <file>]` instead, `export` takes `[--to csv|json|pie|datalog]
+/// [--output <path>] [--predicate <name>] [--all]`, `graph` takes `[--derivation <fact>]`, `convert` takes
+/// `[--to pie|datalog] [--output <path>]`, `fmt` takes `[--check]` and `watch` takes `[--interval <ms>]`. `lint`, `fmt`
+/// and `watch` need at least one path. `--help` shows the usage of the command.
+struct Options {
+    help: bool,
+    goal: Option<Fact>,
+    destination: Option<PathBuf>,
+    assumptions: Vec<Fact>,
+    file_paths: Vec<String>,
+    data: Vec<DataSource>,
+    format: Format,
+    trace: Option<Sink>,
+    trace_events: Option<Vec<&'static str>>,
+    profile: bool,
+    history: Option<PathBuf>,
+    event_log: Option<PathBuf>,
+    log_source: String,
+    until: Option<usize>,
+    at: Option<String>,
+    export_format: ExportFormat,
+    predicate: Option<String>,
+    include_given: bool,
+    check: bool,
+    interval: Duration
+}
+impl Options {
+    fn parse(command: &str, args: &[String]) -> Result<Self, String> {
+        let takes_goal: bool = matches!(command, "prove" | "query" | "explain");
+        let mut goal: Option<Fact> = None;
+        let mut converted: bool = false;
+        let mut options: Options = Options {
+            help: false, goal: None, destination: None, assumptions: Vec::new(), file_paths: Vec::new(), data: Vec::new(), format: Format::Text, trace: None, trace_events: None, profile: false, history: default_history_path(),
+            event_log: None, log_source: std::env::var("USER").unwrap_or_else(|_| String::from("pie")), until: None, at: None,
+            export_format: ExportFormat::Pie, predicate: None, include_given: false, check: false, interval: DEFAULT_WATCH_INTERVAL
+        };
+        let mut index: usize = 0;
+        while index < args.len() {
+            match args[index].as_str() {
+                "--help" | "-h" => {
+                    options.help = true;
+                    return Ok(options);
+                }
+                "--file" | "-f" => {
+                    options.file_paths.push(args.get(index + 1).ok_or("--file requires a path argument")?.clone());
+                    index += 1;
+                }
+                "--format" => {
+                    options.format = Format::parse(args.get(index + 1).ok_or("--format requires text, json, jsonl or csv")?)?;
+                    index += 1;
+                }
+                "--debug" | "-d" => options.trace = Some(Sink::Pretty),
+                "--trace" => {
+                    options.trace = Some(Sink::parse(args.get(index + 1).ok_or("--trace requires pretty, jsonl or count")?)?);
+                    index += 1;
+                }
+                "--profile" => options.profile = true,
+                "--trace-events" => {
+                    options.trace_events = Some(parse_kinds(args.get(index + 1).ok_or("--trace-events requires a list of events such as rule,fire")?)?);
+                    index += 1;
+                }
+                "--assume" => {
+                    let assumption: &String = args.get(index + 1).ok_or("--assume requires a fact argument")?;
+                    options.assumptions.push(Fact::parse(assumption).map_err(|error| format!("Invalid assumption '{}': {}", assumption, error))?);
+                    index += 1;
+                }
+                "--facts-csv" => {
+                    options.data.push(DataSource::parse_csv(args.get(index + 1).ok_or("--facts-csv requires predicate=path")?)?);
+                    index += 1;
+                }
+                "--facts-json" => {
+                    options.data.push(DataSource::parse_json(args.get(index + 1).ok_or("--facts-json requires a path argument")?)?);
+                    index += 1;
+                }
+                "--log" if command != "replay" => {
+                    options.event_log = Some(PathBuf::from(args.get(index + 1).ok_or("--log requires a path argument")?));
+                    index += 1;
+                }
+                "--log-source" => {
+                    options.log_source = args.get(index + 1).ok_or("--log-source requires a name")?.clone();
+                    index += 1;
+                }
+                "--until" if command == "replay" => {
+                    options.until = Some(args.get(index + 1).and_then(|value: &String| value.parse().ok()).ok_or("--until requires a number of events")?);
+                    index += 1;
+                }
+                "--at" if command == "replay" => {
+                    options.at = Some(args.get(index + 1).ok_or("--at requires a time such as 2026-10-18T09:30")?.clone());
+                    index += 1;
+                }
+                "--save" if command == "replay" => {
+                    options.destination = Some(PathBuf::from(args.get(index + 1).ok_or("--save requires a path argument")?));
+                    index += 1;
+                }
+                "--to" if command == "export" => {
+                    options.export_format = ExportFormat::parse(args.get(index + 1).ok_or("--to requires csv, json, pie or datalog")?)?;
+                    index += 1;
+                }
+                "--to" if command == "convert" => {
+                    options.export_format = match args.get(index + 1).map(String::as_str) {
+                        Some("pie") => ExportFormat::Pie,
+                        Some("datalog") => ExportFormat::Datalog,
+                        _ => return Err(String::from("--to requires pie or datalog"))
+                    };
+                    converted = true;
+                    index += 1;
+                }
+                "--output" | "-o" if matches!(command, "export" | "convert") => {
+                    options.destination = Some(PathBuf::from(args.get(index + 1).ok_or("--output requires a path argument")?));
+                    index += 1;
+                }
+                "--predicate" if command == "export" => {
+                    options.predicate = Some(args.get(index + 1).ok_or("--predicate requires a predicate such as bonus or bonus/1")?.clone());
+                    index += 1;
+                }
+                "--all" if command == "export" => options.include_given = true,
+                "--derivation" if command == "graph" => {
+                    let fact: &String = args.get(index + 1).ok_or("--derivation requires a fact argument")?;
+                    options.goal = Some(Fact::parse(fact).map_err(|error| format!("Invalid fact '{}': {}", fact, error))?);
+                    index += 1;
+                }
+                "--history" if command == "repl" => {
+                    options.history = Some(PathBuf::from(args.get(index + 1).ok_or("--history requires a path argument")?));
+                    index += 1;
+                }
+                "--check" if command == "fmt" => options.check = true,
+                "--interval" if command == "watch" => {
+                    options.interval = Duration::from_millis(args.get(index + 1).and_then(|value: &String| value.parse().ok()).ok_or("--interval requires a number of milliseconds")?);
+                    index += 1;
+                }
+                arg if arg.starts_with('-') && arg.len() > 1 => return Err(format!("Unknown argument: {}", arg)),
+                arg if command == "save" && options.destination.is_none() => options.destination = Some(PathBuf::from(arg)),
+                arg if takes_goal && goal.is_none() => goal = Some(Fact::parse(arg).map_err(|error| format!("Invalid goal '{}': {}", arg, error))?),
+                arg => options.file_paths.push(arg.to_string())
+            }
+            index += 1;
+        }
+        if takes_goal {
+            options.goal = Some(goal.ok_or(format!("{} requires a goal, e.g. pie {} \"grandparent(x?, y?)\" <path>...", command, command))?);
+        }
+        if options.trace_events.is_some() && options.trace.is_none() {
+            options.trace = Some(Sink::Pretty);
+        }
+        if command == "save" && options.destination.is_none() {
+            return Err(String::from("save requires a file to save to, e.g. pie save family.piekb <path>..."));
+        }
+        if command == "load" && options.file_paths.len() != 1 {
+            return Err(String::from("load requires exactly one saved knowledge base"));
+        }
+        if command == "replay" && options.file_paths.len() != 1 {
+            return Err(String::from("replay requires exactly one event log"));
+        }
+        if matches!(command, "lint" | "fmt" | "watch") && options.file_paths.is_empty() {
+            return Err(format!("{} requires a file argument", command));
+        }
+        if options.file_paths.is_empty() && options.data.is_empty() {
+            options.file_paths.push(String::from(DEFAULT_FILE));
+        }
+        // Without --to, convert goes to the other syntax than that of the first file.
+        if command == "convert" && !converted {
+            options.export_format = if datalog::is_datalog_file(Path::new(&options.file_paths.first().map_or("", String::as_str))) { ExportFormat::Pie } else { ExportFormat::Datalog };
+        }
+        Ok(options)
+    }
+}
+
+/// What `run` reads the REPL's input from. A terminal gets line editing and history, anything else is read line by
+/// line as it is.
+pub trait Input: BufRead {
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+impl Input for std::io::StdinLock<'_> {
+    fn is_terminal(&self) -> bool {
+        IsTerminal::is_terminal(self)
+    }
+}
+impl Input for std::io::Empty {}
+impl Input for &[u8] {}
+
+/// Runs a subcommand and returns its exit status. Results go to `output` in the requested format, errors to stderr.
+pub fn run(command: &str, args: &[String], input: impl Input, output: &mut impl Write) -> i32 {
+    let options: Options = match Options::parse(command, args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            return EXIT_ERROR;
+        }
+    };
+    if options.help {
+        let help: Option<&CommandHelp> = COMMANDS.iter().find(|help: &&CommandHelp| help.name == command);
+        let text: String = help.map_or_else(|| format!("Unknown command '{}'\n", command), |help: &CommandHelp| format!("Usage: pie {}\n\n{}\n", help.usage, help.description));
+        return emit(output, &Report::new(text, Vec::new()), Format::Text).unwrap_or(if help.is_some() { EXIT_TRUE } else { EXIT_ERROR });
+    }
+    match command {
+        "check" => {
+            let (report, status) = check(&options.file_paths, &options.data);
+            return emit(output, &report, options.format).unwrap_or(status);
+        }
+        "lint" => {
+            let (report, status) = lint_report(&options.file_paths, &options.data);
+            return emit(output, &report, options.format).unwrap_or(status);
+        }
+        "fmt" => {
+            let paths: Vec<&str> = options.file_paths.iter().map(String::as_str).collect();
+            let (report, status) = format_report(&format_files(&paths, options.check), options.check);
+            return emit(output, &report, options.format).unwrap_or(status);
+        }
+        "watch" if options.format != Format::Text => {
+            eprintln!("Error: watch only writes text");
+            return EXIT_ERROR;
+        }
+        "watch" => {
+            return match Watcher::new(options.file_paths).run(options.interval, output) {
+                Ok(()) => EXIT_TRUE,
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    EXIT_ERROR
+                }
+            };
+        }
+        _ => {}
+    }
+    if command == "load" && !storage::is_saved_knowledge_base(Path::new(&options.file_paths[0])) {
+        eprintln!("Error: '{}' is not a saved knowledge base", options.file_paths[0]);
+        return EXIT_ERROR;
+    }
+    if command == "replay" {
+        return match replay_log(&options) {
+            Ok(report) => emit(output, &report, options.format).unwrap_or(EXIT_TRUE),
+            Err(error) => {
+                eprintln!("Error: {}", error);
+                EXIT_ERROR
+            }
+        };
+    }
+    // The engine may be handed on, e.g. to a hypothesis or the REPL, so the tracer is kept here to be finished.
+    let mut tracer: Option<Arc<Mutex<Box<dyn Tracer>>>> = tracer(&options).map(|tracer: Box<dyn Tracer>| Arc::new(Mutex::new(tracer)));
+    let status: i32 = run_traced(command, options, tracer.clone(), input, output);
+    if let Some(tracer) = &mut tracer {
+        tracer.finish();
+    }
+    status
+}
+
+/// Loads the files and runs a command on them, reporting the engine's steps to `tracer`.
+fn run_traced(command: &str, options: Options, tracer: Option<Arc<Mutex<Box<dyn Tracer>>>>, input: impl Input, output: &mut impl Write) -> i32 {
+    let loaded: Result<(Loader, KnowledgeBase), String> = Loader::from_sources(&options.file_paths, &options.data)
+        .and_then(|mut loader: Loader| loader.take().map(|knowledge_base: KnowledgeBase| (loader, knowledge_base)));
+    let (loader, mut knowledge_base) = match loaded {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            return EXIT_ERROR;
+        }
+    };
+    if let Some(path) = &options.event_log {
+        match EventLog::open(path, &options.log_source) {
+            Ok(event_log) => knowledge_base.attach_log(event_log),
+            Err(error) => {
+                eprintln!("Error: {}", error);
+                return EXIT_ERROR;
+            }
+        }
+    }
+    let event_log: Option<Arc<Mutex<dyn ChangeLog>>> = knowledge_base.event_log().cloned();
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(knowledge_base);
+    inference_engine.set_tracer(tracer.map(|tracer: Arc<Mutex<Box<dyn Tracer>>>| Box::new(tracer) as Box<dyn Tracer>));
+    let hypothetical: bool = !options.assumptions.is_empty();
+    if hypothetical && matches!(command, "query" | "explain" | "repl") {
+        inference_engine.infer();
+        let mut hypothesis: Hypothesis = assume(&mut inference_engine, &options.assumptions);
+        hypothesis.infer();
+        let tracer: Option<Box<dyn Tracer>> = hypothesis.inference_engine.set_tracer(None);
+        inference_engine = InferenceEngine::new(hypothesis.into_knowledge_base());
+        inference_engine.set_tracer(tracer);
+    }
+    let (report, status): (Report, i32) = match (command, &options.goal) {
+        ("infer", _) if hypothetical => {
+            inference_engine.infer();
+            let mut hypothesis: Hypothesis = assume(&mut inference_engine, &options.assumptions);
+            hypothesis.infer();
+            (changes_report(&hypothesis.diff()), EXIT_TRUE)
+        }
+        ("infer", _) => {
+            inference_engine.infer();
+            (infer_report(&inference_engine), EXIT_TRUE)
+        }
+        ("save", _) => {
+            inference_engine.infer();
+            let destination: &Path = options.destination.as_deref().expect("save requires a destination");
+            if let Err(error) = inference_engine.knowledge_base.checkpoint(destination) {
+                eprintln!("Error: {}", error);
+                return EXIT_ERROR;
+            }
+            let knowledge_base: &KnowledgeBase = &inference_engine.knowledge_base;
+            let (facts, rules, derived) = (knowledge_base.get_axiomatic_facts().count(), knowledge_base.get_rules().count(), knowledge_base.working_memory().len());
+            let text: String = format!("Saved {} fact(s), {} rule(s) and {} derived fact(s) to {}\n", facts, rules, derived, destination.display());
+            let record: Json = Json::object(vec![
+                ("path", Json::string(destination.display().to_string())),
+                ("facts", Json::Number(facts as f64)),
+                ("rules", Json::Number(rules as f64)),
+                ("derived", Json::Number(derived as f64))
+            ]);
+            (Report::new(text, vec![record]), EXIT_TRUE)
+        }
+        ("export", _) => {
+            inference_engine.infer();
+            return match export(&inference_engine.knowledge_base, &options, output) {
+                Ok(()) => check_log(&event_log, EXIT_TRUE),
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    EXIT_ERROR
+                }
+            };
+        }
+        ("convert", _) => {
+            let knowledge_base: &KnowledgeBase = &inference_engine.knowledge_base;
+            let content: String = if options.export_format == ExportFormat::Datalog { knowledge_base.to_datalog() } else { format_knowledge_base(knowledge_base) };
+            let written: Result<(), String> = match &options.destination {
+                Some(path) => std::fs::write(path, content).map_err(|error| format!("Failed to write '{}': {}", path.display(), error)),
+                None => write!(output, "{}", content).and_then(|_| output.flush()).map_err(|error| format!("Failed to write output: {}", error))
+            };
+            return match written {
+                Ok(()) => check_log(&event_log, EXIT_TRUE),
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    EXIT_ERROR
+                }
+            };
+        }
+        // The derived facts were saved along with the knowledge base, so they are shown without inferring again.
+        ("load", _) => (infer_report(&inference_engine), EXIT_TRUE),
+        ("prove", Some(goal)) => {
+            let proven: bool = if hypothetical { assume(&mut inference_engine, &options.assumptions).prove(goal) } else { inference_engine.prove(goal) };
+            let report: Report = Report::new(format!("{} is {}\n", goal, proven), vec![Json::object(vec![("goal", Json::string(goal.to_string())), ("proven", Json::Bool(proven))])]);
+            (report, if proven { EXIT_TRUE } else { EXIT_FALSE })
+        }
+        ("query", Some(goal)) => {
+            inference_engine.infer();
+            let matches: Vec<QueryMatch> = inference_engine.query_matches(goal);
+            let status: i32 = if matches.is_empty() == goal.is_negative() { EXIT_TRUE } else { EXIT_FALSE };
+            (query_report(goal, &matches), status)
+        }
+        ("explain", Some(goal)) => {
+            let explanations: Vec<Explanation> = inference_engine.explain_matches(goal);
+            if explanations.is_empty() {
+                (Report::new(format!("{} does not hold\n", goal), Vec::new()), EXIT_FALSE)
+            } else {
+                (explanation_report(&explanations), EXIT_TRUE)
+            }
+        }
+        ("graph", None) => (Report::new(dependency_graph(&inference_engine.knowledge_base), Vec::new()), EXIT_TRUE),
+        ("graph", Some(goal)) => {
+            let explanations: Vec<Explanation> = inference_engine.explain_matches(goal);
+            if explanations.is_empty() {
+                eprintln!("{} does not hold", goal);
+                return EXIT_FALSE;
+            }
+            (Report::new(derivation_graph(&explanations), Vec::new()), EXIT_TRUE)
+        }
+        ("repl", _) => {
+            let mut repl: Repl = Repl::new(inference_engine, options.file_paths, options.format).with_data(options.data).with_loader(loader);
+            let result: Result<(), String> = if input.is_terminal() {
+                repl.run_interactive(options.history.as_deref())
+            } else {
+                repl.run(input, output)
+            };
+            return match result {
+                Ok(()) => check_log(&event_log, EXIT_TRUE),
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    EXIT_ERROR
+                }
+            };
+        }
+        _ => {
+            eprintln!("Error: Unknown command '{}'", command);
+            return EXIT_ERROR;
+        }
+    };
+    emit(output, &report, options.format).unwrap_or(check_log(&event_log, status))
+}
+
+/// The tracer asked for with `--trace` and `--profile`, reporting to stderr.
+fn tracer(options: &Options) -> Option<Box<dyn Tracer>> {
+    let mut tracers: Vec<Box<dyn Tracer>> = Vec::new();
+    if let Some(sink) = options.trace {
+        tracers.push(sink.tracer(options.trace_events.clone()));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if options.profile {
+        tracers.push(Box::new(Profiler::reporting_to(std::io::stderr())));
+    }
+    match tracers.len() {
+        0 | 1 => tracers.pop(),
+        _ => Some(Box::new(tracers))
+    }
+}
+
+/// Starts a hypothesis with the assumptions, moving the tracer over so that what happens in it is traced.
+fn assume<'e>(inference_engine: &'e mut InferenceEngine, assumptions: &[Fact]) -> Hypothesis<'e> {
+    let tracer: Option<Box<dyn Tracer>> = inference_engine.set_tracer(None);
+    let mut hypothesis: Hypothesis = inference_engine.assume(assumptions.iter().cloned());
+    hypothesis.inference_engine.set_tracer(tracer);
+    hypothesis
+}
+
+/// Turns the status into an error if the event log could not be written, as the log is then incomplete.
+fn check_log(event_log: &Option<Arc<Mutex<dyn ChangeLog>>>, status: i32) -> i32 {
+    let error: Option<String> = event_log.as_ref().and_then(|event_log| event_log.lock().ok()?.error().map(str::to_string));
+    match error {
+        Some(error) => {
+            eprintln!("Error: {}", error);
+            EXIT_ERROR
+        }
+        None => status
+    }
+}
+
+/// Writes the derived facts, and with `--all` the given ones, to `--output` or else to `output`. CSV is one file per
+/// predicate, so it needs `--output` to be a directory unless there is only one predicate.
+fn export(knowledge_base: &KnowledgeBase, options: &Options, output: &mut impl Write) -> Result<(), String> {
+    let mut export: Export = knowledge_base.export(options.include_given);
+    if let Some(predicate) = &options.predicate {
+        export = export.only(predicate);
+    }
+    let write_error = |path: &Path, error: std::io::Error| format!("Failed to write '{}': {}", path.display(), error);
+    let content: String = match options.export_format {
+        ExportFormat::Json => format!("{}\n", export.to_json()),
+        ExportFormat::Pie => export.to_pie(),
+        ExportFormat::Datalog => export.to_datalog(),
+        ExportFormat::Csv => {
+            let mut files: Vec<(String, String)> = export.to_csv_files();
+            match &options.destination {
+                Some(directory) if files.len() != 1 || directory.is_dir() => {
+                    std::fs::create_dir_all(directory).map_err(|error| write_error(directory, error))?;
+                    for (file_name, csv) in &files {
+                        let path: PathBuf = directory.join(file_name);
+                        std::fs::write(&path, csv).map_err(|error| write_error(&path, error))?;
+                    }
+                    return Ok(());
+                }
+                _ if files.len() > 1 => return Err(format!("{} predicates need one CSV file each, give a directory with --output or pick one with --predicate", files.len())),
+                _ => files.pop().map_or(String::new(), |(_, csv)| csv)
+            }
+        }
+    };
+    match &options.destination {
+        Some(path) => std::fs::write(path, content).map_err(|error| write_error(path, error)),
+        None => write!(output, "{}", content).and_then(|_| output.flush()).map_err(|error| format!("Failed to write output: {}", error))
+    }
+}
+
+/// Rebuilds the knowledge base from the first `--until` events of a log, or those up to the `--at` time, and lists
+/// its given facts, rules and derived facts.
+fn replay_log(options: &Options) -> Result<Report, String> {
+    let path: &String = &options.file_paths[0];
+    let file: std::fs::File = std::fs::File::open(path).map_err(|error| format!("Failed to read file '{}': {}", path, error))?;
+    let entries: Vec<LogEntry> = read_log(std::io::BufReader::new(file)).map_err(|error| format!("{}:{}", path, error))?;
+    let replayed: Vec<&LogEntry> = entries.iter()
+        .take(options.until.unwrap_or(usize::MAX))
+        .take_while(|entry: &&LogEntry| options.at.as_ref().is_none_or(|at: &String| entry.time.get(..at.len()).unwrap_or(&entry.time) <= at.as_str()))
+        .collect();
+    let knowledge_base: KnowledgeBase = replay(replayed.iter().copied())?;
+    if let Some(destination) = &options.destination {
+        knowledge_base.checkpoint(destination)?;
+    }
+    let mut text: String = format!("Replayed {} of {} event(s)\n", replayed.len(), entries.len());
+    let mut records: Vec<Json> = Vec::new();
+    let record = |kind: &str, value: String| Json::object(vec![("kind", Json::string(kind)), ("value", Json::string(value))]);
+    let sections: [(&str, &str, Vec<String>); 3] = [
+        ("Given facts", "given", sorted_facts(knowledge_base.get_axiomatic_facts()).iter().map(|fact: &&Fact| fact.to_string()).collect()),
+        ("Rules", "rule", knowledge_base.get_rules().map(|rule: &Rule| format!("{} -> {}.", format_antecedents(rule), rule.consequent)).collect()),
+        ("Derived facts", "derived", sorted_facts(knowledge_base.working_memory().iter()).iter().map(|fact: &&Fact| fact.to_string()).collect())
+    ];
+    for (title, kind, values) in sections {
+        if values.is_empty() { continue; }
+        text.push_str(&format!("{}:\n", title));
+        for value in values {
+            text.push_str(&format!("  {}\n", value));
+            records.push(record(kind, value));
+        }
+    }
+    Ok(Report::new(text, records))
+}
+
+/// Writes a report, returning an error status if the output is gone.
+fn emit(output: &mut impl Write, report: &Report, format: Format) -> Option<i32> {
+    match write!(output, "{}", report.render(format)).and_then(|_| output.flush()) {
+        Ok(()) => None,
+        Err(error) => {
+            eprintln!("Error: Failed to write output: {}", error);
+            Some(EXIT_ERROR)
+        }
+    }
+}
+
+fn sorted_facts<'f>(facts: impl Iterator<Item = &'f Fact>) -> Vec<&'f Fact> {
+    let mut facts: Vec<&Fact> = facts.collect();
+    facts.sort_by_cached_key(|fact: &&Fact| fact.to_string());
+    facts
+}
+
+fn infer_report(inference_engine: &InferenceEngine) -> Report {
+    let facts: Vec<&Fact> = sorted_facts(inference_engine.knowledge_base.working_memory().iter());
+    let text: String = if facts.is_empty() {
+        String::from("No new facts\n")
+    } else {
+        format!("New facts:\n{}", facts.iter().map(|fact: &&Fact| format!("  {}\n", fact)).collect::<String>())
+    };
+    Report::new(text, facts.into_iter().map(fact_record).collect())
+}
+
+/// What assuming facts changed: the facts that would be removed and added, as `- fact` and `+ fact`.
+fn changes_report(changes: &FactDiff) -> Report {
+    let removed = changes.removed.iter().map(|fact: &Fact| ("removed", fact));
+    let added = changes.added.iter().map(|fact: &Fact| ("added", fact));
+    let changes: Vec<(&str, &Fact)> = removed.chain(added).collect();
+    if changes.is_empty() { return Report::new(String::from("No changes\n"), Vec::new()); }
+    let text: String = changes.iter().map(|(change, fact)| format!("{} {}\n", if *change == "added" { "+" } else { "-" }, fact)).collect();
+    let records: Vec<Json> = changes.iter().map(|(change, fact)| {
+        let mut pairs: Vec<(String, Json)> = fact_fields(fact);
+        pairs.insert(0, (String::from("change"), Json::string(*change)));
+        Json::Object(pairs)
+    }).collect();
+    Report::new(text, records)
+}
+
+/// Loads the files and reports load errors and lint warnings. Only errors make the check fail.
+fn check(file_paths: &[String], data: &[DataSource]) -> (Report, i32) {
+    let record = |severity: &str, location: Option<String>, code: Option<&str>, message: &str| Json::object(vec![
+        ("severity", Json::string(severity)),
+        ("location", location.map_or(Json::Null, Json::string)),
+        ("code", code.map_or(Json::Null, Json::string)),
+        ("message", Json::string(message))
+    ]);
+    match load_sources(file_paths, data) {
+        Ok(knowledge_base) => {
+            let warnings: Vec<LintWarning> = lint_warnings(&knowledge_base, data);
+            let mut text: String = warnings.iter().map(|warning: &LintWarning| format!("{}\n", warning)).collect();
+            text.push_str(&format!("OK: {} fact(s), {} rule(s), {} warning(s)\n", knowledge_base.get_axiomatic_facts().count(), knowledge_base.get_rules().count(), warnings.len()));
+            let records: Vec<Json> = warnings.iter().map(|warning: &LintWarning| {
+                record("warning", warning.location.as_ref().map(SourceLocation::to_string), Some(warning.code), &warning.message)
+            }).collect();
+            (Report::new(text, records), EXIT_TRUE)
+        }
+        Err(error) => {
+            let records: Vec<Json> = error.lines().filter(|line: &&str| !line.starts_with(char::is_whitespace)).map(|line: &str| {
+                let (location, message) = split_location(line);
+                record("error", location.map(str::to_string), None, message)
+            }).collect();
+            (Report::new(format!("Error: {}\n", error), records), EXIT_FALSE)
+        }
+    }
+}
+
+/// The lint warnings of a knowledge base. Imported values keep their case, so a capitalised atom from a data file is
+/// a value, not a misspelt variable, and is not reported.
+fn lint_warnings(knowledge_base: &KnowledgeBase, data: &[DataSource]) -> Vec<LintWarning> {
+    let imported = |warning: &LintWarning| warning.code == "capitalised-constant"
+        && warning.location.as_ref().is_some_and(|location: &SourceLocation| data.iter().any(|source: &DataSource| source.path() == location.file));
+    lint(knowledge_base).into_iter().filter(|warning: &LintWarning| !imported(warning)).collect()
+}
+
+/// Loads the files and reports their lint warnings, failing if there are any.
+fn lint_report(file_paths: &[String], data: &[DataSource]) -> (Report, i32) {
+    let knowledge_base: KnowledgeBase = match load_sources(file_paths, data) {
+        Ok(knowledge_base) => knowledge_base,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            return (Report::new(String::new(), Vec::new()), EXIT_ERROR);
+        }
+    };
+    let warnings: Vec<LintWarning> = lint_warnings(&knowledge_base, data);
+    if warnings.is_empty() {
+        return (Report::new(String::new(), Vec::new()), EXIT_TRUE);
+    }
+    let mut text: String = warnings.iter().map(|warning: &LintWarning| format!("{}\n", warning)).collect();
+    text.push_str(&format!("{} warning(s)\n", warnings.len()));
+    let records: Vec<Json> = warnings.iter().map(|warning: &LintWarning| Json::object(vec![
+        ("location", warning.location.as_ref().map_or(Json::Null, |location: &SourceLocation| Json::string(location.to_string()))),
+        ("code", Json::string(warning.code)),
+        ("message", Json::string(&warning.message))
+    ])).collect();
+    (Report::new(text, records), EXIT_FALSE)
+}
+
+/// The files `fmt` changed, or with `--check` would change, failing if a file could not be formatted or, when
+/// checking, if any would change.
+fn format_report(formatted: &FormattedFiles, check: bool) -> (Report, i32) {
+    for error in &formatted.errors {
+        eprintln!("{}", error);
+    }
+    let text: String = if check { formatted.changed.iter().map(|path: &String| format!("Would reformat {}\n", path)).collect() } else { String::new() };
+    let records: Vec<Json> = formatted.changed.iter().map(|path: &String| Json::object(vec![("path", Json::string(path))])).collect();
+    let status: i32 = if !formatted.errors.is_empty() {
+        EXIT_ERROR
+    } else if check && !formatted.changed.is_empty() {
+        EXIT_FALSE
+    } else {
+        EXIT_TRUE
+    };
+    (Report::new(text, records), status)
+}
+
+/// Splits `path:line[:column]: message` into its location and message.
+fn split_location(line: &str) -> (Option<&str>, &str) {
+    let mut search_from: usize = 0;
+    while let Some(offset) = line[search_from..].find(": ") {
+        let (location, message) = (&line[..search_from + offset], &line[search_from + offset + 2..]);
+        if location.rsplit(':').next().is_some_and(|last: &str| !last.is_empty() && last.chars().all(|ch: char| ch.is_ascii_digit())) {
+            return (Some(location), message);
+        }
+        search_from += offset + 2;
+    }
+    (None, line)
+}
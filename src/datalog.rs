@@ -0,0 +1,400 @@
+use std::{collections::HashSet, path::Path};
+
+use crate::knowledge_base::*;
+
+/// A clause of a Datalog file, the pure Datalog subset of Prolog syntax that knowledge bases are read from and
+/// written to:
+///
+/// ```text
+/// :- type parent(atom, atom).
+/// parent(ama, osei).
+/// grandparent(X, Z) :- parent(X, Y), parent(Y, Z).
+/// only_child(X) :- parent(P, X), \+ has_sibling(X), (age(X, A), A >= 18 ; adult(X)).
+/// ```
+///
+/// Variables start with an uppercase letter or `_` and become PIE variables with the first letter lowercased
+/// (`Person` is `person?`; every `_` is a fresh variable). `,` is `&`, `;` is `|`, `\+` negates a literal, and the
+/// comparisons are `=`, `\=`, `<`, `=<`, `>` and `>=` (`==`, `=:=`, `\==`, `=\=`, `!=` and `<=` are read too) on
+/// variables, numbers and atoms, as in PIE. Numbers are 32-bit integers. Atoms that are not plain lowercase names are quoted, e.g. `'4.5'` or `'hr::employee'`.
+/// Comments are `%` to the end of the line and `/* ... */`. Besides `:- type` declarations, directives are not
+/// supported.
+pub enum Clause {
+    Fact(Fact),
+    Rule(Rule),
+    Type(TypeDeclaration)
+}
+
+/// Whether a file is read as Datalog rather than PIE, going by its extension.
+pub fn is_datalog_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|extension| extension.to_str()), Some("pl" | "dl" | "datalog"))
+}
+
+/// Parses Datalog source into clauses with the line each one starts on.
+pub fn parse(content: &str) -> Result<Vec<(usize, Clause)>, String> {
+    let tokens: Vec<(usize, Token)> = tokenize(content)?;
+    let mut parser: Parser = Parser { tokens, position: 0, anonymous: 0, variables: HashSet::new() };
+    let mut clauses: Vec<(usize, Clause)> = Vec::new();
+    while parser.position < parser.tokens.len() {
+        let line: usize = parser.line();
+        let clause: Clause = parser.clause().map_err(|error| format!("{}: {}", parser.line(), error))?;
+        clauses.push((line, clause));
+    }
+    Ok(clauses)
+}
+
+impl KnowledgeBase {
+    /// Adds the clauses of Datalog source, recording the file and line of each. Rules must be safe, as in PIE files.
+    pub fn load_datalog(&mut self, content: &str, file: &Path) -> Result<(), String> {
+        let clauses: Vec<(usize, Clause)> = parse(content).map_err(|error| format!("{}:{}", file.display(), error))?;
+        for (line, clause) in clauses {
+            let location: SourceLocation = SourceLocation::new(file.to_path_buf(), line);
+            match clause {
+                Clause::Fact(fact) => self.add_axiomatic_fact_from(fact, location),
+                Clause::Rule(rule) => {
+                    rule.check_safety().map_err(|error| format!("{}: Unsafe rule {}: {}", location, rule, error))?;
+                    self.add_axiomatic_rule_from(rule, location);
+                }
+                Clause::Type(declaration) => self.add_type_declaration(declaration).map_err(|error| format!("{}: {}", location, error))?
+            }
+        }
+        Ok(())
+    }
+    /// Writes the type declarations, given facts and rules as Datalog that `load_datalog` reads back. Derived facts
+    /// are left out.
+    pub fn to_datalog(&self) -> String {
+        let mut declarations: Vec<String> = self.get_type_declarations().map(|declaration: &TypeDeclaration| format!(":- type {}.\n", write_fact(&declaration_fact(declaration)))).collect();
+        declarations.sort();
+        let mut facts: Vec<String> = self.get_axiomatic_facts().map(|fact: &Fact| format!("{}.\n", write_fact(fact))).collect();
+        facts.sort();
+        let rules: Vec<String> = self.get_rules().map(|rule: &Rule| format!("{}.\n", write_rule(rule))).collect();
+        [declarations, facts, rules].into_iter().filter(|section: &Vec<String>| !section.is_empty()).map(|section: Vec<String>| section.concat()).collect::<Vec<String>>().join("\n")
+    }
+}
+
+/// A rule as `head :- body`, without the final period.
+pub fn write_rule(rule: &Rule) -> String {
+    let mut stack: Vec<(String, u8)> = Vec::new();
+    for item in &rule.antecedents {
+        let (operator, precedence): (&str, u8) = match item {
+            AntecedentItem::Fact(fact) => {
+                stack.push((write_fact(fact), 3));
+                continue;
+            }
+            AntecedentItem::Or => (";", 0),
+            AntecedentItem::And => (",", 1),
+            AntecedentItem::Equals => ("=", 2),
+            AntecedentItem::NotEquals => ("\\=", 2),
+            AntecedentItem::GreaterThan => (">", 2),
+            AntecedentItem::GreaterOrEquals => (">=", 2),
+            AntecedentItem::LesserThan => ("<", 2),
+            AntecedentItem::LesserOrEquals => ("=<", 2)
+        };
+        let (right, right_precedence) = stack.pop().expect("rules are validated");
+        let (left, left_precedence) = stack.pop().expect("rules are validated");
+        // Operators group to the left, so an equal right operand needs parentheses to keep its shape.
+        let left: String = if left_precedence < precedence { format!("({})", left) } else { left };
+        let right: String = if right_precedence <= precedence { format!("({})", right) } else { right };
+        let text: String = if precedence == 1 { format!("{}, {}", left, right) } else { format!("{} {} {}", left, operator, right) };
+        stack.push((text, precedence));
+    }
+    let (body, _) = stack.pop().expect("rules are validated");
+    format!("{} :- {}", write_fact(&rule.consequent), body)
+}
+
+/// A fact or term in Datalog syntax: `\+` for negation, variables capitalised and unusual atoms quoted.
+pub fn write_fact(fact: &Fact) -> String {
+    match fact {
+        Fact::Number(number) => number.value.to_string(),
+        Fact::Variable(variable) => {
+            let mut characters = variable.name.chars();
+            match characters.next() {
+                Some('_') => variable.name.clone(),
+                Some(first) if first.is_alphabetic() => first.to_uppercase().chain(characters).collect(),
+                _ => format!("V{}", variable.name)
+            }
+        }
+        Fact::Atomic(atomic) => format!("{}{}", if atomic.positive { "" } else { "\\+ " }, write_atom(&atomic.name)),
+        Fact::Predicate(predicate) => {
+            let arguments: Vec<String> = predicate.arguments.iter().map(write_fact).collect();
+            format!("{}{}({})", if predicate.positive { "" } else { "\\+ " }, write_atom(&predicate.name), arguments.join(", "))
+        }
+    }
+}
+
+fn write_atom(name: &str) -> String {
+    let plain: bool = name.starts_with(|ch: char| ch.is_lowercase()) && name.chars().all(|ch: char| ch.is_alphanumeric() || ch == '_');
+    if plain { name.to_string() } else { format!("'{}'", name.replace('\\', "\\\\").replace('\'', "\\'")) }
+}
+
+fn declaration_fact(declaration: &TypeDeclaration) -> Fact {
+    let arguments: Vec<Fact> = declaration.arguments.iter().map(|argument: &ArgumentType| Fact::Atomic(AtomicFact::new(true, argument.to_string()))).collect();
+    if arguments.is_empty() {
+        Fact::Atomic(AtomicFact::new(true, declaration.name.clone()))
+    } else {
+        Fact::Predicate(PredicateFact::new(true, declaration.name.clone(), arguments))
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Atom(String),
+    Variable(String),
+    Number(String),
+    Symbol(&'static str)
+}
+
+/// Longer symbols come first so that e.g. `=<` is not read as `=` followed by `<`.
+const SYMBOLS: [&str; 21] = [":-", "\\+", "=:=", "=\\=", "\\==", "\\=", "==", "=<", ">=", "<=", "!=", "=", "<", ">", "(", ")", ",", ";", ".", "|", "/"];
+
+fn tokenize(content: &str) -> Result<Vec<(usize, Token)>, String> {
+    let characters: Vec<char> = content.chars().collect();
+    let mut tokens: Vec<(usize, Token)> = Vec::new();
+    let mut line: usize = 1;
+    let mut position: usize = 0;
+    let starts_with = |position: usize, text: &str| text.chars().enumerate().all(|(offset, ch)| characters.get(position + offset) == Some(&ch));
+    while let Some(&ch) = characters.get(position) {
+        let start: usize = position;
+        if ch == '\n' {
+            line += 1;
+            position += 1;
+        } else if ch.is_whitespace() {
+            position += 1;
+        } else if ch == '%' {
+            while characters.get(position).is_some_and(|ch: &char| *ch != '\n') { position += 1; }
+        } else if starts_with(position, "/*") {
+            position += 2;
+            while position < characters.len() && !starts_with(position, "*/") {
+                if characters[position] == '\n' { line += 1; }
+                position += 1;
+            }
+            if position >= characters.len() { return Err(format!("{}: Unterminated comment", line)); }
+            position += 2;
+        } else if ch.is_ascii_digit() || (ch == '-' && characters.get(position + 1).is_some_and(char::is_ascii_digit) && !matches!(tokens.last(), Some((_, Token::Atom(_) | Token::Variable(_) | Token::Number(_) | Token::Symbol(")"))))) {
+            position += 1;
+            while characters.get(position).is_some_and(char::is_ascii_digit) { position += 1; }
+            if characters.get(position) == Some(&'.') && characters.get(position + 1).is_some_and(char::is_ascii_digit) {
+                position += 1;
+                while characters.get(position).is_some_and(char::is_ascii_digit) { position += 1; }
+            }
+            tokens.push((line, Token::Number(characters[start..position].iter().collect())));
+        } else if ch.is_alphabetic() || ch == '_' {
+            while characters.get(position).is_some_and(|ch: &char| ch.is_alphanumeric() || *ch == '_') { position += 1; }
+            let name: String = characters[start..position].iter().collect();
+            tokens.push((line, if ch.is_uppercase() || ch == '_' { Token::Variable(name) } else { Token::Atom(name) }));
+        } else if ch == '\'' {
+            position += 1;
+            let mut name: String = String::new();
+            loop {
+                match characters.get(position) {
+                    None | Some('\n') => return Err(format!("{}: Unterminated quoted atom", line)),
+                    Some('\'') if characters.get(position + 1) == Some(&'\'') => {
+                        name.push('\'');
+                        position += 2;
+                    }
+                    Some('\'') => break,
+                    Some('\\') if position + 1 < characters.len() => {
+                        name.push(characters[position + 1]);
+                        position += 2;
+                    }
+                    Some(&ch) => {
+                        name.push(ch);
+                        position += 1;
+                    }
+                }
+            }
+            position += 1;
+            if name.is_empty() || name.contains(|ch: char| ch.is_whitespace() || "(),?!&|[]'\"".contains(ch)) || name.contains("->") {
+                return Err(format!("{}: Atom '{}' cannot be represented in PIE", line, name));
+            }
+            tokens.push((line, Token::Atom(name)));
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol: &&&str| starts_with(position, symbol)) {
+            position += symbol.chars().count();
+            tokens.push((line, Token::Symbol(symbol)));
+        } else {
+            let unsupported: &str = match ch {
+                '[' => "Lists are not supported",
+                '"' => "Strings are not supported",
+                _ => "Unexpected character"
+            };
+            return Err(format!("{}: {} '{}'", line, unsupported, ch));
+        }
+    }
+    Ok(tokens)
+}
+
+/// A rule body before it is turned into postfix antecedents.
+enum Body {
+    Literal(Fact),
+    Comparison(Fact, AntecedentItem, Fact),
+    And(Box<Body>, Box<Body>),
+    Or(Box<Body>, Box<Body>)
+}
+impl Body {
+    fn into_postfix(self, output: &mut Vec<AntecedentItem>) {
+        match self {
+            Body::Literal(fact) => output.push(AntecedentItem::Fact(fact)),
+            Body::Comparison(left, operator, right) => output.extend([AntecedentItem::Fact(left), AntecedentItem::Fact(right), operator]),
+            Body::And(left, right) => {
+                left.into_postfix(output);
+                right.into_postfix(output);
+                output.push(AntecedentItem::And);
+            }
+            Body::Or(left, right) => {
+                left.into_postfix(output);
+                right.into_postfix(output);
+                output.push(AntecedentItem::Or);
+            }
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    anonymous: usize,
+    /// The variables written in the current clause, which the names given to `_` must differ from.
+    variables: HashSet<String>
+}
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens.get(self.position).or(self.tokens.last()).map_or(1, |(line, _)| *line)
+    }
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+    fn next(&mut self) -> Option<Token> {
+        let token: Option<Token> = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+    fn accept(&mut self, symbol: &str) -> bool {
+        if self.peek() == Some(&Token::Symbol(SYMBOLS.iter().find(|candidate: &&&str| **candidate == symbol).expect("known symbol"))) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.accept(symbol) { Ok(()) } else { Err(format!("Expected '{}'", symbol)) }
+    }
+    fn clause(&mut self) -> Result<Clause, String> {
+        self.variables = self.tokens[self.position..].iter().map(|(_, token)| token).take_while(|token: &&Token| **token != Token::Symbol(".")).filter_map(|token: &Token| match token {
+            Token::Variable(name) => Some(variable_name(name)),
+            _ => None
+        }).collect();
+        if self.accept(":-") {
+            if self.next() != Some(Token::Atom(String::from("type"))) {
+                return Err(String::from("Only ':- type' directives are supported"));
+            }
+            let declaration: Fact = self.literal()?;
+            self.expect(".")?;
+            return Ok(Clause::Type(TypeDeclaration::parse(&declaration.to_string())?));
+        }
+        let head: Fact = self.literal()?;
+        if head.is_negative() { return Err(String::from("The head of a clause cannot be negated")); }
+        if self.accept(".") { return Ok(Clause::Fact(head)); }
+        self.expect(":-").map_err(|_| String::from("Expected '.' or ':-'"))?;
+        let body: Body = self.disjunction()?;
+        self.expect(".")?;
+        let mut antecedents: Vec<AntecedentItem> = Vec::new();
+        body.into_postfix(&mut antecedents);
+        Ok(Clause::Rule(Rule::new(antecedents, head).validate()))
+    }
+    fn disjunction(&mut self) -> Result<Body, String> {
+        let mut body: Body = self.conjunction()?;
+        while self.accept(";") || self.accept("|") {
+            body = Body::Or(Box::new(body), Box::new(self.conjunction()?));
+        }
+        Ok(body)
+    }
+    fn conjunction(&mut self) -> Result<Body, String> {
+        let mut body: Body = self.goal()?;
+        while self.accept(",") {
+            body = Body::And(Box::new(body), Box::new(self.goal()?));
+        }
+        Ok(body)
+    }
+    fn goal(&mut self) -> Result<Body, String> {
+        if self.accept("(") {
+            let body: Body = self.disjunction()?;
+            self.expect(")")?;
+            return Ok(body);
+        }
+        if self.accept("\\+") {
+            let mut fact: Fact = self.literal().map_err(|_| String::from("\\+ can only negate a single literal"))?;
+            if fact.is_negative() { return Err(String::from("\\+ can only negate a positive literal")); }
+            fact.negate();
+            return Ok(Body::Literal(fact));
+        }
+        let left: Fact = self.term()?;
+        let operator: Option<AntecedentItem> = match self.peek() {
+            Some(Token::Symbol("=" | "==" | "=:=")) => Some(AntecedentItem::Equals),
+            Some(Token::Symbol("\\=" | "\\==" | "=\\=" | "!=")) => Some(AntecedentItem::NotEquals),
+            Some(Token::Symbol(">")) => Some(AntecedentItem::GreaterThan),
+            Some(Token::Symbol(">=")) => Some(AntecedentItem::GreaterOrEquals),
+            Some(Token::Symbol("<")) => Some(AntecedentItem::LesserThan),
+            Some(Token::Symbol("=<" | "<=")) => Some(AntecedentItem::LesserOrEquals),
+            _ => None
+        };
+        match operator {
+            Some(operator) => {
+                self.position += 1;
+                let right: Fact = self.term()?;
+                for operand in [&left, &right] {
+                    if !matches!(operand, Fact::Variable(_) | Fact::Number(_) | Fact::Atomic(_)) {
+                        return Err(format!("Comparisons need variables, numbers or atoms, found {}", operand));
+                    }
+                }
+                Ok(Body::Comparison(left, operator, right))
+            }
+            None if matches!(left, Fact::Atomic(_) | Fact::Predicate(_)) => Ok(Body::Literal(left)),
+            None if self.peek() == Some(&Token::Atom(String::from("is"))) => Err(String::from("Arithmetic with 'is' is not supported")),
+            None => Err(format!("Expected a literal, found {}", left))
+        }
+    }
+    /// An atom or compound term, optionally negated with `\+`.
+    fn literal(&mut self) -> Result<Fact, String> {
+        if self.accept("\\+") {
+            let mut fact: Fact = self.literal()?;
+            fact.negate();
+            return Ok(fact);
+        }
+        match self.term()? {
+            fact @ (Fact::Atomic(_) | Fact::Predicate(_)) => Ok(fact),
+            other => Err(format!("Expected a literal, found {}", other))
+        }
+    }
+    fn term(&mut self) -> Result<Fact, String> {
+        match self.next() {
+            Some(Token::Variable(name)) if name == "_" => {
+                let name: String = loop {
+                    self.anonymous += 1;
+                    let name: String = format!("_{}", self.anonymous);
+                    if !self.variables.contains(&name) { break name; }
+                };
+                Ok(Fact::Variable(Variable::new(name)))
+            }
+            Some(Token::Variable(name)) => Ok(Fact::Variable(Variable::new(variable_name(&name)))),
+            Some(Token::Number(number)) if number.contains('.') => Err(format!("Decimal numbers are not supported; write '{}' to use it as an atom", number)),
+            Some(Token::Number(number)) => number.parse::<i32>().map(|value: i32| Fact::Number(NumericFact::new(value))).map_err(|_| format!("{} does not fit in a 32-bit integer", number)),
+            Some(Token::Atom(name)) => {
+                if !self.accept("(") { return Ok(Fact::Atomic(AtomicFact::new(true, name))); }
+                let mut arguments: Vec<Fact> = vec![self.term()?];
+                while self.accept(",") {
+                    arguments.push(self.term()?);
+                }
+                self.expect(")")?;
+                Ok(Fact::Predicate(PredicateFact::new(true, name, arguments)))
+            }
+            Some(Token::Symbol(symbol)) => Err(format!("Unexpected '{}'", symbol)),
+            None => Err(String::from("Unexpected end of input, expected a term"))
+        }
+    }
+}
+
+/// The PIE name of a Datalog variable, with the first letter lowercased.
+fn variable_name(name: &str) -> String {
+    let mut characters = name.chars();
+    characters.next().into_iter().flat_map(char::to_lowercase).chain(characters).collect()
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashSet, fs::OpenOptions, io::{BufRead, BufWriter, Write}, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::formatter::format_antecedents;
+use crate::json::Json;
+use crate::knowledge_base::*;
+pub use crate::knowledge_base::Event;
+
+/// One line of an event log: when it happened, who or what made the change, and the change itself.
+#[derive(Clone, PartialEq)]
+pub struct LogEntry {
+    /// UTC in RFC 3339 with milliseconds, e.g. `2026-10-18T09:30:00.000Z`. Every timestamp has the same width, so
+    /// they compare correctly as strings.
+    pub time: String,
+    pub source: String,
+    pub event: Event
+}
+
+/// An append-only log of every change made to a knowledge base, one JSON object per line:
+///
+/// ```text
+/// {"time":"2026-10-18T09:30:00.000Z","source":"alice","event":"assert","fact":"parent(ama, osei)","location":"family.txt:3"}
+/// ```
+///
+/// Entries are buffered and flushed once per batch of changes, such as a loaded file or a pass of inference; given
+/// facts and rules are written before the change is made and derived facts right after. Writing never interrupts
+/// the change itself; the first failure is kept in `error` and later entries are dropped, so callers should check it
+/// before exiting.
+pub struct EventLog {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    source: String,
+    error: Option<String>
+}
+impl EventLog {
+    pub fn new(writer: impl Write + Send + 'static, source: &str) -> Self {
+        EventLog { writer: BufWriter::new(Box::new(writer)), source: source.to_string(), error: None }
+    }
+    /// Opens a log file for appending, creating it if needed. `source` tags every entry, e.g. with a user name.
+    pub fn open(path: &Path, source: &str) -> Result<Self, String> {
+        let file: std::fs::File = OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|error| format!("Failed to open event log '{}': {}", path.display(), error))?;
+        Ok(EventLog::new(file, source))
+    }
+}
+impl ChangeLog for EventLog {
+    fn record(&mut self, event: Event) {
+        if self.error.is_some() { return; }
+        let entry: LogEntry = LogEntry { time: format_time(SystemTime::now()), source: self.source.clone(), event };
+        if let Err(error) = writeln!(self.writer, "{}", entry.to_json()) {
+            self.error = Some(format!("Failed to write event log: {}", error));
+        }
+    }
+    fn flush(&mut self) {
+        if self.error.is_some() { return; }
+        if let Err(error) = self.writer.flush() {
+            self.error = Some(format!("Failed to write event log: {}", error));
+        }
+    }
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+impl KnowledgeBase {
+    /// Starts logging every change to the knowledge base. The log first records a reset followed by the current
+    /// state, so replaying it from any session start rebuilds the knowledge base as it was attached.
+    pub fn attach_log(&mut self, mut event_log: EventLog) {
+        event_log.record(Event::Clear);
+        self.set_event_log(Some(Arc::new(Mutex::new(event_log))));
+        self.record_changes(&KnowledgeBase::new());
+    }
+    /// Records how this knowledge base differs from `previous`, for changes that were made with the log detached,
+    /// e.g. on a copy that was validated before replacing the original. When rules or type declarations were removed,
+    /// which cannot be logged individually, the log records a reset and the whole state instead.
+    pub fn record_changes(&self, previous: &KnowledgeBase) {
+        let Some(event_log) = self.event_log() else { return };
+        let mut event_log = event_log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous_rules: Vec<&Rule> = previous.get_rules().collect();
+        let rules: Vec<&Rule> = self.get_rules().collect();
+        let kept: bool = rules.starts_with(&previous_rules)
+            && previous.get_type_declarations().all(|declaration: &TypeDeclaration| self.get_type_declaration(&declaration.name) == Some(declaration));
+        let empty: KnowledgeBase = KnowledgeBase::new();
+        let previous: &KnowledgeBase = if kept { previous } else {
+            event_log.record(Event::Clear);
+            &empty
+        };
+        let mut declarations: Vec<&TypeDeclaration> = self.get_type_declarations().filter(|declaration: &&TypeDeclaration| previous.get_type_declaration(&declaration.name).is_none()).collect();
+        declarations.sort_by(|left, right| left.name.cmp(&right.name));
+        for declaration in declarations {
+            event_log.record(Event::Declare(declaration.clone()));
+        }
+        let facts = |knowledge_base: &KnowledgeBase| knowledge_base.get_axiomatic_facts().cloned().collect::<HashSet<Fact>>();
+        let changes: FactDiff = diff(&facts(previous), &facts(self));
+        for fact in changes.removed {
+            event_log.record(Event::Retract(fact));
+        }
+        for fact in changes.added {
+            match self.get_fact_sources(&fact) {
+                [] => event_log.record(Event::Assert(fact, None)),
+                sources => for source in sources {
+                    event_log.record(Event::Assert(fact.clone(), Some(source.clone())));
+                }
+            }
+        }
+        for (index, rule) in rules.iter().enumerate().skip(previous.get_rules().count()) {
+            event_log.record(Event::AddRule((*rule).clone(), self.get_rule_source(index).cloned()));
+        }
+        let derived: FactDiff = diff(previous.working_memory(), self.working_memory());
+        for fact in derived.removed {
+            event_log.record(Event::Underive(fact));
+        }
+        for fact in derived.added {
+            event_log.record(Event::Derive(fact));
+        }
+        event_log.flush();
+    }
+}
+
+impl LogEntry {
+    pub fn to_json(&self) -> Json {
+        let mut pairs: Vec<(&str, Json)> = vec![("time", Json::string(&self.time)), ("source", Json::string(&self.source))];
+        let location = |location: &Option<SourceLocation>| location.as_ref().map_or(Json::Null, |location: &SourceLocation| Json::string(location.to_string()));
+        match &self.event {
+            Event::Assert(fact, source) => pairs.extend([("event", Json::string("assert")), ("fact", Json::string(fact.to_string())), ("location", location(source))]),
+            Event::Retract(fact) => pairs.extend([("event", Json::string("retract")), ("fact", Json::string(fact.to_string()))]),
+            Event::AddRule(rule, source) => pairs.extend([
+                ("event", Json::string("rule")),
+                ("antecedents", Json::string(format_antecedents(rule))),
+                ("consequent", Json::string(rule.consequent.to_string())),
+                ("location", location(source))
+            ]),
+            Event::Declare(declaration) => pairs.extend([("event", Json::string("declare")), ("declaration", Json::string(declaration.to_string()))]),
+            Event::Derive(fact) => pairs.extend([("event", Json::string("derive")), ("fact", Json::string(fact.to_string()))]),
+            Event::Underive(fact) => pairs.extend([("event", Json::string("underive")), ("fact", Json::string(fact.to_string()))]),
+            Event::ClearDerived => pairs.push(("event", Json::string("clear_derived"))),
+            Event::Clear => pairs.push(("event", Json::string("clear")))
+        }
+        Json::object(pairs)
+    }
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let field = |name: &str| json.get(name).and_then(Json::as_str).ok_or(format!("Missing field '{}'", name));
+        let fact = |name: &str| field(name).and_then(Fact::parse);
+        let location = || json.get("location").and_then(Json::as_str).map(parse_location).transpose();
+        let event: Event = match field("event")? {
+            "assert" => Event::Assert(fact("fact")?, location()?),
+            "retract" => Event::Retract(fact("fact")?),
+            "rule" => {
+                let rule: Rule = Rule::parse(field("antecedents")?, field("consequent")?)?;
+                rule.check_safety().map_err(|error| format!("Unsafe rule {}: {}", rule, error))?;
+                Event::AddRule(rule, location()?)
+            }
+            "declare" => Event::Declare(TypeDeclaration::parse(field("declaration")?)?),
+            "derive" => Event::Derive(fact("fact")?),
+            "underive" => Event::Underive(fact("fact")?),
+            "clear_derived" => Event::ClearDerived,
+            "clear" => Event::Clear,
+            other => return Err(format!("Unknown event '{}'", other))
+        };
+        Ok(LogEntry { time: field("time")?.to_string(), source: field("source")?.to_string(), event })
+    }
+}
+
+/// Parses a `path:line` location as written by `SourceLocation`'s `Display`.
+fn parse_location(location: &str) -> Result<SourceLocation, String> {
+    let (file, line) = location.rsplit_once(':').ok_or(format!("Invalid location '{}'", location))?;
+    let line: usize = line.parse().map_err(|_| format!("Invalid location '{}'", location))?;
+    Ok(SourceLocation::new(PathBuf::from(file), line))
+}
+
+/// Reads the entries of an event log, reporting malformed lines with their line number.
+pub fn read_log(input: impl BufRead) -> Result<Vec<LogEntry>, String> {
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line: String = line.map_err(|error| format!("Failed to read event log: {}", error))?;
+        if line.trim().is_empty() { continue; }
+        let entry: LogEntry = Json::parse(&line).and_then(|json: Json| LogEntry::from_json(&json)).map_err(|error| format!("line {}: {}", index + 1, error))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Rebuilds a knowledge base by applying log entries in order. Derived facts are restored as they were recorded
+/// rather than inferred again, so replaying part of a log shows exactly what was known at that point.
+pub fn replay<'e>(entries: impl IntoIterator<Item = &'e LogEntry>) -> Result<KnowledgeBase, String> {
+    let mut knowledge_base: KnowledgeBase = KnowledgeBase::new();
+    for entry in entries {
+        match &entry.event {
+            Event::Assert(fact, Some(source)) => knowledge_base.add_axiomatic_fact_from(fact.clone(), source.clone()),
+            Event::Assert(fact, None) => knowledge_base.add_axiomatic_fact(fact.clone()),
+            Event::Retract(fact) => { knowledge_base.remove_axiomatic_fact(fact); }
+            Event::AddRule(rule, Some(source)) => knowledge_base.add_axiomatic_rule_from(rule.clone(), source.clone()),
+            Event::AddRule(rule, None) => knowledge_base.add_axiomatic_rule(rule.clone()),
+            Event::Declare(declaration) => knowledge_base.add_type_declaration(declaration.clone())?,
+            Event::Derive(fact) => knowledge_base.add_fact(fact.clone()),
+            Event::Underive(fact) => knowledge_base.remove_fact(fact),
+            Event::ClearDerived => knowledge_base.clear_working_memory(),
+            Event::Clear => knowledge_base.clear()
+        }
+    }
+    Ok(knowledge_base)
+}
+
+/// Formats a point in time as UTC in RFC 3339 with milliseconds.
+pub fn format_time(time: SystemTime) -> String {
+    let milliseconds: u64 = time.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_millis() as u64);
+    // Days to a civil date, after Howard Hinnant's `civil_from_days`.
+    let days: i64 = (milliseconds / 86_400_000) as i64 + 719_468;
+    let era: i64 = days.div_euclid(146_097);
+    let day_of_era: i64 = days - era * 146_097;
+    let year_of_era: i64 = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month: i64 = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year: i64 = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    let time_of_day: u64 = milliseconds % 86_400_000;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day,
+        time_of_day / 3_600_000, time_of_day / 60_000 % 60, time_of_day / 1000 % 60, time_of_day % 1000)
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use crate::datalog::write_fact;
+use crate::json::Json;
+use crate::knowledge_base::*;
+use crate::output::{csv_field, term_value};
+
+/// The file formats facts can be exported to. Each one can be read back: CSV with `--facts-csv`, JSON with
+/// `--facts-json`, and PIE and Datalog as knowledge base files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Pie,
+    Datalog
+}
+impl ExportFormat {
+    pub fn parse(string: &str) -> Result<Self, String> {
+        match string {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "pie" => Ok(ExportFormat::Pie),
+            "datalog" => Ok(ExportFormat::Datalog),
+            _ => Err(format!("Unknown export format '{}', expected csv, json, pie or datalog", string))
+        }
+    }
+}
+
+/// Facts grouped by predicate and arity, both in sorted order.
+pub struct Export<'k> {
+    groups: BTreeMap<(String, usize), Vec<&'k Fact>>
+}
+impl KnowledgeBase {
+    /// The derived facts, and the given ones too when `include_given` is set, ready to be written out.
+    pub fn export(&self, include_given: bool) -> Export<'_> {
+        let facts: Box<dyn Iterator<Item = &Fact>> = if include_given { Box::new(self.get_facts()) } else { Box::new(self.working_memory().iter()) };
+        let mut groups: BTreeMap<(String, usize), Vec<&Fact>> = BTreeMap::new();
+        for fact in facts {
+            if let Some((name, arity)) = fact.signature() {
+                groups.entry((name.to_string(), arity)).or_default().push(fact);
+            }
+        }
+        for facts in groups.values_mut() {
+            facts.sort_by_cached_key(|fact: &&Fact| fact.to_string());
+        }
+        Export { groups }
+    }
+}
+impl<'k> Export<'k> {
+    /// Keeps only the facts of one predicate, given as `name` or `name/arity`.
+    pub fn only(mut self, predicate: &str) -> Self {
+        self.groups.retain(|(name, arity), _| name == predicate || format!("{}/{}", name, arity) == predicate);
+        self
+    }
+    /// One CSV table per predicate, named after it: a header of `argument1`, `argument2`, ... and a row per fact.
+    /// A predicate that is used with several arities gets the arity in its name, e.g. `edge_2.csv`, and `::` in
+    /// module-qualified names becomes `.`. Facts without arguments cannot be written as rows and are left out.
+    pub fn to_csv_files(&self) -> Vec<(String, String)> {
+        let mut files: Vec<(String, String)> = Vec::new();
+        for ((name, arity), facts) in &self.groups {
+            if *arity == 0 { continue; }
+            let overloaded: bool = self.groups.keys().filter(|(other, _)| other == name).count() > 1;
+            let stem: String = name.replace("::", ".");
+            let file_name: String = if overloaded { format!("{}_{}.csv", stem, arity) } else { format!("{}.csv", stem) };
+            let header: Vec<String> = (1..=*arity).map(|index: usize| format!("argument{}", index)).collect();
+            let mut csv: String = header.join(",") + "\n";
+            for fact in facts {
+                let fields: Vec<String> = arguments(fact).iter().map(|argument: &Fact| csv_field(&argument.to_string())).collect();
+                csv.push_str(&fields.join(","));
+                csv.push('\n');
+            }
+            files.push((file_name, csv));
+        }
+        files
+    }
+    /// An object from predicate name to an array of argument arrays, `{"bonus": [["john"]]}`, the shape
+    /// `--facts-json` reads without a predicate. Numbers stay numbers.
+    pub fn to_json(&self) -> Json {
+        let mut pairs: Vec<(String, Json)> = Vec::new();
+        for ((name, _), facts) in &self.groups {
+            let rows = facts.iter().map(|fact: &&Fact| Json::Array(arguments(fact).iter().map(term_value).collect()));
+            match pairs.last_mut() {
+                Some((previous, Json::Array(existing))) if previous == name => existing.extend(rows),
+                _ => pairs.push((name.clone(), Json::Array(rows.collect())))
+            }
+        }
+        Json::Object(pairs)
+    }
+    /// A knowledge base file with a comment before the facts of each predicate.
+    pub fn to_pie(&self) -> String {
+        let sections: Vec<String> = self.groups.iter().map(|((name, arity), facts)| {
+            let lines: String = facts.iter().map(|fact: &&Fact| format!("{}.\n", fact)).collect();
+            format!("# {}/{}\n{}", name, arity, lines)
+        }).collect();
+        sections.join("\n")
+    }
+    /// A Datalog file with a comment before the facts of each predicate, see `datalog`.
+    pub fn to_datalog(&self) -> String {
+        let sections: Vec<String> = self.groups.iter().map(|((name, arity), facts)| {
+            let lines: String = facts.iter().map(|fact: &&Fact| format!("{}.\n", write_fact(fact))).collect();
+            format!("% {}/{}\n{}", name, arity, lines)
+        }).collect();
+        sections.join("\n")
+    }
+}
+
+fn arguments(fact: &Fact) -> &[Fact] {
+    match fact {
+        Fact::Predicate(predicate) => &predicate.arguments,
+        _ => &[]
+    }
+}
@@ -0,0 +1,307 @@
+use std::{cell::RefCell, ffi::{c_char, c_int, CStr, CString}, panic::{self, AssertUnwindSafe}, path::Path, ptr};
+
+use crate::import::predicate_name;
+use crate::inference_engine::{InferenceEngine, QueryMatch};
+use crate::knowledge_base::*;
+use crate::loader::Loader;
+
+/// Status codes of the C API declared in `include/pie.h`, which also describes who owns which handle. No function
+/// unwinds into C: a panic is caught and reported as `PIE_ERROR_PANIC`.
+pub const PIE_OK: c_int = 0;
+/// `pie_query_next` has no more results.
+pub const PIE_DONE: c_int = 1;
+pub const PIE_ERROR_NULL_ARGUMENT: c_int = -1;
+pub const PIE_ERROR_INVALID_UTF8: c_int = -2;
+/// The text could not be parsed or loaded, e.g. a syntax error, an unsafe rule or a type declaration mismatch.
+pub const PIE_ERROR_INVALID: c_int = -3;
+pub const PIE_ERROR_NOT_FOUND: c_int = -4;
+pub const PIE_ERROR_PANIC: c_int = -5;
+
+/// Where text loaded through the C API is recorded as coming from when no name is given.
+const DEFAULT_SOURCE: &str = "<text>";
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// The matches of a query, walked with `pie_query_next`.
+pub struct PieQuery {
+    matches: Vec<(CString, Vec<(CString, CString)>)>,
+    /// The number of matches handed out so far; the current one is the one before it.
+    position: usize
+}
+
+/// The message of the last call that failed on this thread, or an empty string. Valid until the next failure.
+#[unsafe(no_mangle)]
+pub extern "C" fn pie_last_error() -> *const c_char {
+    LAST_ERROR.with(|error: &RefCell<CString>| error.borrow().as_ptr())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn pie_kb_new() -> *mut KnowledgeBase {
+    Box::into_raw(Box::new(KnowledgeBase::new()))
+}
+
+/// # Safety
+/// `knowledge_base` must be NULL or a handle from `pie_kb_new` that was not given to `pie_engine_new` or freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_kb_free(knowledge_base: *mut KnowledgeBase) {
+    if !knowledge_base.is_null() {
+        drop(unsafe { Box::from_raw(knowledge_base) });
+    }
+}
+
+/// Loads knowledge-base text in PIE syntax, recording `name` (or `<text>` if NULL) as its file. Nothing is added if
+/// any of it fails to load.
+///
+/// # Safety
+/// `knowledge_base` must be a live handle and `text` and `name` NUL-terminated strings or NULL.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_kb_load_text(knowledge_base: *mut KnowledgeBase, text: *const c_char, name: *const c_char) -> c_int {
+    guard(|| {
+        let knowledge_base: &mut KnowledgeBase = unsafe { knowledge_base.as_mut() }.ok_or(null_argument("knowledge_base"))?;
+        let text: &str = unsafe { string(text, "text") }?;
+        let name: &str = if name.is_null() { DEFAULT_SOURCE } else { unsafe { string(name, "name") }? };
+        load(knowledge_base, text, name)
+    })
+}
+
+/// Adds facts from JSON in the shapes `--facts-json` reads, see `KnowledgeBase::import_json`: an array of records of
+/// `predicate`, or with `predicate` NULL an object of predicates with their arrays of records. Nothing is added if
+/// any record is invalid or a fact does not match its type declaration.
+///
+/// # Safety
+/// `knowledge_base` must be a live handle and `json` and `predicate` NUL-terminated strings or NULL.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_kb_import_json(knowledge_base: *mut KnowledgeBase, json: *const c_char, predicate: *const c_char) -> c_int {
+    guard(|| {
+        let knowledge_base: &mut KnowledgeBase = unsafe { knowledge_base.as_mut() }.ok_or(null_argument("knowledge_base"))?;
+        let json: &str = unsafe { string(json, "json") }?;
+        let predicate: Option<String> = if predicate.is_null() { None } else { Some(predicate_name(unsafe { string(predicate, "predicate") }?).map_err(|error| (PIE_ERROR_INVALID, error))?) };
+        let mut imported: KnowledgeBase = knowledge_base.clone();
+        imported.import_json(predicate.as_deref(), json, Path::new(DEFAULT_SOURCE)).map_err(|error| (PIE_ERROR_INVALID, error))?;
+        *knowledge_base = Loader::with_knowledge_base(imported).finish().map_err(|error| (PIE_ERROR_INVALID, error))?;
+        Ok(PIE_OK)
+    })
+}
+
+/// Adds a given fact such as `parent(ama, osei)`, checked against the type declarations.
+///
+/// # Safety
+/// `knowledge_base` must be a live handle and `fact` a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_kb_add_fact(knowledge_base: *mut KnowledgeBase, fact: *const c_char) -> c_int {
+    guard(|| {
+        let knowledge_base: &mut KnowledgeBase = unsafe { knowledge_base.as_mut() }.ok_or(null_argument("knowledge_base"))?;
+        let fact: Fact = unsafe { parse_fact(fact, "fact") }?;
+        if fact.is_negative() {
+            return Err((PIE_ERROR_INVALID, format!("Cannot add negative fact {}, remove {} instead", fact, fact.get_negated())));
+        }
+        load(knowledge_base, &format!("{}.", fact), DEFAULT_SOURCE)
+    })
+}
+
+/// Removes a given fact. The derived facts are cleared, as some may no longer hold; infer again to rebuild them.
+/// Returns `PIE_ERROR_NOT_FOUND` if the fact is not a given fact.
+///
+/// # Safety
+/// `knowledge_base` must be a live handle and `fact` a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_kb_remove_fact(knowledge_base: *mut KnowledgeBase, fact: *const c_char) -> c_int {
+    guard(|| {
+        let knowledge_base: &mut KnowledgeBase = unsafe { knowledge_base.as_mut() }.ok_or(null_argument("knowledge_base"))?;
+        let fact: Fact = unsafe { parse_fact(fact, "fact") }?;
+        if !knowledge_base.remove_axiomatic_fact(&fact) {
+            return Err((PIE_ERROR_NOT_FOUND, format!("{} is not a given fact", fact)));
+        }
+        knowledge_base.clear_working_memory();
+        Ok(PIE_OK)
+    })
+}
+
+/// Creates an engine that takes over the knowledge base. Returns NULL if `knowledge_base` is NULL.
+///
+/// # Safety
+/// `knowledge_base` must be NULL or a live handle from `pie_kb_new`; it must not be used or freed afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_engine_new(knowledge_base: *mut KnowledgeBase) -> *mut InferenceEngine {
+    if knowledge_base.is_null() {
+        set_last_error("knowledge_base is NULL");
+        return ptr::null_mut();
+    }
+    let knowledge_base: KnowledgeBase = *unsafe { Box::from_raw(knowledge_base) };
+    Box::into_raw(Box::new(InferenceEngine::new(knowledge_base)))
+}
+
+/// # Safety
+/// `engine` must be NULL or a live handle from `pie_engine_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_engine_free(engine: *mut InferenceEngine) {
+    if !engine.is_null() {
+        drop(unsafe { Box::from_raw(engine) });
+    }
+}
+
+/// The engine's knowledge base, to load text or add and remove facts after creating the engine. Lent until the
+/// engine is freed; do not free it. Returns NULL if `engine` is NULL.
+///
+/// # Safety
+/// `engine` must be NULL or a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_engine_knowledge_base(engine: *mut InferenceEngine) -> *mut KnowledgeBase {
+    match unsafe { engine.as_mut() } {
+        Some(engine) => &mut engine.knowledge_base,
+        None => ptr::null_mut()
+    }
+}
+
+/// Derives every fact that follows from the knowledge base.
+///
+/// # Safety
+/// `engine` must be a live handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_engine_infer(engine: *mut InferenceEngine) -> c_int {
+    guard(|| {
+        let engine: &mut InferenceEngine = unsafe { engine.as_mut() }.ok_or(null_argument("engine"))?;
+        engine.infer();
+        Ok(PIE_OK)
+    })
+}
+
+/// Proves a goal such as `grandparent(ama, kofi)` by backward chaining, storing the answer in `proven`.
+///
+/// # Safety
+/// `engine` must be a live handle, `goal` a NUL-terminated string and `proven` point to writable memory.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_engine_prove(engine: *mut InferenceEngine, goal: *const c_char, proven: *mut bool) -> c_int {
+    guard(|| {
+        let engine: &mut InferenceEngine = unsafe { engine.as_mut() }.ok_or(null_argument("engine"))?;
+        let proven: &mut bool = unsafe { proven.as_mut() }.ok_or(null_argument("proven"))?;
+        let goal: Fact = unsafe { parse_fact(goal, "goal") }?;
+        *proven = engine.prove(&goal);
+        Ok(PIE_OK)
+    })
+}
+
+/// Finds the known facts matching a pattern such as `parent(x?, osei)` and stores them in a new result set in
+/// `result`. Infer first for the derived facts to be included.
+///
+/// # Safety
+/// `engine` must be a live handle, `pattern` a NUL-terminated string and `result` point to writable memory.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_engine_query(engine: *mut InferenceEngine, pattern: *const c_char, result: *mut *mut PieQuery) -> c_int {
+    guard(|| {
+        let engine: &InferenceEngine = unsafe { engine.as_ref() }.ok_or(null_argument("engine"))?;
+        let result: &mut *mut PieQuery = unsafe { result.as_mut() }.ok_or(null_argument("result"))?;
+        let pattern: Fact = unsafe { parse_fact(pattern, "pattern") }?;
+        let text = |value: String| CString::new(value).unwrap_or_default();
+        let matches: Vec<(CString, Vec<(CString, CString)>)> = engine.query_matches(&pattern).into_iter().map(|query_match: QueryMatch| {
+            let bindings: Vec<(CString, CString)> = query_match.bindings.into_iter().map(|(name, value)| (text(name), text(value.to_string()))).collect();
+            (text(query_match.fact.to_string()), bindings)
+        }).collect();
+        *result = Box::into_raw(Box::new(PieQuery { matches, position: 0 }));
+        Ok(PIE_OK)
+    })
+}
+
+/// Moves to the next match and stores its fact in `fact`. Returns `PIE_DONE` when there are no more.
+///
+/// # Safety
+/// `query` must be a live result set and `fact` point to writable memory.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_query_next(query: *mut PieQuery, fact: *mut *const c_char) -> c_int {
+    guard(|| {
+        let query: &mut PieQuery = unsafe { query.as_mut() }.ok_or(null_argument("query"))?;
+        let fact: &mut *const c_char = unsafe { fact.as_mut() }.ok_or(null_argument("fact"))?;
+        let Some((text, _)) = query.matches.get(query.position) else { return Ok(PIE_DONE); };
+        *fact = text.as_ptr();
+        query.position += 1;
+        Ok(PIE_OK)
+    })
+}
+
+/// The number of variables bound by the current match, 0 before the first `pie_query_next` or if `query` is NULL.
+///
+/// # Safety
+/// `query` must be NULL or a live result set.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_query_binding_count(query: *const PieQuery) -> usize {
+    unsafe { query.as_ref() }.and_then(PieQuery::current).map_or(0, |(_, bindings)| bindings.len())
+}
+
+/// Stores the name (without `?`) and value of a variable bound by the current match.
+///
+/// # Safety
+/// `query` must be a live result set and `name` and `value` point to writable memory.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_query_binding(query: *const PieQuery, index: usize, name: *mut *const c_char, value: *mut *const c_char) -> c_int {
+    guard(|| {
+        let query: &PieQuery = unsafe { query.as_ref() }.ok_or(null_argument("query"))?;
+        let name: &mut *const c_char = unsafe { name.as_mut() }.ok_or(null_argument("name"))?;
+        let value: &mut *const c_char = unsafe { value.as_mut() }.ok_or(null_argument("value"))?;
+        let (_, bindings) = query.current().ok_or((PIE_ERROR_NOT_FOUND, String::from("No current match, call pie_query_next first")))?;
+        let (binding_name, binding_value) = bindings.get(index).ok_or((PIE_ERROR_NOT_FOUND, format!("No binding {}, the match has {}", index, bindings.len())))?;
+        *name = binding_name.as_ptr();
+        *value = binding_value.as_ptr();
+        Ok(PIE_OK)
+    })
+}
+
+/// # Safety
+/// `query` must be NULL or a live result set from `pie_engine_query`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_query_free(query: *mut PieQuery) {
+    if !query.is_null() {
+        drop(unsafe { Box::from_raw(query) });
+    }
+}
+
+impl PieQuery {
+    fn current(&self) -> Option<&(CString, Vec<(CString, CString)>)> {
+        self.position.checked_sub(1).and_then(|index: usize| self.matches.get(index))
+    }
+}
+
+/// Runs the body of an exported function, turning errors and panics into status codes and a last error message.
+fn guard(body: impl FnOnce() -> Result<c_int, (c_int, String)>) -> c_int {
+    let (code, message): (c_int, String) = match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(code)) => return code,
+        Ok(Err(error)) => error,
+        Err(payload) => {
+            let message: &str = payload.downcast_ref::<&str>().copied().or(payload.downcast_ref::<String>().map(String::as_str)).unwrap_or("unknown panic");
+            (PIE_ERROR_PANIC, format!("Internal error: {}", message))
+        }
+    };
+    set_last_error(&message);
+    code
+}
+
+fn set_last_error(message: &str) {
+    LAST_ERROR.with(|error: &RefCell<CString>| *error.borrow_mut() = CString::new(message.replace('\0', " ")).unwrap_or_default());
+}
+
+fn null_argument(name: &str) -> (c_int, String) {
+    (PIE_ERROR_NULL_ARGUMENT, format!("{} is NULL", name))
+}
+
+/// # Safety
+/// `pointer` must be NULL or a NUL-terminated string that outlives `'a`.
+unsafe fn string<'a>(pointer: *const c_char, name: &str) -> Result<&'a str, (c_int, String)> {
+    if pointer.is_null() { return Err(null_argument(name)); }
+    unsafe { CStr::from_ptr(pointer) }.to_str().map_err(|_| (PIE_ERROR_INVALID_UTF8, format!("{} is not valid UTF-8", name)))
+}
+
+/// # Safety
+/// As for `string`.
+unsafe fn parse_fact(pointer: *const c_char, name: &str) -> Result<Fact, (c_int, String)> {
+    let text: &str = unsafe { string(pointer, name) }?;
+    Fact::parse(text.trim().trim_end_matches('.')).map_err(|error| (PIE_ERROR_INVALID, format!("Invalid {} '{}': {}", name, text, error)))
+}
+
+/// Loads text into a copy of the knowledge base and replaces it only if everything loaded.
+fn load(knowledge_base: &mut KnowledgeBase, text: &str, name: &str) -> Result<c_int, (c_int, String)> {
+    let mut loader: Loader = Loader::with_knowledge_base(knowledge_base.clone());
+    loader.load_source(text, Path::new(name)).map_err(|error| (PIE_ERROR_INVALID, error))?;
+    *knowledge_base = loader.finish().map_err(|error| (PIE_ERROR_INVALID, error))?;
+    Ok(PIE_OK)
+}
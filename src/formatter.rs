@@ -0,0 +1,230 @@
+use crate::knowledge_base::*;
+use crate::loader::{split_lines, SourceLine};
+
+/// Rules longer than this are wrapped with one top-level operand per line.
+const MAX_WIDTH: usize = 100;
+
+/// Re-emits a knowledge-base file in the canonical layout:
+/// - comments are kept where they are, runs of blank lines are collapsed to one
+/// - within a block (lines between blank lines, comments and directives) facts are grouped by predicate in order
+///   of first appearance, followed by a blank line and the rules in their original order
+/// - operators are surrounded by single spaces, `[ ]` only appear where precedence requires them and every
+///   statement ends with a period
+/// - rules longer than `MAX_WIDTH` put each top-level operand and the head on a continuation line
+pub fn format_source(content: &str) -> Result<String, String> {
+    let mut output: Vec<String> = Vec::new();
+    let mut block: Vec<BlockItem> = Vec::new();
+    for source_line in split_lines(content) {
+        let text: &str = &source_line.text;
+        if text.is_empty() {
+            flush_block(&mut block, &mut output);
+            push_blank(&mut output);
+        } else if source_line.is_comment() {
+            flush_block(&mut block, &mut output);
+            output.push(text.to_string());
+        } else if is_directive(text) {
+            flush_block(&mut block, &mut output);
+            output.push(format_directive(&source_line)?);
+        } else if let Some((body, head)) = text.split_once("->") {
+            if head.contains("->") {
+                return Err(format!("Line {}: Invalid rule syntax", source_line.line));
+            }
+            let rule: Rule = Rule::parse(body.trim(), head.trim().trim_end_matches('.').trim()).map_err(|error| format!("Line {}: {}", source_line.line, error))?;
+            block.push(BlockItem::Rule(format_rule(&rule)));
+        } else if text.ends_with('.') {
+            let fact: &str = text.trim_end_matches('.').trim();
+            if fact.is_empty() { continue; }
+            let fact: Fact = Fact::parse(fact).map_err(|error| format!("Line {}: {}", source_line.line, error))?;
+            let signature: (String, usize) = fact.signature().map(|(name, arity)| (name.to_string(), arity)).unwrap_or_default();
+            block.push(BlockItem::Fact(signature, format!("{}.", fact)));
+        } else {
+            return Err(format!("Line {}: Invalid syntax - must end with period or contain implication", source_line.line));
+        }
+    }
+    flush_block(&mut block, &mut output);
+    while output.last().is_some_and(String::is_empty) {
+        output.pop();
+    }
+    Ok(output.into_iter().map(|line: String| line + "\n").collect())
+}
+
+/// The outcome of `format_files`: the files that were reformatted, or with `check` would be, and one message for
+/// each file that could not be read, parsed or written.
+pub struct FormattedFiles {
+    pub changed: Vec<String>,
+    pub errors: Vec<String>
+}
+
+/// Formats files in place, or with `check` only finds the ones that are not formatted. A file that fails is reported
+/// and the remaining files are still formatted.
+pub fn format_files(file_paths: &[&str], check: bool) -> FormattedFiles {
+    let mut result: FormattedFiles = FormattedFiles { changed: Vec::new(), errors: Vec::new() };
+    for path in file_paths {
+        let formatted: Result<(String, String), String> = std::fs::read_to_string(path)
+            .map_err(|error| format!("Failed to read file: {}", error))
+            .and_then(|content: String| format_source(&content).map(|formatted: String| (content, formatted)));
+        let (content, formatted) = match formatted {
+            Ok(result) => result,
+            Err(error) => {
+                result.errors.push(format!("Error formatting '{}': {}", path, error));
+                continue;
+            }
+        };
+        if content == formatted { continue; }
+        if !check && let Err(error) = std::fs::write(path, formatted) {
+            result.errors.push(format!("Error writing '{}': {}", path, error));
+            continue;
+        }
+        result.changed.push(path.to_string());
+    }
+    result
+}
+
+/// A whole knowledge base as a PIE file: type declarations, the given facts grouped by predicate, then the rules in
+/// order. Derived facts are left out.
+pub fn format_knowledge_base(knowledge_base: &KnowledgeBase) -> String {
+    let mut declarations: Vec<String> = knowledge_base.get_type_declarations().map(|declaration: &TypeDeclaration| format!(":- type {}.\n", declaration)).collect();
+    declarations.sort();
+    let mut facts: Vec<&Fact> = knowledge_base.get_axiomatic_facts().collect();
+    facts.sort_by_cached_key(|fact: &&Fact| (fact.signature().map(|(name, arity)| (name.to_string(), arity)), fact.to_string()));
+    let facts: Vec<String> = facts.iter().map(|fact: &&Fact| format!("{}.\n", fact)).collect();
+    let rules: Vec<String> = knowledge_base.get_rules().map(|rule: &Rule| format_rule(rule) + "\n").collect();
+    [declarations, facts, rules].into_iter().filter(|section: &Vec<String>| !section.is_empty()).map(|section: Vec<String>| section.concat()).collect::<Vec<String>>().join("\n")
+}
+
+pub fn format_rule(rule: &Rule) -> String {
+    let expression: Expression = Expression::from_postfix(&rule.antecedents);
+    let single_line: String = format!("{} -> {}.", format_antecedents(rule), rule.consequent);
+    if single_line.chars().count() <= MAX_WIDTH { return single_line; }
+    let (operator, operands) = expression.top_level_operands();
+    let mut lines: Vec<String> = Vec::new();
+    for (index, operand) in operands.iter().enumerate() {
+        let rendered: String = operand.render(operator.map_or(0, precedence));
+        match (index, operator) {
+            (0, _) | (_, None) => lines.push(rendered),
+            (_, Some(operator)) => lines.push(format!("    {} {}", operator, rendered))
+        }
+    }
+    lines.push(format!("    -> {}.", rule.consequent));
+    lines.join("\n")
+}
+
+/// The body of a rule on one line, in the syntax `Rule::parse` reads.
+pub fn format_antecedents(rule: &Rule) -> String {
+    Expression::from_postfix(&rule.antecedents).render(0)
+}
+
+/// A formatted statement waiting for the end of its block to be placed.
+enum BlockItem {
+    Fact((String, usize), String),
+    Rule(String)
+}
+
+fn flush_block(block: &mut Vec<BlockItem>, output: &mut Vec<String>) {
+    let mut groups: Vec<(&(String, usize), Vec<&String>)> = Vec::new();
+    let mut rules: Vec<&String> = Vec::new();
+    for item in block.iter() {
+        match item {
+            BlockItem::Fact(signature, fact) => match groups.iter_mut().find(|(existing, _)| *existing == signature) {
+                Some((_, facts)) => facts.push(fact),
+                None => groups.push((signature, vec![fact]))
+            },
+            BlockItem::Rule(rule) => rules.push(rule)
+        }
+    }
+    for (_, facts) in &groups {
+        output.extend(facts.iter().map(|fact: &&String| fact.to_string()));
+    }
+    if !groups.is_empty() && !rules.is_empty() { push_blank(output); }
+    for rule in rules {
+        output.extend(rule.lines().map(str::to_string));
+    }
+    block.clear();
+}
+
+fn push_blank(output: &mut Vec<String>) {
+    if output.last().is_some_and(|line: &String| !line.is_empty()) {
+        output.push(String::new());
+    }
+}
+
+fn is_directive(text: &str) -> bool {
+    ["include ", "module ", "export ", "import ", ":- type "].iter().any(|prefix: &&str| text.starts_with(prefix))
+}
+
+fn format_directive(source_line: &SourceLine) -> Result<String, String> {
+    let (keyword, rest) = source_line.text.split_once(' ').unwrap_or((&source_line.text, ""));
+    let rest: &str = rest.trim().trim_end_matches('.').trim();
+    match keyword {
+        "include" | "module" => Ok(format!("{} {}.", keyword, rest)),
+        "export" | "import" => {
+            let items: Vec<&str> = rest.split(',').map(str::trim).filter(|item: &&str| !item.is_empty()).collect();
+            Ok(format!("{} {}.", keyword, items.join(", ")))
+        }
+        _ => {
+            let declaration: &str = rest.strip_prefix("type").unwrap_or(rest).trim();
+            let declaration: TypeDeclaration = TypeDeclaration::parse(declaration).map_err(|error| format!("Line {}: {}", source_line.line, error))?;
+            Ok(format!(":- type {}.", declaration))
+        }
+    }
+}
+
+fn precedence(operator: &AntecedentItem) -> u8 {
+    match operator {
+        AntecedentItem::Or => 1,
+        AntecedentItem::And => 2,
+        _ => 3
+    }
+}
+
+/// A rule body rebuilt as a tree from its postfix form, so it can be printed with the fewest brackets.
+enum Expression<'r> {
+    Fact(&'r Fact),
+    Operation(&'r AntecedentItem, Box<Expression<'r>>, Box<Expression<'r>>)
+}
+impl<'r> Expression<'r> {
+    fn from_postfix(antecedents: &'r [AntecedentItem]) -> Self {
+        let mut stack: Vec<Expression> = Vec::new();
+        for item in antecedents {
+            match item {
+                AntecedentItem::Fact(fact) => stack.push(Expression::Fact(fact)),
+                _ => {
+                    let right: Expression = stack.pop().expect("validated postfix expression");
+                    let left: Expression = stack.pop().expect("validated postfix expression");
+                    stack.push(Expression::Operation(item, Box::new(left), Box::new(right)));
+                }
+            }
+        }
+        stack.pop().expect("validated postfix expression")
+    }
+    fn render(&self, parent_precedence: u8) -> String {
+        match self {
+            Expression::Fact(fact) => fact.to_string(),
+            Expression::Operation(operator, left, right) => {
+                let own_precedence: u8 = precedence(operator);
+                let rendered: String = format!("{} {} {}", left.render(own_precedence), operator, right.render(own_precedence));
+                if own_precedence < parent_precedence { format!("[{}]", rendered) } else { rendered }
+            }
+        }
+    }
+    /// The operator at the root of the tree and its operands, flattening chains of the same operator.
+    fn top_level_operands(&self) -> (Option<&'r AntecedentItem>, Vec<&Expression<'r>>) {
+        fn collect<'e, 'r>(expression: &'e Expression<'r>, operator: &AntecedentItem, operands: &mut Vec<&'e Expression<'r>>) {
+            match expression {
+                Expression::Operation(own_operator, left, right) if *own_operator == operator => {
+                    collect(left, operator, operands);
+                    collect(right, operator, operands);
+                }
+                _ => operands.push(expression)
+            }
+        }
+        match self {
+            Expression::Operation(operator, _, _) if matches!(operator, AntecedentItem::And | AntecedentItem::Or) => {
+                let mut operands: Vec<&Expression> = Vec::new();
+                collect(self, operator, &mut operands);
+                (Some(*operator), operands)
+            }
+            _ => (None, vec![self])
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::formatter::format_antecedents;
+use crate::inference_engine::{Explanation, Reason};
+use crate::knowledge_base::*;
+
+/// The predicate dependency graph as Graphviz DOT: an edge from every predicate in a rule body to the predicate of
+/// its head. Edges through a negated literal are dashed, red and labelled `not`, which is where a change in one
+/// predicate can make facts of another disappear. Predicates with given facts are boxes, the others ellipses.
+pub fn dependency_graph(knowledge_base: &KnowledgeBase) -> String {
+    let signature = |fact: &Fact| fact.signature().map(|(name, arity)| format!("{}/{}", name, arity));
+    let given: BTreeSet<String> = knowledge_base.get_axiomatic_facts().filter_map(signature).collect();
+    let mut predicates: BTreeSet<String> = given.clone();
+    let mut edges: BTreeSet<(String, String, bool)> = BTreeSet::new();
+    for rule in knowledge_base.get_rules() {
+        let Some(head) = signature(&rule.consequent) else { continue; };
+        for index in rule.literal_indices() {
+            let AntecedentItem::Fact(fact) = &rule.antecedents[index] else { continue; };
+            if let Some(body) = signature(fact) {
+                predicates.insert(body.clone());
+                edges.insert((body, head.clone(), fact.is_negative()));
+            }
+        }
+        predicates.insert(head);
+    }
+    let mut dot: String = String::from("digraph dependencies {\n    rankdir=LR;\n");
+    for predicate in &predicates {
+        let shape: &str = if given.contains(predicate) { "box" } else { "ellipse" };
+        dot.push_str(&format!("    {} [shape={}];\n", quote(predicate), shape));
+    }
+    for (body, head, negative) in &edges {
+        let style: &str = if *negative { " [style=dashed, color=red, label=\"not\"]" } else { "" };
+        dot.push_str(&format!("    {} -> {}{};\n", quote(body), quote(head), style));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The derivation of one or more facts as Graphviz DOT. Facts are nodes (given facts as boxes, derived ones as
+/// ellipses and negations that hold because the fact is not known as dashed boxes) and every rule firing is a diamond
+/// with the rule and its comparisons, with edges from the premises to the firing and from the firing to its
+/// conclusion. A fact used by several firings appears once.
+pub fn derivation_graph(explanations: &[Explanation]) -> String {
+    let mut graph: DerivationGraph = DerivationGraph { dot: String::from("digraph derivation {\n    rankdir=BT;\n"), facts: HashMap::new(), firings: 0 };
+    for explanation in explanations {
+        graph.add(explanation);
+    }
+    graph.dot.push_str("}\n");
+    graph.dot
+}
+
+struct DerivationGraph {
+    dot: String,
+    facts: HashMap<Fact, String>,
+    firings: usize
+}
+impl DerivationGraph {
+    /// Adds the fact and, the first time it is seen, how it was derived. Returns its node.
+    fn add(&mut self, explanation: &Explanation) -> String {
+        if let Some(node) = self.facts.get(&explanation.fact) { return node.clone(); }
+        let node: String = format!("fact{}", self.facts.len());
+        self.facts.insert(explanation.fact.clone(), node.clone());
+        let style: &str = match explanation.reason {
+            Reason::Given => "shape=box",
+            Reason::NotKnown => "shape=box, style=dashed",
+            Reason::Derived { .. } => "shape=ellipse"
+        };
+        self.dot.push_str(&format!("    {} [label={}, {}];\n", node, quote(&explanation.fact.to_string()), style));
+        if let Reason::Derived { rule, premises, comparisons } = &explanation.reason {
+            let firing: String = format!("rule{}", self.firings);
+            self.firings += 1;
+            let mut label: String = format!("{} -> {}", format_antecedents(rule), rule.consequent);
+            for comparison in comparisons {
+                label.push_str(&format!("\n{}", comparison));
+            }
+            self.dot.push_str(&format!("    {} [label={}, shape=diamond];\n", firing, quote(&label)));
+            self.dot.push_str(&format!("    {} -> {};\n", firing, node));
+            for premise in premises {
+                let premise_node: String = self.add(premise);
+                self.dot.push_str(&format!("    {} -> {};\n", premise_node, firing));
+            }
+        }
+        node
+    }
+}
+
+/// A DOT string literal.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
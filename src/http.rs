@@ -0,0 +1,194 @@
+use std::{collections::BTreeMap, io::{BufRead, BufReader, Read, Write}, net::{TcpListener, TcpStream}, sync::{Arc, Mutex}, thread, time::Duration};
+
+use crate::json::Json;
+use crate::server::{self, Server};
+
+/// Requests with a larger body, or a larger request line and headers, are refused.
+const MAX_BODY_LENGTH: usize = 16 * 1024 * 1024;
+pub const MAX_HEAD_LENGTH: usize = 64 * 1024;
+/// How long a connection may wait to send more of its request before it is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A REST API over HTTP/1.1 for one or more named knowledge bases, each kept loaded as a `Server`. Every response
+/// is JSON, errors as `{"error": "..."}`:
+///
+/// - `GET /kb`: the names of the knowledge bases with their numbers of given and derived facts and rules
+/// - `GET /kb/<name>/facts[?predicate=parent]`: the given and derived facts, optionally of one predicate
+/// - `POST /kb/<name>/facts` with `{"facts": ["parent(ama, osei)"]}`: adds facts, returning the derived facts that
+///   were added and removed
+/// - `GET /kb/<name>/query?q=parent(x?, osei)`: the matching facts with their variable bindings
+/// - `GET /kb/<name>/explain?fact=grandparent(ama, kofi)`: how a fact, or every fact matching a pattern, was derived
+///
+/// Each connection carries one request. Requests to the same knowledge base are handled one at a time, and a request
+/// that panics gets a 500 response.
+pub struct HttpServer {
+    knowledge_bases: BTreeMap<String, Mutex<Server>>
+}
+/// A parsed request: the method, the path split into segments, the decoded query parameters and the body.
+pub struct Request {
+    pub method: String,
+    pub path: Vec<String>,
+    pub query: Vec<(String, String)>,
+    pub body: String
+}
+impl Default for HttpServer {
+    fn default() -> Self {
+        HttpServer::new()
+    }
+}
+impl HttpServer {
+    pub fn new() -> Self {
+        HttpServer { knowledge_bases: BTreeMap::new() }
+    }
+    pub fn add(&mut self, name: &str, server: Server) {
+        self.knowledge_bases.insert(name.to_string(), Mutex::new(server));
+    }
+    /// Answers a request with a status code and a JSON body.
+    pub fn handle(&self, request: &Request) -> (u16, Json) {
+        let path: Vec<&str> = request.path.iter().map(String::as_str).collect();
+        let parameter = |name: &str| request.query.iter().find(|(key, _)| key == name).map(|(_, value)| Json::string(value));
+        let (name, method, params): (&str, &str, Json) = match (request.method.as_str(), path.as_slice()) {
+            ("GET", ["kb"]) => return (200, self.list()),
+            ("GET", ["kb", name, "facts"]) => (name, "facts", Json::object(parameter("predicate").map(|predicate: Json| ("predicate", predicate)).into_iter().collect())),
+            ("POST", ["kb", name, "facts"]) => match Json::parse(&request.body) {
+                Ok(body) => (name, "assert", body),
+                Err(error) => return (400, error_body(&format!("Invalid JSON body: {}", error)))
+            },
+            ("GET", ["kb", name, "query"]) => (name, "query", Json::object(parameter("q").map(|pattern: Json| ("pattern", pattern)).into_iter().collect())),
+            ("GET", ["kb", name, "explain"]) => (name, "explain", Json::object(parameter("fact").map(|fact: Json| ("fact", fact)).into_iter().collect())),
+            (_, ["kb"] | ["kb", _, "facts" | "query" | "explain"]) => return (405, error_body(&format!("Method {} is not allowed here", request.method))),
+            _ => return (404, error_body("Not found"))
+        };
+        let Some(server) = self.knowledge_bases.get(name) else {
+            return (404, error_body(&format!("No knowledge base named '{}'", name)));
+        };
+        let result: Result<Json, (f64, String)> = server.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).call_caught(method, &params);
+        match result {
+            Ok(result) => (200, result),
+            Err((code, message)) if code == server::INTERNAL_ERROR => (500, error_body(&message)),
+            Err((code, message)) if code == server::REQUEST_FAILED => (422, error_body(&message)),
+            Err((_, message)) => (400, error_body(&message))
+        }
+    }
+    fn list(&self) -> Json {
+        Json::Array(self.knowledge_bases.iter().map(|(name, server)| {
+            let mut pairs: Vec<(String, Json)> = server.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).counts();
+            pairs.insert(0, (String::from("name"), Json::string(name)));
+            Json::Object(pairs)
+        }).collect())
+    }
+}
+
+/// Accepts connections on 127.0.0.1 at `port` (0 picks a free one) and serves each on its own thread. The address
+/// is printed to stderr once listening.
+pub fn listen(server: HttpServer, port: u16) -> Result<(), String> {
+    let listener: TcpListener = TcpListener::bind(("127.0.0.1", port)).map_err(|error| format!("Failed to listen on port {}: {}", port, error))?;
+    if let Ok(address) = listener.local_addr() {
+        eprintln!("Listening on http://{}", address);
+    }
+    serve_connections(Arc::new(server), listener)
+}
+
+/// Serves every connection to `listener` until accepting fails.
+pub fn serve_connections(server: Arc<HttpServer>, listener: TcpListener) -> Result<(), String> {
+    for stream in listener.incoming() {
+        let stream: TcpStream = stream.map_err(|error| format!("Failed to accept connection: {}", error))?;
+        let server: Arc<HttpServer> = Arc::clone(&server);
+        thread::spawn(move || {
+            if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() { return; }
+            let Ok(mut output) = stream.try_clone() else { return; };
+            let (status, body): (u16, Json) = match read_request(&mut BufReader::new(stream)) {
+                Ok(request) => server.handle(&request),
+                Err(error) => (400, error_body(&error))
+            };
+            let _ = write_response(&mut output, status, &body);
+        });
+    }
+    Ok(())
+}
+
+/// Reads the request line, headers and a body of `Content-Length` bytes.
+pub fn read_request(input: &mut impl BufRead) -> Result<Request, String> {
+    let mut remaining: usize = MAX_HEAD_LENGTH;
+    let line: String = read_head_line(input, &mut remaining)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(String::from("Invalid request line"));
+    };
+    let mut content_length: usize = 0;
+    loop {
+        let header: String = read_head_line(input, &mut remaining)?;
+        if header.is_empty() { break; }
+        let header: &str = header.trim_end();
+        if header.is_empty() { break; }
+        if let Some((name, value)) = header.split_once(':') && name.eq_ignore_ascii_case("content-length") {
+            content_length = value.trim().parse().map_err(|_| String::from("Invalid Content-Length"))?;
+        }
+    }
+    if content_length > MAX_BODY_LENGTH {
+        return Err(format!("Request body is larger than {} bytes", MAX_BODY_LENGTH));
+    }
+    let mut body: Vec<u8> = vec![0; content_length];
+    input.read_exact(&mut body).map_err(|error| format!("Failed to read request body: {}", error))?;
+    let (path, query): (&str, &str) = target.split_once('?').unwrap_or((target, ""));
+    Ok(Request {
+        method: method.to_string(),
+        path: path.split('/').filter(|segment: &&str| !segment.is_empty()).map(percent_decode).collect(),
+        query: query.split('&').filter(|pair: &&str| !pair.is_empty()).map(|pair: &str| {
+            let (key, value): (&str, &str) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        }).collect(),
+        body: String::from_utf8_lossy(&body).into_owned()
+    })
+}
+
+/// Reads a line of the request line and headers, which together may take up at most `MAX_HEAD_LENGTH` bytes.
+/// Returns an empty string at the end of the input.
+fn read_head_line(input: &mut impl BufRead, remaining: &mut usize) -> Result<String, String> {
+    let mut line: String = String::new();
+    let length: usize = input.by_ref().take(*remaining as u64).read_line(&mut line).map_err(|error| format!("Failed to read request: {}", error))?;
+    *remaining -= length;
+    if *remaining == 0 && !line.ends_with('\n') {
+        return Err(format!("Request head is larger than {} bytes", MAX_HEAD_LENGTH));
+    }
+    Ok(line)
+}
+
+fn write_response(output: &mut impl Write, status: u16, body: &Json) -> std::io::Result<()> {
+    let reason: &str = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        422 => "Unprocessable Content",
+        500 => "Internal Server Error",
+        _ => "Error"
+    };
+    let body: String = format!("{}\n", body);
+    write!(output, "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, reason, body.len(), body)?;
+    output.flush()
+}
+
+fn error_body(message: &str) -> Json {
+    Json::object(vec![("error", Json::string(message))])
+}
+
+/// Decodes `%XX` escapes and `+` as a space, as in query strings. Invalid escapes are kept as they are.
+fn percent_decode(text: &str) -> String {
+    let bytes: &[u8] = text.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut index: usize = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => decoded.push(b' '),
+            b'%' if let Some(hex) = text.get(index + 1..index + 3) && hex.bytes().all(|byte: u8| byte.is_ascii_hexdigit()) => {
+                let byte: u8 = u8::from_str_radix(hex, 16).expect("two hex digits");
+                decoded.push(byte);
+                index += 2;
+            }
+            byte => decoded.push(byte)
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
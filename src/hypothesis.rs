@@ -0,0 +1,56 @@
+use std::collections::HashSet;
+
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::*;
+
+/// A child context for hypothetical reasoning: a copy-on-write snapshot of an engine's knowledge base with extra
+/// assumptions. Inference and proofs run against the snapshot only, so the parent is never changed; `diff` shows
+/// what the assumptions changed and dropping the hypothesis (or calling `discard`) throws it away.
+pub struct Hypothesis<'p> {
+    parent: &'p KnowledgeBase,
+    pub inference_engine: InferenceEngine
+}
+impl InferenceEngine {
+    /// Starts a hypothesis on top of the current knowledge base. A positive assumption adds a fact, a negative one
+    /// such as `!detected(zergling_1)` removes the given fact. Nothing that happens in the hypothesis is written
+    /// to the parent's event log.
+    pub fn assume(&self, assumptions: impl IntoIterator<Item = Fact>) -> Hypothesis<'_> {
+        let mut knowledge_base: KnowledgeBase = self.knowledge_base.clone();
+        knowledge_base.set_event_log(None);
+        let mut hypothesis: Hypothesis = Hypothesis { parent: &self.knowledge_base, inference_engine: InferenceEngine::new(knowledge_base) };
+        for assumption in assumptions {
+            hypothesis.assume(assumption);
+        }
+        hypothesis
+    }
+}
+impl<'p> Hypothesis<'p> {
+    pub fn assume(&mut self, mut assumption: Fact) {
+        if assumption.is_negative() {
+            assumption.negate();
+            self.inference_engine.knowledge_base.remove_axiomatic_fact(&assumption);
+        } else {
+            self.inference_engine.knowledge_base.add_axiomatic_fact(assumption);
+        }
+    }
+    /// Derives everything that follows from the parent's facts and the assumptions. Working memory is rebuilt from
+    /// scratch, as an assumption can invalidate facts the parent derived through negation.
+    pub fn infer(&mut self) {
+        self.inference_engine.knowledge_base.clear_working_memory();
+        self.inference_engine.infer();
+    }
+    pub fn prove(&mut self, fact: &Fact) -> bool {
+        self.inference_engine.prove(fact)
+    }
+    /// The facts that hold in the hypothesis but not in the parent (`added`) and the other way around (`removed`).
+    pub fn diff(&self) -> FactDiff {
+        let facts = |knowledge_base: &KnowledgeBase| knowledge_base.get_facts().cloned().collect::<HashSet<Fact>>();
+        diff(&facts(self.parent), &facts(&self.inference_engine.knowledge_base))
+    }
+    /// Throws the hypothesis away; the parent is unaffected either way.
+    pub fn discard(self) {}
+    /// Keeps the hypothetical knowledge base, e.g. to make it the new state.
+    pub fn into_knowledge_base(self) -> KnowledgeBase {
+        self.inference_engine.knowledge_base
+    }
+}
@@ -0,0 +1,200 @@
+use std::path::{Path, PathBuf};
+
+use crate::json::Json;
+use crate::knowledge_base::*;
+
+/// A data file to read facts from, given on the command line as `--facts-csv employee=path.csv` or
+/// `--facts-json [employee=]path.json`.
+#[derive(Clone)]
+pub enum DataSource {
+    Csv { predicate: String, path: PathBuf },
+    Json { predicate: Option<String>, path: PathBuf }
+}
+impl DataSource {
+    pub fn parse_csv(argument: &str) -> Result<Self, String> {
+        let (predicate, path) = argument.split_once('=').ok_or(format!("Expected predicate=path, e.g. employee=employees.csv, found '{}'", argument))?;
+        Ok(DataSource::Csv { predicate: predicate_name(predicate)?, path: PathBuf::from(path) })
+    }
+    pub fn parse_json(argument: &str) -> Result<Self, String> {
+        Ok(match argument.split_once('=') {
+            Some((predicate, path)) => DataSource::Json { predicate: Some(predicate_name(predicate)?), path: PathBuf::from(path) },
+            None => DataSource::Json { predicate: None, path: PathBuf::from(argument) }
+        })
+    }
+    pub fn path(&self) -> &Path {
+        match self {
+            DataSource::Csv { path, .. } | DataSource::Json { path, .. } => path
+        }
+    }
+}
+
+impl KnowledgeBase {
+    /// Adds one fact of `predicate` per row of a CSV file, with the columns as arguments in order. The first row is
+    /// the header and is skipped; every other row must have the same number of columns. Each fact records the file
+    /// and line of its row. Returns the number of rows read. The facts are not type checked here; load them through
+    /// `Loader::load_data` for that.
+    pub fn import_csv(&mut self, predicate: &str, content: &str, file: &Path) -> Result<usize, String> {
+        let records: Vec<(usize, Vec<String>)> = parse_csv(content).map_err(|error| format!("{}:{}", file.display(), error))?;
+        let Some(((_, header), rows)) = records.split_first() else { return Ok(0); };
+        for (line, fields) in rows {
+            let location: SourceLocation = SourceLocation::new(file.to_path_buf(), *line);
+            if fields.len() != header.len() {
+                return Err(format!("{}: Expected {} column(s) like the header, found {}", location, header.len(), fields.len()));
+            }
+            let arguments: Vec<Fact> = fields.iter().map(|field: &String| value_fact(field)).collect::<Result<_, String>>().map_err(|error| format!("{}: {}", location, error))?;
+            self.add_axiomatic_fact_from(Fact::Predicate(PredicateFact::new(true, predicate.to_string(), arguments)), location);
+        }
+        Ok(rows.len())
+    }
+    /// Adds facts from JSON in one of these shapes, returning the number of facts read:
+    /// - an array of arrays, `[["john", "engineering", 50000]]`, each inner array being the arguments of a fact;
+    ///   an empty array gives a fact without arguments
+    /// - an array of objects, `[{"name": "john", "salary": 50000}]`, with the values as arguments in the key order
+    ///   of the first object; every object must have the same keys
+    /// - an object of predicates, `{"employee": [...], "manager": [...]}`, whose arrays are read as above
+    ///
+    /// The first two need `predicate`, the last must not have one. JSON has no useful line numbers, so the location
+    /// recorded for each fact is its position in its array instead.
+    pub fn import_json(&mut self, predicate: Option<&str>, content: &str, file: &Path) -> Result<usize, String> {
+        let json: Json = Json::parse(content).map_err(|error| format!("{}: {}", file.display(), error))?;
+        match (predicate, &json) {
+            (Some(predicate), Json::Array(records)) => self.import_json_records(predicate, records, file),
+            (None, Json::Object(predicates)) => {
+                let mut count: usize = 0;
+                for (predicate, records) in predicates {
+                    let Json::Array(records) = records else {
+                        return Err(format!("{}: Expected an array of records for '{}'", file.display(), predicate));
+                    };
+                    count += self.import_json_records(&predicate_name(predicate)?, records, file)?;
+                }
+                Ok(count)
+            }
+            (Some(_), _) => Err(format!("{}: Expected an array of records", file.display())),
+            (None, _) => Err(format!("{}: Expected an object of predicates, or give the predicate as predicate=path", file.display()))
+        }
+    }
+    fn import_json_records(&mut self, predicate: &str, records: &[Json], file: &Path) -> Result<usize, String> {
+        let keys: Option<Vec<&String>> = match records.first() {
+            Some(Json::Object(pairs)) => Some(pairs.iter().map(|(key, _)| key).collect()),
+            _ => None
+        };
+        for (index, record) in records.iter().enumerate() {
+            let location: SourceLocation = SourceLocation::new(file.to_path_buf(), index + 1);
+            let values: Vec<&Json> = match (record, &keys) {
+                (Json::Array(values), None) => values.iter().collect(),
+                (Json::Object(pairs), Some(keys)) => {
+                    if pairs.len() != keys.len() {
+                        return Err(format!("{}: Expected the keys {} like the first record", location, keys.iter().map(|key: &&String| key.as_str()).collect::<Vec<&str>>().join(", ")));
+                    }
+                    keys.iter().map(|key: &&String| record.get(key).ok_or(format!("{}: Missing key '{}'", location, key))).collect::<Result<_, String>>()?
+                }
+                _ => return Err(format!("{}: Expected every record to be {} like the first", location, if keys.is_some() { "an object" } else { "an array" }))
+            };
+            let arguments: Vec<Fact> = values.into_iter().map(json_fact).collect::<Result<_, String>>().map_err(|error| format!("{}: {}", location, error))?;
+            let fact: Fact = if arguments.is_empty() {
+                Fact::Atomic(AtomicFact::new(true, predicate.to_string()))
+            } else {
+                Fact::Predicate(PredicateFact::new(true, predicate.to_string(), arguments))
+            };
+            self.add_axiomatic_fact_from(fact, location);
+        }
+        Ok(records.len())
+    }
+}
+
+/// Turns a data value into an argument. Integers become numbers, and like the numbers of a knowledge base file they
+/// must fit in an `i32`. Decimals such as `4.5` become atoms, as they are in a knowledge base file. Anything else
+/// becomes an atom with its case kept and runs of characters that cannot appear in an atom replaced by `_`, so
+/// `Human Resources` becomes `Human_Resources`. Empty values are an error rather than being skipped, since every
+/// argument of a fact needs a value.
+pub fn value_fact(value: &str) -> Result<Fact, String> {
+    let value: &str = value.trim();
+    if value.is_empty() {
+        return Err(String::from("Empty value; every argument of a fact needs one"));
+    }
+    let digits = |text: &str| !text.is_empty() && text.chars().all(|ch: char| ch.is_ascii_digit());
+    let unsigned: &str = value.strip_prefix(['-', '+']).unwrap_or(value);
+    if digits(unsigned) {
+        return value.parse::<i32>().map(|number: i32| Fact::Number(NumericFact::new(number))).map_err(|_| format!("{} does not fit in a 32-bit integer", value));
+    }
+    if let Some((whole, fraction)) = unsigned.split_once('.') && digits(whole) && digits(fraction) {
+        return Ok(Fact::Atomic(AtomicFact::new(true, value.to_string())));
+    }
+    let mut name: String = String::new();
+    for ch in value.chars() {
+        if ch.is_alphanumeric() || ch == '_' || ch == '.' {
+            name.push(ch);
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name: &str = name.trim_matches('_');
+    if name.is_empty() {
+        return Err(format!("Cannot turn '{}' into an atom", value));
+    }
+    Ok(Fact::Atomic(AtomicFact::new(true, name.to_string())))
+}
+
+fn json_fact(value: &Json) -> Result<Fact, String> {
+    match value {
+        Json::String(string) => value_fact(string),
+        Json::Number(_) | Json::Bool(_) => value_fact(&value.to_string()),
+        _ => Err(format!("Expected a string, number or boolean, found {}", value))
+    }
+}
+
+/// Checks that `name` can be used as a predicate name.
+pub fn predicate_name(name: &str) -> Result<String, String> {
+    match Fact::parse(name.trim()) {
+        Ok(Fact::Atomic(atomic)) if atomic.positive && !atomic.name.is_empty() && !atomic.name.contains(char::is_whitespace) => Ok(atomic.name),
+        _ => Err(format!("Invalid predicate name '{}'", name))
+    }
+}
+
+/// Splits CSV into records of fields with the line each record starts on. Fields may be quoted with `"`, doubling
+/// quotes inside, and quoted fields may span lines. Blank lines are skipped.
+pub fn parse_csv(content: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut fields: Vec<String> = Vec::new();
+    let mut field: String = String::new();
+    let mut line: usize = 1;
+    let mut start: usize = 1;
+    let mut quoted: bool = false;
+    let mut characters = content.chars().peekable();
+    while let Some(ch) = characters.next() {
+        match ch {
+            '"' if quoted && characters.peek() == Some(&'"') => {
+                characters.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            '\r' if !quoted && characters.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                fields.push(std::mem::take(&mut field));
+                if fields.len() > 1 || !fields[0].trim().is_empty() {
+                    records.push((start, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line += 1;
+                start = line;
+            }
+            ch => {
+                if ch == '\n' { line += 1; }
+                field.push(ch);
+            }
+        }
+    }
+    if quoted {
+        return Err(format!("{}: Unterminated quoted field", start));
+    }
+    fields.push(field);
+    if fields.len() > 1 || !fields[0].trim().is_empty() {
+        records.push((start, fields));
+    }
+    Ok(records)
+}
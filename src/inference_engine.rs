@@ -1,16 +1,106 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, fmt, mem, panic::{self, AssertUnwindSafe}};
 
 use crate::knowledge_base::*;
+use crate::trace::{PrettyTracer, TraceEvent, Tracer};
 
-/// The inference engine operates under the closed-world assumption, meaning that any fact not present in the knowledge base is considered false.
+/// A known fact matching a query, and the values its variables were bound to, in order of appearance.
+pub struct QueryMatch {
+    pub fact: Fact,
+    pub bindings: Vec<(String, Fact)>
+}
+
+/// Why a fact holds; see `InferenceEngine::explain`.
+#[derive(Clone)]
+pub struct Explanation {
+    pub fact: Fact,
+    pub reason: Reason
+}
+#[derive(Clone)]
+pub enum Reason {
+    /// The fact is one of the axiomatic facts.
+    Given,
+    /// The fact is a negation and the positive fact is not known (closed-world assumption).
+    NotKnown,
+    /// The fact is the consequent of a rule whose body holds with the premises below.
+    Derived { rule: Rule, premises: Vec<Explanation>, comparisons: Vec<String> }
+}
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_tree(f: &mut fmt::Formatter<'_>, explanation: &Explanation, depth: usize) -> fmt::Result {
+            let indentation: String = "  ".repeat(depth);
+            match &explanation.reason {
+                Reason::Given => writeln!(f, "{}{} (given)", indentation, explanation.fact),
+                Reason::NotKnown => writeln!(f, "{}{} (not known)", indentation, explanation.fact),
+                Reason::Derived { rule, premises, comparisons, .. } => {
+                    writeln!(f, "{}{} by {}", indentation, explanation.fact, rule)?;
+                    for premise in premises {
+                        write_tree(f, premise, depth + 1)?;
+                    }
+                    for comparison in comparisons {
+                        writeln!(f, "{}  {}", indentation, comparison)?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+        write_tree(f, self, 0)
+    }
+}
 
-pub struct InferenceEngine { pub knowledge_base: KnowledgeBase, debug: bool }
+/// The inference engine operates under the closed-world assumption, meaning that any fact not present in the knowledge base is considered false.
+pub struct InferenceEngine { pub knowledge_base: KnowledgeBase, tracer: RefCell<Option<Box<dyn Tracer>>> }
 impl InferenceEngine {
     pub fn new(knowledge_base: KnowledgeBase) -> Self {
-        InferenceEngine { knowledge_base, debug: false }
+        InferenceEngine { knowledge_base, tracer: RefCell::new(None) }
+    }
+    /// Reports the steps of `infer`, `prove` and `change` to `tracer`, or stops reporting them with `None`. Returns the
+    /// previous tracer, e.g. to hand it on to another engine.
+    pub fn set_tracer(&mut self, tracer: Option<Box<dyn Tracer>>) -> Option<Box<dyn Tracer>> {
+        mem::replace(self.tracer.get_mut(), tracer)
     }
+    /// Prints the steps of inference to stdout, or stops printing them.
+    #[deprecated(note = "use set_tracer with a PrettyTracer, or a tracer of your own")]
     pub fn set_debug(&mut self, debug: bool) {
-        self.debug = debug;
+        self.set_tracer(debug.then(|| Box::new(PrettyTracer::new(std::io::stdout())) as Box<dyn Tracer>));
+    }
+    fn trace(&self, event: TraceEvent) {
+        if let Some(tracer) = self.tracer.borrow_mut().as_mut() {
+            tracer.event(&event);
+        }
+    }
+    /// Replaces the knowledge base with a changed one and re-runs inference from scratch, returning the derived facts
+    /// that appeared or disappeared. If the change fails or panics the knowledge base is left as it was. The change is
+    /// made with the event log detached, which then records the difference, so a failed change leaves no trace in it.
+    pub fn change(&mut self, apply: impl FnOnce(KnowledgeBase) -> Result<KnowledgeBase, String>) -> Result<FactDiff, String> {
+        let event_log = self.knowledge_base.set_event_log(None);
+        let backup: KnowledgeBase = self.knowledge_base.clone();
+        self.knowledge_base.clear_working_memory();
+        let knowledge_base: KnowledgeBase = mem::take(&mut self.knowledge_base);
+        match panic::catch_unwind(AssertUnwindSafe(|| apply(knowledge_base))) {
+            Ok(Ok(knowledge_base)) => self.knowledge_base = knowledge_base,
+            Ok(Err(error)) => {
+                self.knowledge_base = backup;
+                self.knowledge_base.set_event_log(event_log);
+                return Err(error);
+            }
+            Err(payload) => {
+                self.knowledge_base = backup;
+                self.knowledge_base.set_event_log(event_log);
+                panic::resume_unwind(payload);
+            }
+        }
+        // Only the given facts and rules are compared here; the derived facts are logged as inference replaces them.
+        let mut given: KnowledgeBase = backup.clone();
+        given.clear_working_memory();
+        self.knowledge_base.set_event_log(event_log);
+        self.knowledge_base.record_changes(&given);
+        self.knowledge_base.clear_working_memory();
+        self.infer();
+        let changes: FactDiff = diff(backup.working_memory(), self.knowledge_base.working_memory());
+        for fact in &changes.removed {
+            self.trace(TraceEvent::FactRemoved { fact });
+        }
+        Ok(changes)
     }
     pub fn infer(&mut self) {
         let mut changed: bool = true;
@@ -18,120 +108,64 @@ impl InferenceEngine {
             changed = false;
             let mut newly_inferred: Vec<Fact> = Vec::new();
             for rule in self.knowledge_base.get_rules() {
-                let mut antecedents: Vec<Fact> = Vec::new();
-                for item in &rule.antecedents {
-                    if let AntecedentItem::Fact(fact) = item {
-                        if let Fact::Variable(_) | Fact::Number(_) = fact { continue; }
-                        antecedents.push(fact.clone());
-                    }
-                }
+                self.trace(TraceEvent::RuleConsidered { rule });
+                let antecedents: Vec<Fact> = rule.literals();
                 let valid_substitutions: Vec<HashMap<String, Fact>> = self.find_valid_substitutions(&antecedents, 0, &HashMap::new());
+                let substitutions: usize = valid_substitutions.len();
                 for valid_substitution in valid_substitutions {
                     let all_antecedents_satisfied: bool = self.evaluate_antecedents(
                         &rule.antecedents,
                         &mut |antecedent: &Fact| {
                             let substituted_antecedent: Fact = self.apply_substitution(antecedent, &valid_substitution);
                             if self.knowledge_base.has_fact(&substituted_antecedent) { return true; }
-                            if substituted_antecedent.is_negative() {
-                                if !self.knowledge_base.has_fact(&substituted_antecedent.get_negated()) {
-                                    return true;
-                                }
+                            if substituted_antecedent.is_negative() && !self.knowledge_base.has_fact(&substituted_antecedent.get_negated()) {
+                                return true;
                             }
-                            return false;
+                            false
                         },
-                        &mut |operator: &AntecedentItem, left: &Fact, right: &Fact| {
+                        &|operator: &AntecedentItem, left: &Fact, right: &Fact| {
                             let substituted_left: &Fact = &self.apply_substitution(left, &valid_substitution);
                             let substituted_right: &Fact = &self.apply_substitution(right, &valid_substitution);
-                            match operator {
-                                AntecedentItem::Equals => substituted_left == substituted_right,
-                                AntecedentItem::NotEquals => substituted_left != substituted_right,
-                                AntecedentItem::GreaterThan => {
-                                    let left_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_left {
-                                        value
-                                    } else {
-                                        panic!("Not a number")
-                                    };
-                                    let right_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_right {
-                                        value
-                                    } else {
-                                        panic!("Not a number")
-                                    };
-                                    left_number > right_number
-                                }
-                                AntecedentItem::GreaterOrEquals => {
-                                    let left_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_left {
-                                        value
-                                    } else {
-                                        panic!("Not a number")
-                                    };
-                                    let right_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_right {
-                                        value
-                                    } else {
-                                        panic!("Not a number")
-                                    };
-                                    left_number >= right_number
-                                }
-                                AntecedentItem::LesserThan => {
-                                    let left_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_left {
-                                        value
-                                    } else {
-                                        panic!("Not a number")
-                                    };
-                                    let right_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_right {
-                                        value
-                                    } else {
-                                        panic!("Not a number")
-                                    };
-                                    left_number < right_number
-                                }
-                                AntecedentItem::LesserOrEquals => {
-                                    let left_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_left {
-                                        value
-                                    } else {
-                                        panic!("Not a number")
-                                    };
-                                    let right_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_right {
-                                        value
-                                    } else {
-                                        panic!("Not a number")
-                                    };
-                                    left_number <= right_number
-                                }
-                                _ => unreachable!()
-                            }
+                            Self::compare(operator, substituted_left, substituted_right)
                         }
                     );
                     if all_antecedents_satisfied {
                         let new_fact: Fact = self.apply_substitution(&rule.consequent, &valid_substitution);
+                        self.trace(TraceEvent::ActivationFired { rule, fact: &new_fact });
                         if !self.knowledge_base.has_fact(&new_fact) && !newly_inferred.contains(&new_fact) {
-                            if self.debug { println!("Inferred new fact: {}", new_fact); }
                             newly_inferred.push(new_fact);
                             changed = true;
                         }
                     }
                 }
+                self.trace(TraceEvent::RuleFinished { rule, substitutions });
+            }
+            for fact in &newly_inferred {
+                self.trace(TraceEvent::FactAdded { fact });
             }
-            for fact in newly_inferred { self.knowledge_base.add_fact(fact); }
+            self.knowledge_base.add_facts(newly_inferred);
         }
     }
     pub fn prove(&mut self, fact: &Fact) -> bool {
-        fn process(engine: &InferenceEngine, fact: &Fact, proven_facts: &mut Vec<Fact>) -> bool {
+        fn process(engine: &InferenceEngine, fact: &Fact, proven_facts: &mut Vec<Fact>, depth: usize) -> bool {
+            engine.trace(TraceEvent::GoalEntered { goal: fact, depth });
+            let proven: bool = search(engine, fact, proven_facts, depth);
+            engine.trace(TraceEvent::GoalExited { goal: fact, depth, proven });
+            proven
+        }
+        fn search(engine: &InferenceEngine, fact: &Fact, proven_facts: &mut Vec<Fact>, depth: usize) -> bool {
             if engine.knowledge_base.has_fact(fact) { return true; }
-            if fact.is_negative() {
-                if !engine.knowledge_base.has_fact(&fact.get_negated()) {
-                    return true;
-                }
+            if fact.is_negative() && !engine.knowledge_base.has_fact(&fact.get_negated()) {
+                return true;
             }
             for rule in engine.knowledge_base.get_rules() {
-                if engine.debug { println!("unify consequent: {} U {}", rule.consequent, fact); }
-                if let Some(consequent_substitution) = engine.unify(&rule.consequent, fact) {
-                    let mut antecedents: Vec<Fact> = Vec::new();
-                    for item in &rule.antecedents {
-                        if let AntecedentItem::Fact(fact) = item {
-                            if let Fact::Variable(_) | Fact::Number(_) = fact { continue; }
-                            antecedents.push(fact.clone());
-                        }
-                    }
+                engine.trace(TraceEvent::RuleConsidered { rule });
+                let consequent_substitution: Option<HashMap<String, Fact>> = engine.unify(&rule.consequent, fact);
+                engine.trace(TraceEvent::UnificationAttempted { pattern: &rule.consequent, fact, unified: consequent_substitution.is_some() });
+                let mut substitutions: usize = 0;
+                let mut proven: bool = false;
+                if let Some(consequent_substitution) = consequent_substitution {
+                    let antecedents: Vec<Fact> = rule.literals();
                     /*let mut antecedents: Vec<Fact> = Vec::new();
                     for antecedent in unproven_antecedents {
                         if !process(engine, &antecedent, proven_facts) {
@@ -141,104 +175,153 @@ impl InferenceEngine {
                     //antecedents could be unproven
                     let valid_substitutions: Vec<HashMap<String, Fact>> = engine.find_valid_substitutions(&antecedents, 0, &consequent_substitution);
                     for valid_substitution in valid_substitutions {
+                        substitutions += 1;
                         let all_antecedents_proven: bool = engine.evaluate_antecedents(
                             &rule.antecedents,
                             &mut |antecedent: &Fact| {
-                                process(engine, &engine.apply_substitution(antecedent, &valid_substitution), proven_facts)
+                                process(engine, &engine.apply_substitution(antecedent, &valid_substitution), proven_facts, depth + 1)
                             },
-                            &mut |operator: &AntecedentItem, left: &Fact, right: &Fact| {
+                            &|operator: &AntecedentItem, left: &Fact, right: &Fact| {
                                 let substituted_left: &Fact = &engine.apply_substitution(left, &valid_substitution);
                                 let substituted_right: &Fact = &engine.apply_substitution(right, &valid_substitution);
-                                match operator {
-                                    AntecedentItem::Equals => substituted_left == substituted_right,
-                                    AntecedentItem::NotEquals => substituted_left != substituted_right,
-                                    AntecedentItem::GreaterThan => {
-                                        let left_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_left {
-                                            value
-                                        } else {
-                                            panic!("Not a number")
-                                        };
-                                        let right_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_right {
-                                            value
-                                        } else {
-                                            panic!("Not a number")
-                                        };
-                                        left_number > right_number
-                                    }
-                                    AntecedentItem::GreaterOrEquals => {
-                                        let left_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_left {
-                                            value
-                                        } else {
-                                            panic!("Not a number")
-                                        };
-                                        let right_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_right {
-                                            value
-                                        } else {
-                                            panic!("Not a number")
-                                        };
-                                        left_number >= right_number
-                                    }
-                                    AntecedentItem::LesserThan => {
-                                        let left_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_left {
-                                            value
-                                        } else {
-                                            panic!("Not a number")
-                                        };
-                                        let right_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_right {
-                                            value
-                                        } else {
-                                            panic!("Not a number")
-                                        };
-                                        left_number < right_number
-                                    }
-                                    AntecedentItem::LesserOrEquals => {
-                                        let left_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_left {
-                                            value
-                                        } else {
-                                            panic!("Not a number")
-                                        };
-                                        let right_number: &i32 = if let Fact::Number(NumericFact { value }) = substituted_right {
-                                            value
-                                        } else {
-                                            panic!("Not a number")
-                                        };
-                                        left_number <= right_number
-                                    }
-                                    _ => unreachable!()
-                                }
+                                InferenceEngine::compare(operator, substituted_left, substituted_right)
                             }
                         );
                         if all_antecedents_proven {
+                            engine.trace(TraceEvent::ActivationFired { rule, fact });
                             proven_facts.push(fact.clone());
-                            return true;
+                            proven = true;
+                            break;
                         }
                     }
                 }
+                engine.trace(TraceEvent::RuleFinished { rule, substitutions });
+                if proven { return true; }
             }
             false
         }
         let mut proven_facts: Vec<Fact> = Vec::new();
-        if process(self, fact, &mut proven_facts) {
+        if process(self, fact, &mut proven_facts, 0) {
+            let mut new_facts: Vec<Fact> = Vec::new();
             for fact in proven_facts {
-                if self.debug { println!("Proved new fact: {}", fact); }
-                self.knowledge_base.add_fact(fact);
+                if !self.knowledge_base.has_fact(&fact) && !new_facts.contains(&fact) {
+                    self.trace(TraceEvent::FactAdded { fact: &fact });
+                    new_facts.push(fact);
+                }
             }
+            self.knowledge_base.add_facts(new_facts);
             true
         } else {
             false
         }
     }
     pub fn query(&self, query: &Fact) -> String {
-        let mut output: Vec<String> = Vec::new();
+        let output: Vec<String> = self.query_matches(query).iter().map(|query_match: &QueryMatch| query_match.fact.to_string()).collect();
+        if query.is_negative() {
+            if output.is_empty() { String::from("Yes") } else { output.join(",\n") }
+        } else {
+            if output.is_empty() { String::from("No") } else { output.join(",\n") }
+        }
+    }
+    /// The known facts matching a query, sorted by their text, with the values bound to the query's variables.
+    pub fn query_matches(&self, query: &Fact) -> Vec<QueryMatch> {
+        let mut variables: Vec<&Variable> = query.variables();
+        variables.dedup_by(|variable: &mut &Variable, previous: &mut &Variable| variable.name == previous.name);
+        let mut matches: Vec<QueryMatch> = Vec::new();
         for fact in self.knowledge_base.get_facts() {
             if let Some(substitution) = self.unify(query, fact) {
-                output.push(self.apply_substitution(query, &substitution).to_string());
+                let bindings: Vec<(String, Fact)> = variables.iter().filter_map(|variable: &&Variable| substitution.get(&variable.name).map(|value: &Fact| (variable.name.clone(), value.clone()))).collect();
+                matches.push(QueryMatch { fact: self.apply_substitution(query, &substitution), bindings });
             }
         }
-        if query.is_negative() {
-            if output.is_empty() { String::from("Yes") } else { output.join(",\n") }
+        matches.sort_by_cached_key(|query_match: &QueryMatch| query_match.fact.to_string());
+        matches.dedup_by(|query_match: &mut QueryMatch, previous: &mut QueryMatch| query_match.fact == previous.fact);
+        matches
+    }
+    /// Infers, then explains the goal, or every fact matching it when it has variables.
+    pub fn explain_matches(&mut self, goal: &Fact) -> Vec<Explanation> {
+        self.infer();
+        let facts: Vec<Fact> = if goal.variables().is_empty() {
+            vec![goal.clone()]
         } else {
-            if output.is_empty() { String::from("No") } else { output.join(",\n") }
+            self.query_matches(goal).into_iter().map(|query_match: QueryMatch| query_match.fact).collect()
+        };
+        facts.iter().filter_map(|fact: &Fact| self.explain(fact)).collect()
+    }
+    /// Explains why a fact holds: it was given, it is a negation of a fact that is not known, or it follows from a
+    /// rule whose premises are explained in turn. Derived facts must already be in working memory, so run `infer`
+    /// first. Returns `None` if the fact does not hold.
+    pub fn explain(&self, fact: &Fact) -> Option<Explanation> {
+        // Explaining only retraces inference that already happened, so it is not reported to the tracer.
+        let tracer: Option<Box<dyn Tracer>> = self.tracer.take();
+        let explanation: Option<Explanation> = self.explain_fact(fact, &mut Vec::new());
+        self.tracer.replace(tracer);
+        explanation
+    }
+    fn explain_fact(&self, fact: &Fact, in_progress: &mut Vec<Fact>) -> Option<Explanation> {
+        if self.knowledge_base.get_axiomatic_facts().any(|axiom: &Fact| axiom == fact) {
+            return Some(Explanation { fact: fact.clone(), reason: Reason::Given });
+        }
+        if fact.is_negative() && !self.knowledge_base.has_fact(&fact.get_negated()) {
+            return Some(Explanation { fact: fact.clone(), reason: Reason::NotKnown });
+        }
+        if !self.knowledge_base.has_fact(fact) || in_progress.contains(fact) { return None; }
+        in_progress.push(fact.clone());
+        let mut explanation: Option<Explanation> = None;
+        'rules: for rule in self.knowledge_base.get_rules() {
+            let Some(consequent_substitution) = self.unify(&rule.consequent, fact) else { continue; };
+            let antecedents: Vec<Fact> = rule.literals();
+            for valid_substitution in self.find_valid_substitutions(&antecedents, 0, &consequent_substitution) {
+                let mut premises: Vec<Explanation> = Vec::new();
+                let holds: bool = self.evaluate_antecedents(
+                    &rule.antecedents,
+                    &mut |antecedent: &Fact| {
+                        match self.explain_fact(&self.apply_substitution(antecedent, &valid_substitution), in_progress) {
+                            Some(premise) => {
+                                premises.push(premise);
+                                true
+                            }
+                            None => false
+                        }
+                    },
+                    &|operator: &AntecedentItem, left: &Fact, right: &Fact| {
+                        InferenceEngine::compare(operator, &self.apply_substitution(left, &valid_substitution), &self.apply_substitution(right, &valid_substitution))
+                    }
+                );
+                if holds {
+                    let comparisons: Vec<String> = rule.antecedents.iter().enumerate().filter_map(|(index, item)| match (item, index.checked_sub(2)) {
+                        (AntecedentItem::Fact(_) | AntecedentItem::And | AntecedentItem::Or, _) | (_, None) => None,
+                        (operator, Some(left_index)) => match (&rule.antecedents[left_index], &rule.antecedents[left_index + 1]) {
+                            (AntecedentItem::Fact(left), AntecedentItem::Fact(right)) => {
+                                let (left, right) = (self.apply_substitution(left, &valid_substitution), self.apply_substitution(right, &valid_substitution));
+                                InferenceEngine::compare(operator, &left, &right).then(|| format!("{} {} {}", left, operator, right))
+                            }
+                            _ => None
+                        }
+                    }).collect();
+                    explanation = Some(Explanation { fact: fact.clone(), reason: Reason::Derived { rule: rule.clone(), premises, comparisons } });
+                    break 'rules;
+                }
+            }
+        }
+        in_progress.pop();
+        explanation
+    }
+    /// Evaluates a comparison between two ground terms. Only numbers are ordered, so an ordering comparison with
+    /// anything else, such as `old > 18`, does not hold.
+    fn compare(operator: &AntecedentItem, left: &Fact, right: &Fact) -> bool {
+        let ordering: Option<std::cmp::Ordering> = match (left, right) {
+            (Fact::Number(NumericFact { value: left }), Fact::Number(NumericFact { value: right })) => Some(left.cmp(right)),
+            _ => None
+        };
+        match operator {
+            AntecedentItem::Equals => left == right,
+            AntecedentItem::NotEquals => left != right,
+            AntecedentItem::GreaterThan => ordering.is_some_and(std::cmp::Ordering::is_gt),
+            AntecedentItem::GreaterOrEquals => ordering.is_some_and(std::cmp::Ordering::is_ge),
+            AntecedentItem::LesserThan => ordering.is_some_and(std::cmp::Ordering::is_lt),
+            AntecedentItem::LesserOrEquals => ordering.is_some_and(std::cmp::Ordering::is_le),
+            _ => unreachable!()
         }
     }
     fn evaluate_antecedents(&self, antecedents: &Vec<AntecedentItem>, fact_evaluator: &mut impl FnMut(&Fact) -> bool, operation_evaluator: &impl Fn(&AntecedentItem, &Fact, &Fact) -> bool) -> bool {
@@ -252,7 +335,7 @@ impl InferenceEngine {
                     let right: StackItem = stack.pop().unwrap();
                     match stack.pop().unwrap() {
                         StackItem::Fact(fact) => {
-                            if !fact_evaluator(&fact) {
+                            if !fact_evaluator(fact) {
                                 stack.push(StackItem::Value(false));
                                 continue;
                             }
@@ -265,7 +348,7 @@ impl InferenceEngine {
                         }
                     }
                     match right {
-                        StackItem::Fact(fact) => stack.push(StackItem::Value(fact_evaluator(&fact))),
+                        StackItem::Fact(fact) => stack.push(StackItem::Value(fact_evaluator(fact))),
                         StackItem::Value(value) => stack.push(StackItem::Value(value))
                     }
                 }
@@ -273,7 +356,7 @@ impl InferenceEngine {
                     let right: StackItem = stack.pop().unwrap();
                     match stack.pop().unwrap() {
                         StackItem::Fact(fact) => {
-                            if fact_evaluator(&fact) {
+                            if fact_evaluator(fact) {
                                 stack.push(StackItem::Value(true));
                                 continue;
                             }
@@ -286,20 +369,18 @@ impl InferenceEngine {
                         }
                     }
                     match right {
-                        StackItem::Fact(fact) => stack.push(StackItem::Value(fact_evaluator(&fact))),
+                        StackItem::Fact(fact) => stack.push(StackItem::Value(fact_evaluator(fact))),
                         StackItem::Value(value) => stack.push(StackItem::Value(value))
                     }
                 }
                 AntecedentItem::Equals | AntecedentItem::NotEquals | AntecedentItem::GreaterThan | AntecedentItem::GreaterOrEquals | AntecedentItem::LesserThan | AntecedentItem::LesserOrEquals => {
-                    let right: &Fact = match stack.pop().unwrap() {
-                        StackItem::Fact(fact) => fact,
-                        _ => panic!("cannot compair")
-                    };
-                    let left: &Fact = match stack.pop().unwrap() {
-                        StackItem::Fact(fact) => fact,
-                        _ => panic!("cannot compair")
+                    let right: StackItem = stack.pop().unwrap();
+                    let value: bool = match (stack.pop().unwrap(), right) {
+                        (StackItem::Fact(left), StackItem::Fact(right)) => operation_evaluator(item, left, right),
+                        // Only terms can be compared, not the truth of a sub-expression.
+                        _ => false
                     };
-                    stack.push(StackItem::Value(operation_evaluator(item, left, right)));
+                    stack.push(StackItem::Value(value));
                 }
             }
         }
@@ -324,12 +405,12 @@ impl InferenceEngine {
         }
         let mut valid_substitutions: Vec<HashMap<String, Fact>> = Vec::new();
         for fact in self.knowledge_base.get_facts() {
-            if self.debug { println!("unify antecedent: {} U {}", antecedent, fact); }
-            if let Some(unified_substitution) = self.unify(antecedent, fact) {
-                if let Some(combined_substitution) = self.combine_substitutions(&current_substitution, unified_substitution) {
-                    let further_substitutions: Vec<HashMap<String, Fact>> = self.find_valid_substitutions(antecedents, index + 1, &combined_substitution);
-                    valid_substitutions.extend(further_substitutions);
-                }
+            let unified_substitution: Option<HashMap<String, Fact>> = self.unify(antecedent, fact);
+            self.trace(TraceEvent::UnificationAttempted { pattern: antecedent, fact, unified: unified_substitution.is_some() });
+            if let Some(unified_substitution) = unified_substitution
+                && let Some(combined_substitution) = self.combine_substitutions(current_substitution, unified_substitution) {
+                let further_substitutions: Vec<HashMap<String, Fact>> = self.find_valid_substitutions(antecedents, index + 1, &combined_substitution);
+                valid_substitutions.extend(further_substitutions);
             }
         }
         valid_substitutions
@@ -0,0 +1,254 @@
+use std::fmt;
+
+/// A minimal JSON value with a parser and a compact writer, so that JSON can be read and written without a dependency.
+/// Objects keep their keys in insertion order so that output is deterministic.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+impl Json {
+    pub fn object(pairs: Vec<(&str, Json)>) -> Self {
+        Json::Object(pairs.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+    pub fn string(value: impl Into<String>) -> Self {
+        Json::String(value.into())
+    }
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(pairs) => pairs.iter().find(|(existing, _)| existing == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+    /// Follows a path of object keys, e.g. `["params", "textDocument", "uri"]`.
+    pub fn get_path(&self, path: &[&str]) -> Option<&Json> {
+        path.iter().try_fold(self, |value: &Json, key: &&str| value.get(key))
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        if let Json::String(value) = self { Some(value) } else { None }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Number(value) if value.fract() == 0.0 => Some(*value as i64),
+            _ => None
+        }
+    }
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        if let Json::Array(values) = self { Some(values) } else { None }
+    }
+    pub fn parse(string: &str) -> Result<Self, String> {
+        let mut parser: Parser = Parser { characters: string.chars().collect(), position: 0, depth: 0 };
+        let value: Json = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.position < parser.characters.len() {
+            return Err(format!("Unexpected trailing characters at position {}", parser.position));
+        }
+        Ok(value)
+    }
+}
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => write!(f, "{}", *value as i64),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => write_escaped(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 { write!(f, ",")?; }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(pairs) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in pairs.iter().enumerate() {
+                    if index > 0 { write!(f, ",")?; }
+                    write_escaped(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_escaped(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in string.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?
+        }
+    }
+    write!(f, "\"")
+}
+
+/// How deeply arrays and objects may nest, so that parsing a hostile input cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser {
+    characters: Vec<char>,
+    position: usize,
+    depth: usize
+}
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.characters.get(self.position).is_some_and(|ch: &char| ch.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.characters.get(self.position) == Some(&expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{}' at position {}", expected, self.position))
+        }
+    }
+    fn parse_literal(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        let end: usize = self.position + literal.chars().count();
+        if end <= self.characters.len() && self.characters[self.position..end].iter().copied().eq(literal.chars()) {
+            self.position = end;
+            Ok(value)
+        } else {
+            Err(format!("Invalid literal at position {}", self.position))
+        }
+    }
+    /// Steps into an array or object.
+    fn enter(&mut self) -> Result<(), String> {
+        self.position += 1;
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(String::from("JSON nested too deeply"));
+        }
+        Ok(())
+    }
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.characters.get(self.position) {
+            None => Err(String::from("Unexpected end of input")),
+            Some('n') => self.parse_literal("null", Json::Null),
+            Some('t') => self.parse_literal("true", Json::Bool(true)),
+            Some('f') => self.parse_literal("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.parse_string()?)),
+            Some('[') => {
+                self.enter()?;
+                let mut values: Vec<Json> = Vec::new();
+                self.skip_whitespace();
+                if self.characters.get(self.position) == Some(&']') {
+                    self.position += 1;
+                    self.depth -= 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.parse_value()?);
+                    self.skip_whitespace();
+                    match self.characters.get(self.position) {
+                        Some(',') => self.position += 1,
+                        Some(']') => {
+                            self.position += 1;
+                            self.depth -= 1;
+                            return Ok(Json::Array(values));
+                        }
+                        _ => return Err(format!("Expected ',' or ']' at position {}", self.position))
+                    }
+                }
+            }
+            Some('{') => {
+                self.enter()?;
+                let mut pairs: Vec<(String, Json)> = Vec::new();
+                self.skip_whitespace();
+                if self.characters.get(self.position) == Some(&'}') {
+                    self.position += 1;
+                    self.depth -= 1;
+                    return Ok(Json::Object(pairs));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key: String = self.parse_string()?;
+                    self.expect(':')?;
+                    pairs.push((key, self.parse_value()?));
+                    self.skip_whitespace();
+                    match self.characters.get(self.position) {
+                        Some(',') => self.position += 1,
+                        Some('}') => {
+                            self.position += 1;
+                            self.depth -= 1;
+                            return Ok(Json::Object(pairs));
+                        }
+                        _ => return Err(format!("Expected ',' or '}}' at position {}", self.position))
+                    }
+                }
+            }
+            Some(_) => self.parse_number()
+        }
+    }
+    fn parse_string(&mut self) -> Result<String, String> {
+        if self.characters.get(self.position) != Some(&'"') {
+            return Err(format!("Expected string at position {}", self.position));
+        }
+        self.position += 1;
+        let mut string: String = String::new();
+        loop {
+            let Some(ch) = self.characters.get(self.position).copied() else {
+                return Err(String::from("Unterminated string"));
+            };
+            self.position += 1;
+            match ch {
+                '"' => return Ok(string),
+                '\\' => {
+                    let Some(escaped) = self.characters.get(self.position).copied() else {
+                        return Err(String::from("Unterminated string"));
+                    };
+                    self.position += 1;
+                    match escaped {
+                        'n' => string.push('\n'),
+                        'r' => string.push('\r'),
+                        't' => string.push('\t'),
+                        'b' => string.push('\u{8}'),
+                        'f' => string.push('\u{c}'),
+                        'u' => {
+                            let mut code: u32 = self.parse_hex()?;
+                            if (0xD800..0xDC00).contains(&code) && self.characters.get(self.position) == Some(&'\\') && self.characters.get(self.position + 1) == Some(&'u') {
+                                self.position += 2;
+                                let low: u32 = self.parse_hex()?;
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                            }
+                            string.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                        }
+                        other => string.push(other)
+                    }
+                }
+                ch => string.push(ch)
+            }
+        }
+    }
+    fn parse_hex(&mut self) -> Result<u32, String> {
+        let end: usize = self.position + 4;
+        if end > self.characters.len() { return Err(String::from("Invalid unicode escape")); }
+        let digits: String = self.characters[self.position..end].iter().collect();
+        self.position = end;
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid unicode escape '{}'", digits))
+    }
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let start: usize = self.position;
+        while self.characters.get(self.position).is_some_and(|ch: &char| ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.' | 'e' | 'E')) {
+            self.position += 1;
+        }
+        let number: String = self.characters[start..self.position].iter().collect();
+        number.parse::<f64>().map(Json::Number).map_err(|_| format!("Invalid number '{}' at position {}", number, start))
+    }
+}
@@ -1,36 +1,129 @@
-use std::{collections::HashSet, fmt};
+use std::{collections::{HashMap, HashSet}, fmt, path::PathBuf, sync::{Arc, Mutex}};
 
+/// Every part is shared copy-on-write, so cloning a knowledge base is cheap and a clone only copies the parts it
+/// changes. This is what makes snapshots for hypothetical reasoning affordable, see `InferenceEngine::assume`.
+#[derive(Clone)]
 pub struct KnowledgeBase {
-    axiomatic_facts: HashSet<Fact>,
-    pub working_memory: HashSet<Fact>,
-    axiomatic_rules: Vec<Rule>,
+    axiomatic_facts: Arc<HashSet<Fact>>,
+    working_memory: Arc<HashSet<Fact>>,
+    axiomatic_rules: Arc<Vec<Rule>>,
     //derived_rules: Vec<Rule>
+    fact_sources: Arc<HashMap<Fact, Vec<SourceLocation>>>,
+    rule_sources: Arc<Vec<Option<SourceLocation>>>,
+    type_declarations: Arc<HashMap<String, TypeDeclaration>>,
+    /// Shared between clones, so that the knowledge base a transaction or the REPL swaps in keeps logging.
+    event_log: Option<Arc<Mutex<dyn ChangeLog>>>
+}
+impl Default for KnowledgeBase {
+    fn default() -> Self {
+        KnowledgeBase::new()
+    }
 }
 impl KnowledgeBase {
     pub fn new() -> Self {
         KnowledgeBase {
-            axiomatic_facts: HashSet::new(),
-            working_memory: HashSet::new(),
-            axiomatic_rules: Vec::new()
+            axiomatic_facts: Arc::default(),
+            working_memory: Arc::default(),
+            axiomatic_rules: Arc::default(),
+            fact_sources: Arc::default(),
+            rule_sources: Arc::default(),
+            type_declarations: Arc::default(),
+            event_log: None
         }
     }
     pub fn add_axiomatic_fact(&mut self, fact: Fact) {
-        self.axiomatic_facts.insert(fact);
+        self.log(|| Event::Assert(fact.clone(), None));
+        Arc::make_mut(&mut self.axiomatic_facts).insert(fact);
     }
     pub fn add_axiomatic_rule(&mut self, rule: Rule) {
-        self.axiomatic_rules.push(rule);
+        self.log(|| Event::AddRule(rule.clone(), None));
+        Arc::make_mut(&mut self.axiomatic_rules).push(rule);
+        Arc::make_mut(&mut self.rule_sources).push(None);
+    }
+    /// Adds a fact and records where it was declared. Every declaration of a duplicated fact is kept.
+    pub fn add_axiomatic_fact_from(&mut self, fact: Fact, source: SourceLocation) {
+        self.log(|| Event::Assert(fact.clone(), Some(source.clone())));
+        Arc::make_mut(&mut self.fact_sources).entry(fact.clone()).or_default().push(source);
+        Arc::make_mut(&mut self.axiomatic_facts).insert(fact);
+    }
+    pub fn add_axiomatic_rule_from(&mut self, rule: Rule, source: SourceLocation) {
+        self.log(|| Event::AddRule(rule.clone(), Some(source.clone())));
+        Arc::make_mut(&mut self.axiomatic_rules).push(rule);
+        Arc::make_mut(&mut self.rule_sources).push(Some(source));
+    }
+    /// Removes an axiomatic fact and where it was declared, returning whether it was present.
+    pub fn remove_axiomatic_fact(&mut self, fact: &Fact) -> bool {
+        if !self.axiomatic_facts.contains(fact) { return false; }
+        self.log(|| Event::Retract(fact.clone()));
+        Arc::make_mut(&mut self.fact_sources).remove(fact);
+        Arc::make_mut(&mut self.axiomatic_facts).remove(fact)
+    }
+    pub fn get_fact_source(&self, fact: &Fact) -> Option<&SourceLocation> {
+        self.fact_sources.get(fact).and_then(|sources| sources.first())
+    }
+    pub fn get_fact_sources(&self, fact: &Fact) -> &[SourceLocation] {
+        self.fact_sources.get(fact).map(Vec::as_slice).unwrap_or_default()
+    }
+    pub fn get_rule_source(&self, index: usize) -> Option<&SourceLocation> {
+        self.rule_sources.get(index).and_then(|source| source.as_ref())
+    }
+    /// Declares the argument types of a predicate, failing if it was already declared differently.
+    pub fn add_type_declaration(&mut self, declaration: TypeDeclaration) -> Result<(), String> {
+        if let Some(existing) = self.type_declarations.get(&declaration.name) {
+            if existing != &declaration {
+                return Err(format!("Conflicting type declaration: {} was already declared as {}", declaration, existing));
+            }
+            return Ok(());
+        }
+        self.log(|| Event::Declare(declaration.clone()));
+        Arc::make_mut(&mut self.type_declarations).insert(declaration.name.clone(), declaration);
+        Ok(())
     }
-    pub fn add_fact(&mut self, mut fact: Fact) {
-        if fact.is_negative() {
-            fact.negate();
-            self.working_memory.remove(&fact);
-        } else {
-            self.working_memory.insert(fact);
+    pub fn get_type_declaration(&self, name: &str) -> Option<&TypeDeclaration> {
+        self.type_declarations.get(name)
+    }
+    pub fn get_type_declarations(&self) -> impl Iterator<Item = &TypeDeclaration> {
+        self.type_declarations.values()
+    }
+    pub fn add_fact(&mut self, fact: Fact) {
+        self.add_facts(vec![fact]);
+    }
+    /// Adds derived facts like `add_fact`, logging them together once they are all in working memory.
+    pub fn add_facts(&mut self, facts: Vec<Fact>) {
+        let logging: bool = self.event_log.is_some();
+        let mut events: Vec<Event> = Vec::new();
+        for mut fact in facts {
+            if fact.is_negative() {
+                fact.negate();
+                if self.working_memory.contains(&fact) {
+                    Arc::make_mut(&mut self.working_memory).remove(&fact);
+                    if logging {
+                        events.push(Event::Underive(fact));
+                    }
+                }
+            } else if !self.working_memory.contains(&fact) {
+                if logging {
+                    events.push(Event::Derive(fact.clone()));
+                }
+                Arc::make_mut(&mut self.working_memory).insert(fact);
+            }
+        }
+        if !events.is_empty() {
+            self.log_all(|| events);
         }
     }
-    #[allow(dead_code)]
     pub fn remove_fact(&mut self, fact: &Fact) {
-        self.working_memory.remove(fact);
+        if self.working_memory.contains(fact) {
+            self.log(|| Event::Underive(fact.clone()));
+            Arc::make_mut(&mut self.working_memory).remove(fact);
+        }
+    }
+    /// The derived facts.
+    pub fn working_memory(&self) -> &HashSet<Fact> {
+        &self.working_memory
+    }
+    pub fn get_axiomatic_facts(&self) -> impl Iterator<Item = &Fact> {
+        self.axiomatic_facts.iter()
     }
     pub fn get_facts(&self) -> impl Iterator<Item = &Fact> {
         self.axiomatic_facts.union(&self.working_memory)
@@ -45,15 +138,38 @@ impl KnowledgeBase {
     pub fn has_rule(&self, rule: &Rule) -> bool {
         self.axiomatic_rules.contains(rule)
     }
-    #[allow(dead_code)]
     pub fn clear_working_memory(&mut self) {
-        self.working_memory.clear();
+        self.log(|| Event::ClearDerived);
+        self.working_memory = Arc::default();
     }
+    /// Removes everything except the event log, which records the reset.
     #[allow(dead_code)]
     pub fn clear(&mut self) {
-        self.axiomatic_facts.clear();
-        self.axiomatic_rules.clear();
-        self.working_memory.clear();
+        self.log(|| Event::Clear);
+        let event_log: Option<Arc<Mutex<dyn ChangeLog>>> = self.event_log.take();
+        *self = KnowledgeBase { event_log, ..KnowledgeBase::new() };
+    }
+    /// Swaps the attached event log without recording anything, returning the previous one. See `attach_log` to
+    /// start logging a knowledge base.
+    pub fn set_event_log(&mut self, event_log: Option<Arc<Mutex<dyn ChangeLog>>>) -> Option<Arc<Mutex<dyn ChangeLog>>> {
+        std::mem::replace(&mut self.event_log, event_log)
+    }
+    pub fn event_log(&self) -> Option<&Arc<Mutex<dyn ChangeLog>>> {
+        self.event_log.as_ref()
+    }
+    /// Passes an event to the attached log, if any, before the change it describes is made.
+    fn log(&self, event: impl FnOnce() -> Event) {
+        self.log_all(|| vec![event()]);
+    }
+    /// Passes a batch of events to the attached log, if any, and flushes it once.
+    fn log_all(&self, events: impl FnOnce() -> Vec<Event>) {
+        if let Some(event_log) = &self.event_log {
+            let mut event_log = event_log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            for event in events() {
+                event_log.record(event);
+            }
+            event_log.flush();
+        }
     }
     pub fn from_strings(facts: Vec<&str>, rules: Vec<(&str, &str)>) -> Result<Self, String> {
         let mut knowledge_base: KnowledgeBase = KnowledgeBase::new();
@@ -61,7 +177,9 @@ impl KnowledgeBase {
             knowledge_base.add_axiomatic_fact(Fact::parse(fact_str)?);
         }
         for (antecedents, consequent) in rules {
-            knowledge_base.add_axiomatic_rule(Rule::parse(antecedents, consequent)?);
+            let rule: Rule = Rule::parse(antecedents, consequent)?;
+            rule.check_safety().map_err(|error| format!("Unsafe rule {}: {}", rule, error))?;
+            knowledge_base.add_axiomatic_rule(rule);
         }
         Ok(knowledge_base)
     }
@@ -69,21 +187,159 @@ impl KnowledgeBase {
 impl fmt::Display for KnowledgeBase {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "Axiomatic Facts:")?;
-        for fact in &self.axiomatic_facts {
+        for fact in self.axiomatic_facts.iter() {
             writeln!(f, "  {}", fact)?;
         }
         writeln!(f, "Axiomatic Rules:")?;
-        for rule in &self.axiomatic_rules {
+        for rule in self.axiomatic_rules.iter() {
             writeln!(f, "  {}", rule)?;
         }
         writeln!(f, "Working Memory:")?;
-        for fact in &self.working_memory {
+        for fact in self.working_memory.iter() {
             writeln!(f, "  {}", fact)?;
         }
         Ok(())
     }
 }
 
+/// A change to a knowledge base, as passed to its `ChangeLog`.
+#[derive(Clone, PartialEq)]
+pub enum Event {
+    /// A given fact was added, from a file and line when it was loaded.
+    Assert(Fact, Option<SourceLocation>),
+    Retract(Fact),
+    AddRule(Rule, Option<SourceLocation>),
+    Declare(TypeDeclaration),
+    /// Inference added a fact to working memory.
+    Derive(Fact),
+    Underive(Fact),
+    ClearDerived,
+    Clear
+}
+
+/// Receives the changes made to a knowledge base, see `KnowledgeBase::set_event_log`. `event_log::EventLog` appends
+/// them to a file.
+pub trait ChangeLog: Send {
+    fn record(&mut self, event: Event);
+    /// Called after each batch of events, such as the facts of one inference pass.
+    fn flush(&mut self) {}
+    /// The first failure to record an event, if any.
+    fn error(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Facts that were added and removed between two sets of facts, each sorted by their text.
+pub struct FactDiff {
+    pub added: Vec<Fact>,
+    pub removed: Vec<Fact>
+}
+impl FactDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+pub fn diff(previous: &HashSet<Fact>, current: &HashSet<Fact>) -> FactDiff {
+    let sorted = |facts: Vec<&Fact>| {
+        let mut facts: Vec<Fact> = facts.into_iter().cloned().collect();
+        facts.sort_by_key(Fact::to_string);
+        facts
+    };
+    FactDiff {
+        added: sorted(current.difference(previous).collect()),
+        removed: sorted(previous.difference(current).collect())
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourceLocation {
+    pub file: PathBuf,
+    pub line: usize
+}
+impl SourceLocation {
+    pub fn new(file: PathBuf, line: usize) -> Self {
+        SourceLocation { file, line }
+    }
+}
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum ArgumentType {
+    Atom,
+    Int,
+    Any
+}
+impl ArgumentType {
+    pub fn parse(string: &str) -> Result<Self, String> {
+        match string {
+            "atom" => Ok(ArgumentType::Atom),
+            "int" => Ok(ArgumentType::Int),
+            "any" => Ok(ArgumentType::Any),
+            _ => Err(format!("Unknown type '{}' - expected atom, int or any", string))
+        }
+    }
+    /// Whether a ground argument (not a variable) is a value of this type.
+    pub fn accepts(&self, fact: &Fact) -> bool {
+        match self {
+            ArgumentType::Atom => matches!(fact, Fact::Atomic(_)),
+            ArgumentType::Int => matches!(fact, Fact::Number(_)),
+            ArgumentType::Any => true
+        }
+    }
+}
+impl fmt::Display for ArgumentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgumentType::Atom => write!(f, "atom"),
+            ArgumentType::Int => write!(f, "int"),
+            ArgumentType::Any => write!(f, "any")
+        }
+    }
+}
+
+/// The argument types of a predicate, declared with `:- type employee(atom, atom, int).`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeDeclaration {
+    pub name: String,
+    pub arguments: Vec<ArgumentType>
+}
+impl TypeDeclaration {
+    pub fn new(name: String, arguments: Vec<ArgumentType>) -> Self {
+        TypeDeclaration { name, arguments }
+    }
+    pub fn parse(string: &str) -> Result<Self, String> {
+        match Fact::parse(string)? {
+            Fact::Atomic(atomic) if atomic.positive => Ok(TypeDeclaration::new(atomic.name, Vec::new())),
+            Fact::Predicate(predicate) if predicate.positive => {
+                let mut arguments: Vec<ArgumentType> = Vec::new();
+                for argument in &predicate.arguments {
+                    match argument {
+                        Fact::Atomic(atomic) => arguments.push(ArgumentType::parse(&atomic.name)?),
+                        _ => return Err(format!("Invalid type '{}' in type declaration", argument))
+                    }
+                }
+                Ok(TypeDeclaration::new(predicate.name, arguments))
+            }
+            _ => Err(format!("Invalid type declaration: {}", string))
+        }
+    }
+}
+impl fmt::Display for TypeDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.arguments.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            let arguments: Vec<String> = self.arguments.iter().map(|argument| argument.to_string()).collect();
+            write!(f, "{}({})", self.name, arguments.join(", "))
+        }
+    }
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Fact {
     Number(NumericFact),
@@ -116,6 +372,22 @@ impl Fact {
             Fact::Variable(_) => self.clone()
         }
     }
+    /// Every variable in the fact, including those nested in predicate arguments, in order of appearance.
+    pub fn variables(&self) -> Vec<&Variable> {
+        match self {
+            Fact::Variable(variable) => vec![variable],
+            Fact::Predicate(predicate) => predicate.arguments.iter().flat_map(|argument: &Fact| argument.variables()).collect(),
+            _ => Vec::new()
+        }
+    }
+    /// The predicate name and arity of an atomic or predicate fact.
+    pub fn signature(&self) -> Option<(&str, usize)> {
+        match self {
+            Fact::Atomic(atomic) => Some((&atomic.name, 0)),
+            Fact::Predicate(predicate) => Some((&predicate.name, predicate.arguments.len())),
+            _ => None
+        }
+    }
     pub fn parse(string: &str) -> Result<Self, String> {
         if string.ends_with('?') {
             Ok(Fact::Variable(Variable::parse(string)?))
@@ -173,10 +445,9 @@ impl AtomicFact {
         AtomicFact::new(!self.positive, self.name.clone())
     }
     pub fn parse(string: &str) -> Self {
-        let (positive, name) = if string.starts_with('!') {
-            (false, string[1..].to_string())
-        } else {
-            (true, string.to_string())
+        let (positive, name) = match string.strip_prefix('!') {
+            Some(name) => (false, name.to_string()),
+            None => (true, string.to_string())
         };
         AtomicFact::new(positive, name)
     }
@@ -204,10 +475,9 @@ impl PredicateFact {
         PredicateFact::new(!self.positive, self.name.clone(), self.arguments.clone())
     }
     pub fn parse(string: &str) -> Result<Self, String> {
-        let (positive, rest) = if string.starts_with('!') {
-            (false, &string[1..])
-        } else {
-            (true, string)
+        let (positive, rest) = match string.strip_prefix('!') {
+            Some(rest) => (false, rest),
+            None => (true, string)
         };
         let name_end: usize = rest.find('(').unwrap_or(rest.len());
         let name: String = rest[..name_end].to_string();
@@ -235,10 +505,10 @@ impl Variable {
         Variable { name }
     }
     pub fn parse(string: &str) -> Result<Self, String> {
-        if string.ends_with('?') {
-            Ok(Variable::new(string[..string.len()-1].to_string()))
+        if let Some(name) = string.strip_suffix('?') {
+            Ok(Variable::new(name.to_string()))
         } else {
-            Err(String::from(format!("Invalid variable: {}", string)))
+            Err(format!("Invalid variable: {}", string))
         }
     }
 }
@@ -260,25 +530,117 @@ impl Rule {
     pub fn parse(antecedents: &str, consequent: &str) -> Result<Self, String> {
         let antecedents_items: Vec<AntecedentItem> = Self::parse_antecedents(antecedents)?;
         let consequent_fact: Fact = Fact::parse(consequent)?;
-        Ok(Rule::new(antecedents_items, consequent_fact).validate())
+        let rule: Rule = Rule::new(antecedents_items, consequent_fact);
+        rule.check_postfix()?;
+        Ok(rule)
+    }
+    /// Indices of the antecedent facts that are looked up as literals, i.e. every fact that is not an operand of a comparison.
+    pub fn literal_indices(&self) -> Vec<usize> {
+        let mut stack: Vec<Option<usize>> = Vec::new();
+        let mut operands: HashSet<usize> = HashSet::new();
+        for (index, item) in self.antecedents.iter().enumerate() {
+            match item {
+                AntecedentItem::Fact(_) => stack.push(Some(index)),
+                AntecedentItem::And | AntecedentItem::Or => {
+                    stack.pop();
+                    stack.pop();
+                    stack.push(None);
+                }
+                _ => {
+                    operands.extend([stack.pop(), stack.pop()].into_iter().flatten().flatten());
+                    stack.push(None);
+                }
+            }
+        }
+        (0..self.antecedents.len()).filter(|index: &usize| matches!(self.antecedents[*index], AntecedentItem::Fact(_)) && !operands.contains(index)).collect()
+    }
+    /// The antecedent facts that are looked up as literals, see `literal_indices`.
+    pub fn literals(&self) -> Vec<Fact> {
+        self.literal_indices().into_iter().filter_map(|index: usize| match &self.antecedents[index] {
+            AntecedentItem::Fact(fact) => Some(fact.clone()),
+            _ => None
+        }).collect()
+    }
+    /// Checks that the rule is range-restricted: every head variable must be bound by a positive body literal on
+    /// every path through `|`, and every body variable must appear in at least one positive literal, rather than
+    /// only under negation or in comparisons.
+    pub fn check_safety(&self) -> Result<(), SafetyError> {
+        enum StackItem<'s> { Fact(&'s Fact), Bound(HashSet<&'s str>) }
+        fn bound_by(item: StackItem<'_>) -> HashSet<&str> {
+            match item {
+                StackItem::Fact(fact @ (Fact::Predicate(_) | Fact::Atomic(_))) if !fact.is_negative() => fact.variables().into_iter().map(|variable: &Variable| variable.name.as_str()).collect(),
+                StackItem::Fact(_) => HashSet::new(),
+                StackItem::Bound(bound) => bound
+            }
+        }
+        let mut stack: Vec<StackItem> = Vec::new();
+        for item in &self.antecedents {
+            match item {
+                AntecedentItem::Fact(fact) => stack.push(StackItem::Fact(fact)),
+                AntecedentItem::And | AntecedentItem::Or => {
+                    let (Some(right), Some(left)) = (stack.pop(), stack.pop()) else { break; };
+                    let (left, right) = (bound_by(left), bound_by(right));
+                    stack.push(StackItem::Bound(if *item == AntecedentItem::And {
+                        left.union(&right).copied().collect()
+                    } else {
+                        left.intersection(&right).copied().collect()
+                    }));
+                }
+                _ => {
+                    stack.pop();
+                    stack.pop();
+                    stack.push(StackItem::Bound(HashSet::new()));
+                }
+            }
+        }
+        let bound: HashSet<&str> = stack.pop().map(bound_by).unwrap_or_default();
+        for variable in self.consequent.variables() {
+            if !bound.contains(variable.name.as_str()) {
+                return Err(SafetyError { variable: variable.clone(), in_head: true });
+            }
+        }
+        let mut positive: HashSet<&str> = HashSet::new();
+        for index in self.literal_indices() {
+            if let AntecedentItem::Fact(fact @ (Fact::Predicate(_) | Fact::Atomic(_))) = &self.antecedents[index] && !fact.is_negative() {
+                positive.extend(fact.variables().into_iter().map(|variable: &Variable| variable.name.as_str()));
+            }
+        }
+        for item in &self.antecedents {
+            if let AntecedentItem::Fact(fact) = item {
+                for variable in fact.variables() {
+                    if !positive.contains(variable.name.as_str()) {
+                        return Err(SafetyError { variable: variable.clone(), in_head: false });
+                    }
+                }
+            }
+        }
+        Ok(())
     }
+    /// Panics if the antecedents are not a well-formed postfix expression; see `check_postfix`.
     pub fn validate(self) -> Self {
+        if let Err(error) = self.check_postfix() {
+            panic!("{}", error);
+        }
+        self
+    }
+    /// Checks that every operator has two operands and that the antecedents reduce to a single expression.
+    pub fn check_postfix(&self) -> Result<(), String> {
         let mut stack_height:u32 = 0;
         for item in &self.antecedents {
             match item {
                 AntecedentItem::Fact(_) => stack_height += 1,
                 AntecedentItem::And | AntecedentItem::Or | AntecedentItem::Equals | AntecedentItem::NotEquals | AntecedentItem::GreaterThan | AntecedentItem::GreaterOrEquals | AntecedentItem::LesserThan | AntecedentItem::LesserOrEquals => {
                     if stack_height < 2 {
-                        panic!("Invalid postfix expression: not enough operands");
+                        return Err(String::from("Invalid postfix expression: not enough operands"));
                     }
                     stack_height -= 1;
                 }
             }
         }
         if stack_height != 1 {
-            panic!("Invalid postfix expression: stack should have exactly one item at end");
+            return Err(String::from("Invalid postfix expression: stack should have exactly one item at end"));
         }
-        self
+        Ok(())
     }
     fn parse_antecedents(input: &str) -> Result<Vec<AntecedentItem>, String> {
         if input.trim().is_empty() { return Err(String::from("Rules must have antecedents")); }
@@ -289,11 +651,9 @@ impl Rule {
             for ch in input.chars() {
                 match ch {
                     ' ' | '\t' | '\n' => {
-                        if !comma_space {
-                            if !current_token.is_empty() {
-                                tokens.push(current_token.clone());
-                                current_token.clear();
-                            }
+                        if !comma_space && !current_token.is_empty() {
+                            tokens.push(current_token.clone());
+                            current_token.clear();
                         }
                         comma_space = false;
                     }
@@ -461,12 +821,7 @@ impl Rule {
             }
         }
         if stack.len() == 1 {
-            let result: String = stack.pop().unwrap();
-            if result.starts_with('(') && result.ends_with(')') {
-                result
-            } else {
-                result
-            }
+            stack.pop().unwrap()
         } else {
             self.antecedents.iter().map(|item: &AntecedentItem| item.to_string()).collect::<Vec<_>>().join(" ")
         }
@@ -478,6 +833,22 @@ impl fmt::Display for Rule {
     }
 }
 
+/// A variable that makes a rule unsafe, see `Rule::check_safety`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SafetyError {
+    pub variable: Variable,
+    pub in_head: bool
+}
+impl fmt::Display for SafetyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.in_head {
+            write!(f, "variable {} in the head is not bound by a positive body literal", self.variable)
+        } else {
+            write!(f, "variable {} only appears under negation or in a comparison", self.variable)
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub enum AntecedentItem {
     Fact(Fact),
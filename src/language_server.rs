@@ -0,0 +1,318 @@
+use std::{collections::{BTreeSet, HashMap}, fs, io::{BufRead, Write}, path::{Path, PathBuf}};
+
+use crate::json::Json;
+use crate::knowledge_base::*;
+use crate::linter::{lint, LintWarning};
+use crate::loader::Loader;
+
+/// How many facts a hover shows before summarising the rest.
+const HOVER_FACT_LIMIT: usize = 20;
+
+/// A Language Server Protocol server for knowledge-base files, speaking JSON-RPC over stdio with full document sync.
+/// It publishes loader errors and lint warnings as diagnostics, and offers go-to-definition, hover and completion
+/// for predicate names based on the last version of each open document that loaded successfully.
+pub struct LanguageServer {
+    documents: HashMap<String, Document>,
+    shutdown_requested: bool
+}
+struct Document {
+    text: String,
+    knowledge_base: Option<KnowledgeBase>
+}
+impl Default for LanguageServer {
+    fn default() -> Self {
+        LanguageServer::new()
+    }
+}
+impl LanguageServer {
+    pub fn new() -> Self {
+        LanguageServer { documents: HashMap::new(), shutdown_requested: false }
+    }
+    /// Serves requests until the client sends `exit` or closes the input. Exiting without a `shutdown` request first
+    /// is an error, as the protocol asks for a non-zero exit status in that case.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> Result<(), String> {
+        while let Some(message) = read_message(&mut input)? {
+            if message.get("method").and_then(Json::as_str) == Some("exit") {
+                return if self.shutdown_requested { Ok(()) } else { Err(String::from("Exit requested before shutdown")) };
+            }
+            for response in self.handle(&message) {
+                write_message(&mut output, &response)?;
+            }
+        }
+        Ok(())
+    }
+    /// Handles one incoming message and returns the responses and notifications to send back.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method: &str = message.get("method").and_then(Json::as_str).unwrap_or_default();
+        let id: Option<&Json> = message.get("id");
+        let params: &Json = message.get("params").unwrap_or(&Json::Null);
+        let result: Option<Json> = match method {
+            "initialize" => Some(Json::object(vec![
+                ("capabilities", Json::object(vec![
+                    ("textDocumentSync", Json::Number(1.0)),
+                    ("definitionProvider", Json::Bool(true)),
+                    ("hoverProvider", Json::Bool(true)),
+                    ("completionProvider", Json::object(vec![]))
+                ])),
+                ("serverInfo", Json::object(vec![("name", Json::string("pie"))]))
+            ])),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Some(Json::Null)
+            }
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let uri: &str = params.get_path(&["textDocument", "uri"]).and_then(Json::as_str).unwrap_or_default();
+                let text: Option<&str> = params.get_path(&["textDocument", "text"]).and_then(Json::as_str).or_else(|| {
+                    params.get("contentChanges").and_then(Json::as_array).and_then(|changes: &Vec<Json>| changes.last()).and_then(|change: &Json| change.get("text")).and_then(Json::as_str)
+                });
+                return match text {
+                    Some(text) => vec![self.update_document(uri, text)],
+                    None => Vec::new()
+                };
+            }
+            "textDocument/didClose" => {
+                let uri: &str = params.get_path(&["textDocument", "uri"]).and_then(Json::as_str).unwrap_or_default();
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, Vec::new())];
+            }
+            "textDocument/definition" => Some(self.definition(params)),
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/completion" => Some(self.completion(params)),
+            _ => None
+        };
+        let Some(id) = id else { return Vec::new(); };
+        match result {
+            Some(result) => vec![Json::object(vec![("jsonrpc", Json::string("2.0")), ("id", id.clone()), ("result", result)])],
+            None => vec![Json::object(vec![
+                ("jsonrpc", Json::string("2.0")),
+                ("id", id.clone()),
+                ("error", Json::object(vec![("code", Json::Number(-32601.0)), ("message", Json::string(format!("Method not found: {}", method)))]))
+            ])]
+        }
+    }
+    fn update_document(&mut self, uri: &str, text: &str) -> Json {
+        let path: PathBuf = uri_to_path(uri);
+        let mut loader: Loader = Loader::new();
+        let loaded: Result<KnowledgeBase, String> = match loader.load_source(text, &path) {
+            Ok(()) => loader.finish(),
+            Err(error) => Err(error)
+        };
+        let diagnostics: Vec<Json> = match &loaded {
+            Ok(knowledge_base) => lint(knowledge_base).iter().filter(|warning: &&LintWarning| warning.location.as_ref().is_some_and(|location: &SourceLocation| location.file == path)).map(|warning: &LintWarning| {
+                let line: usize = warning.location.as_ref().map_or(1, |location: &SourceLocation| location.line);
+                diagnostic(text, line, None, 2, Some(warning.code), &warning.message)
+            }).collect(),
+            Err(error) => error_diagnostics(text, &path, error)
+        };
+        let document: &mut Document = self.documents.entry(uri.to_string()).or_insert(Document { text: String::new(), knowledge_base: None });
+        document.text = text.to_string();
+        if let Ok(knowledge_base) = loaded {
+            document.knowledge_base = Some(knowledge_base);
+        }
+        publish_diagnostics(uri, diagnostics)
+    }
+    /// The path and contents of the open document, and the predicate name under the cursor.
+    fn word_at(&self, params: &Json) -> Option<(PathBuf, &Document, String)> {
+        let uri: &str = params.get_path(&["textDocument", "uri"]).and_then(Json::as_str)?;
+        let line: usize = params.get_path(&["position", "line"]).and_then(Json::as_i64)? as usize;
+        let character: usize = params.get_path(&["position", "character"]).and_then(Json::as_i64)? as usize;
+        let document: &Document = self.documents.get(uri)?;
+        let characters: Vec<char> = document.text.lines().nth(line)?.chars().collect();
+        let is_name = |ch: &char| ch.is_alphanumeric() || *ch == '_' || *ch == ':';
+        let mut start: usize = character.min(characters.len());
+        while start > 0 && is_name(&characters[start - 1]) { start -= 1; }
+        let mut end: usize = character.min(characters.len());
+        while end < characters.len() && is_name(&characters[end]) { end += 1; }
+        if start == end || characters.get(end) == Some(&'?') { return None; }
+        Some((uri_to_path(uri), document, characters[start..end].iter().collect()))
+    }
+    fn definition(&self, params: &Json) -> Json {
+        let Some((path, document, word)) = self.word_at(params) else { return Json::Null; };
+        let Some(knowledge_base) = &document.knowledge_base else { return Json::Null; };
+        let mut sources: BTreeSet<&SourceLocation> = BTreeSet::new();
+        for fact in knowledge_base.get_axiomatic_facts() {
+            if fact.signature().is_some_and(|(name, _)| names_match(name, &word)) {
+                sources.extend(knowledge_base.get_fact_sources(fact));
+            }
+        }
+        for (index, rule) in knowledge_base.get_rules().enumerate() {
+            if rule.consequent.signature().is_some_and(|(name, _)| names_match(name, &word)) {
+                sources.extend(knowledge_base.get_rule_source(index));
+            }
+        }
+        let mut file_texts: HashMap<&Path, String> = HashMap::new();
+        Json::Array(sources.into_iter().map(|source: &SourceLocation| {
+            let text: &str = if source.file == path {
+                &document.text
+            } else {
+                file_texts.entry(&source.file).or_insert_with(|| fs::read_to_string(&source.file).unwrap_or_default())
+            };
+            let line_text: &str = text.lines().nth(source.line - 1).unwrap_or_default();
+            let start: usize = line_text.find(word.rsplit("::").next().unwrap_or(&word)).map_or(0, |index: usize| line_text[..index].chars().count());
+            Json::object(vec![("uri", Json::string(path_to_uri(&source.file))), ("range", range(source.line - 1, start, start + word.chars().count()))])
+        }).collect())
+    }
+    fn hover(&self, params: &Json) -> Json {
+        let Some((_, document, word)) = self.word_at(params) else { return Json::Null; };
+        let Some(knowledge_base) = &document.knowledge_base else { return Json::Null; };
+        let mut signatures: BTreeSet<(String, usize)> = BTreeSet::new();
+        let mut facts: Vec<String> = Vec::new();
+        for fact in knowledge_base.get_axiomatic_facts() {
+            if let Some((name, arity)) = fact.signature() && names_match(name, &word) {
+                signatures.insert((name.to_string(), arity));
+                facts.push(fact.to_string());
+            }
+        }
+        for rule in knowledge_base.get_rules() {
+            for fact in [&rule.consequent].into_iter().chain(rule.antecedents.iter().filter_map(|item: &AntecedentItem| if let AntecedentItem::Fact(fact) = item { Some(fact) } else { None })) {
+                if let Some((name, arity)) = fact.signature() && names_match(name, &word) {
+                    signatures.insert((name.to_string(), arity));
+                }
+            }
+        }
+        if signatures.is_empty() { return Json::Null; }
+        let mut lines: Vec<String> = Vec::new();
+        for (name, arity) in &signatures {
+            lines.push(format!("**{}/{}**", name, arity));
+            if let Some(declaration) = knowledge_base.get_type_declaration(name) {
+                lines.push(format!("`:- type {}.`", declaration));
+            }
+        }
+        facts.sort();
+        if facts.is_empty() {
+            lines.push(String::from("\nNo known facts, only derived by rules."));
+        } else {
+            lines.push(format!("\nKnown facts ({}):", facts.len()));
+            lines.extend(facts.iter().take(HOVER_FACT_LIMIT).map(|fact: &String| format!("- `{}`", fact)));
+            if facts.len() > HOVER_FACT_LIMIT {
+                lines.push(format!("- … and {} more", facts.len() - HOVER_FACT_LIMIT));
+            }
+        }
+        Json::object(vec![("contents", Json::object(vec![("kind", Json::string("markdown")), ("value", Json::string(lines.join("\n")))]))])
+    }
+    fn completion(&self, params: &Json) -> Json {
+        let uri: &str = params.get_path(&["textDocument", "uri"]).and_then(Json::as_str).unwrap_or_default();
+        let Some(knowledge_base) = self.documents.get(uri).and_then(|document: &Document| document.knowledge_base.as_ref()) else { return Json::Array(Vec::new()); };
+        let mut signatures: BTreeSet<(&str, usize)> = knowledge_base.get_axiomatic_facts().filter_map(Fact::signature).collect();
+        signatures.extend(knowledge_base.get_rules().filter_map(|rule: &Rule| rule.consequent.signature()));
+        Json::Array(signatures.into_iter().map(|(name, arity)| Json::object(vec![
+            ("label", Json::string(name)),
+            ("kind", Json::Number(3.0)),
+            ("detail", Json::string(format!("{}/{}", name, arity)))
+        ])).collect())
+    }
+}
+
+fn names_match(name: &str, word: &str) -> bool {
+    name == word || name.strip_suffix(word).is_some_and(|module: &str| module.ends_with("::"))
+}
+
+fn read_message(input: &mut impl BufRead) -> Result<Option<Json>, String> {
+    let mut content_length: Option<usize> = None;
+    loop {
+        let mut header: String = String::new();
+        if input.read_line(&mut header).map_err(|error| format!("Failed to read message: {}", error))? == 0 { return Ok(None); }
+        let header: &str = header.trim_end();
+        if header.is_empty() { break; }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse().ok();
+        }
+    }
+    let length: usize = content_length.ok_or("Missing Content-Length header")?;
+    let mut body: Vec<u8> = vec![0; length];
+    input.read_exact(&mut body).map_err(|error| format!("Failed to read message: {}", error))?;
+    Json::parse(&String::from_utf8_lossy(&body)).map(Some)
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> Result<(), String> {
+    let body: String = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body).and_then(|_| output.flush()).map_err(|error| format!("Failed to write message: {}", error))
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::string("2.0")),
+        ("method", Json::string("textDocument/publishDiagnostics")),
+        ("params", Json::object(vec![("uri", Json::string(uri)), ("diagnostics", Json::Array(diagnostics))]))
+    ])
+}
+
+fn range(line: usize, start: usize, end: usize) -> Json {
+    let position = |character: usize| Json::object(vec![("line", Json::Number(line as f64)), ("character", Json::Number(character as f64))]);
+    Json::object(vec![("start", position(start)), ("end", position(end))])
+}
+
+/// A diagnostic on a 1-based line, from the 1-based column (or the first non-blank character) to the end of the line.
+fn diagnostic(text: &str, line: usize, column: Option<usize>, severity: u8, code: Option<&str>, message: &str) -> Json {
+    let line_text: &str = text.lines().nth(line.saturating_sub(1)).unwrap_or_default();
+    let indentation: usize = line_text.chars().take_while(|ch: &char| ch.is_whitespace()).count();
+    let start: usize = column.map_or(indentation, |column: usize| column.saturating_sub(1));
+    let mut pairs: Vec<(&str, Json)> = vec![
+        ("range", range(line.saturating_sub(1), start, line_text.chars().count().max(start))),
+        ("severity", Json::Number(severity as f64)),
+        ("source", Json::string("pie")),
+        ("message", Json::string(message))
+    ];
+    if let Some(code) = code {
+        pairs.push(("code", Json::string(code)));
+    }
+    Json::object(pairs)
+}
+
+/// Turns a loader error into diagnostics. Errors are reported as `path:line[:column]: message`, possibly followed by
+/// `included from path:line` when they come from an included file, or by a quoted source line with a caret marker.
+fn error_diagnostics(text: &str, path: &Path, error: &str) -> Vec<Json> {
+    let prefix: String = format!("{}:", path.display());
+    let mut diagnostics: Vec<(usize, Option<usize>, String)> = Vec::new();
+    for line in error.lines() {
+        if let Some(included_from) = line.trim_start().strip_prefix("included from ").and_then(|location: &str| location.strip_prefix(&prefix)) {
+            if let (Some(last), Ok(line_number)) = (diagnostics.last_mut(), included_from.parse::<usize>()) {
+                last.0 = line_number;
+                last.1 = None;
+            }
+        } else if let Some(rest) = line.strip_prefix(&prefix) {
+            let mut parts = rest.splitn(3, ':');
+            let line_number: usize = parts.next().and_then(|part: &str| part.trim().parse().ok()).unwrap_or(1);
+            let (column, message): (Option<usize>, String) = match (parts.next(), parts.next()) {
+                (Some(column), Some(message)) if column.trim().parse::<usize>().is_ok() => (column.trim().parse().ok(), message.trim().to_string()),
+                (Some(first), Some(second)) => (None, format!("{}:{}", first, second).trim().to_string()),
+                (Some(message), None) => (None, message.trim().to_string()),
+                _ => (None, String::new())
+            };
+            diagnostics.push((line_number, column, message));
+        } else if !line.starts_with(char::is_whitespace) {
+            diagnostics.push((1, None, line.to_string()));
+        }
+    }
+    diagnostics.into_iter().map(|(line, column, message)| diagnostic(text, line, column, 1, None, &message)).collect()
+}
+
+pub fn uri_to_path(uri: &str) -> PathBuf {
+    let path: &str = uri.strip_prefix("file://").unwrap_or(uri);
+    let bytes: &[u8] = path.as_bytes();
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut index: usize = 0;
+    while index < bytes.len() {
+        let escaped: Option<u8> = bytes.get(index + 1..index + 3).and_then(|hex: &[u8]| std::str::from_utf8(hex).ok()).and_then(|hex: &str| u8::from_str_radix(hex, 16).ok());
+        if bytes[index] == b'%' && let Some(byte) = escaped {
+            decoded.push(byte);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri: String = String::from("file://");
+    for byte in path.display().to_string().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
@@ -0,0 +1,34 @@
+pub mod commands;
+pub mod datalog;
+pub mod event_log;
+pub mod export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+pub mod formatter;
+pub mod graph;
+pub mod http;
+pub mod hypothesis;
+pub mod import;
+pub mod inference_engine;
+pub mod json;
+pub mod knowledge_base;
+pub mod language_server;
+pub mod linter;
+pub mod loader;
+pub mod output;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod profile;
+pub mod repl;
+pub mod server;
+pub mod storage;
+pub mod trace;
+pub mod transaction;
+pub mod type_checker;
+pub mod watcher;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+// The original tests compare lengths with zero, and are kept as they were written.
+#[cfg(test)]
+#[allow(clippy::len_zero)]
+mod tests;
@@ -0,0 +1,165 @@
+use std::{collections::{HashMap, HashSet}, fmt};
+
+use crate::knowledge_base::*;
+
+/// A suspicious construct found by `lint`. The code is a short, stable identifier of the check that found it.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LintWarning {
+    pub location: Option<SourceLocation>,
+    pub code: &'static str,
+    pub message: String
+}
+impl LintWarning {
+    fn new(location: Option<&SourceLocation>, code: &'static str, message: String) -> Self {
+        LintWarning { location: location.cloned(), code, message }
+    }
+}
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{}: ", location)?;
+        }
+        write!(f, "warning[{}]: {}", self.code, self.message)
+    }
+}
+
+type Signature = (String, usize);
+
+/// Looks for likely mistakes in a loaded knowledge base, sorted by location:
+/// - `undefined-predicate`: a rule body uses a predicate that no fact or rule head defines
+/// - `unused-predicate`: a predicate given by facts is never used in a rule body
+/// - `unreachable-rule`: a rule depends on predicates that can never be derived, so it can never fire
+/// - `singleton-variable`: a variable occurs only once in a rule; prefix it with `_` if that is intended
+/// - `capitalised-constant`: a constant starts with a capital letter, as if it was meant to be a variable
+/// - `duplicate-fact`: the same fact is declared more than once
+pub fn lint(knowledge_base: &KnowledgeBase) -> Vec<LintWarning> {
+    let mut warnings: Vec<LintWarning> = Vec::new();
+    let mut fact_predicates: HashMap<Signature, Option<&SourceLocation>> = HashMap::new();
+    for fact in knowledge_base.get_axiomatic_facts() {
+        let sources: &[SourceLocation] = knowledge_base.get_fact_sources(fact);
+        if let Some(signature) = signature_of(fact) {
+            let first_source: Option<&SourceLocation> = sources.iter().min();
+            let entry: &mut Option<&SourceLocation> = fact_predicates.entry(signature).or_insert(first_source);
+            if let Some(first_source) = first_source && entry.is_none_or(|existing: &SourceLocation| first_source < existing) {
+                *entry = Some(first_source);
+            }
+        }
+        let mut sorted_sources: Vec<&SourceLocation> = sources.iter().collect();
+        sorted_sources.sort();
+        for duplicate in sorted_sources.iter().skip(1) {
+            warnings.push(LintWarning::new(Some(duplicate), "duplicate-fact", format!("{} is already declared at {}", fact, sorted_sources[0])));
+        }
+        check_arguments(fact, sources.iter().min(), &mut warnings);
+    }
+    let rules: Vec<(&Rule, Option<&SourceLocation>)> = knowledge_base.get_rules().enumerate().map(|(index, rule)| (rule, knowledge_base.get_rule_source(index))).collect();
+    let mut defined: HashSet<Signature> = fact_predicates.keys().cloned().collect();
+    defined.extend(rules.iter().filter_map(|(rule, _)| signature_of(&rule.consequent)));
+    let mut used: HashSet<Signature> = HashSet::new();
+    let mut derivable: HashSet<Signature> = fact_predicates.keys().cloned().collect();
+    let mut changed: bool = true;
+    while changed {
+        changed = false;
+        for (rule, _) in &rules {
+            if let Some(signature) = signature_of(&rule.consequent)
+                && !derivable.contains(&signature) && can_fire(rule, &derivable) {
+                derivable.insert(signature);
+                changed = true;
+            }
+        }
+    }
+    for (rule, source) in &rules {
+        let literal_indices: Vec<usize> = rule.literal_indices();
+        let mut reported: HashSet<Signature> = HashSet::new();
+        for index in literal_indices.iter().copied() {
+            let AntecedentItem::Fact(fact) = &rule.antecedents[index] else { continue; };
+            let Some(signature) = signature_of(fact) else { continue; };
+            if !defined.contains(&signature) && reported.insert(signature.clone()) {
+                warnings.push(LintWarning::new(*source, "undefined-predicate", format!("{}/{} is not defined by any fact or rule", signature.0, signature.1)));
+            }
+            used.insert(signature);
+        }
+        if !can_fire(rule, &derivable) {
+            warnings.push(LintWarning::new(*source, "unreachable-rule", format!("{} can never fire", rule)));
+        }
+        let mut occurrences: Vec<(&Variable, usize)> = Vec::new();
+        let body_facts = rule.antecedents.iter().enumerate().filter_map(|(index, item)| match item {
+            AntecedentItem::Fact(fact) => Some((fact, literal_indices.contains(&index))),
+            _ => None
+        });
+        for (fact, is_literal) in body_facts.chain([(&rule.consequent, true)]) {
+            for variable in fact.variables() {
+                match occurrences.iter_mut().find(|(existing, _)| existing.name == variable.name) {
+                    Some((_, count)) => *count += 1,
+                    None => occurrences.push((variable, 1))
+                }
+            }
+            if is_literal {
+                check_arguments(fact, *source, &mut warnings);
+            } else {
+                check_constant(fact, *source, &mut warnings);
+            }
+        }
+        for (variable, count) in occurrences {
+            if count == 1 && !variable.name.starts_with('_') {
+                warnings.push(LintWarning::new(*source, "singleton-variable", format!("variable {} is only used once; rename it to _{} if that is intended", variable, variable)));
+            }
+        }
+    }
+    for (signature, source) in &fact_predicates {
+        if !used.contains(signature) && !rules.iter().any(|(rule, _)| signature_of(&rule.consequent).as_ref() == Some(signature)) {
+            warnings.push(LintWarning::new(*source, "unused-predicate", format!("{}/{} is given by facts but never used in a rule", signature.0, signature.1)));
+        }
+    }
+    warnings.sort();
+    warnings.dedup();
+    warnings
+}
+
+fn signature_of(fact: &Fact) -> Option<Signature> {
+    fact.signature().map(|(name, arity)| (name.to_string(), arity))
+}
+
+/// Whether the body of a rule could ever hold, given the predicates that can be derived at all.
+fn can_fire(rule: &Rule, derivable: &HashSet<Signature>) -> bool {
+    enum StackItem<'s> { Fact(&'s Fact), Value(bool) }
+    let holds = |item: StackItem| match item {
+        StackItem::Fact(fact) if !fact.is_negative() => signature_of(fact).is_none_or(|signature: Signature| derivable.contains(&signature)),
+        StackItem::Fact(_) => true,
+        StackItem::Value(value) => value
+    };
+    let mut stack: Vec<StackItem> = Vec::new();
+    for item in &rule.antecedents {
+        match item {
+            AntecedentItem::Fact(fact) => stack.push(StackItem::Fact(fact)),
+            AntecedentItem::And | AntecedentItem::Or => {
+                let (Some(right), Some(left)) = (stack.pop(), stack.pop()) else { return true; };
+                let (left, right) = (holds(left), holds(right));
+                stack.push(StackItem::Value(if *item == AntecedentItem::And { left && right } else { left || right }));
+            }
+            _ => {
+                stack.pop();
+                stack.pop();
+                stack.push(StackItem::Value(true));
+            }
+        }
+    }
+    stack.pop().is_none_or(holds)
+}
+
+fn check_arguments(fact: &Fact, source: Option<&SourceLocation>, warnings: &mut Vec<LintWarning>) {
+    if let Fact::Predicate(predicate) = fact {
+        for argument in &predicate.arguments {
+            check_constant(argument, source, warnings);
+        }
+    }
+}
+
+fn check_constant(fact: &Fact, source: Option<&SourceLocation>, warnings: &mut Vec<LintWarning>) {
+    match fact {
+        Fact::Atomic(atomic) if atomic.name.starts_with(|ch: char| ch.is_uppercase()) => {
+            warnings.push(LintWarning::new(source, "capitalised-constant", format!("{} looks like a variable; variables are written {}?", atomic.name, atomic.name.to_lowercase())));
+        }
+        Fact::Predicate(_) => check_arguments(fact, source, warnings),
+        _ => {}
+    }
+}
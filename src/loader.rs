@@ -0,0 +1,344 @@
+use std::{collections::{HashMap, HashSet}, fs, path::{Path, PathBuf}};
+
+use crate::datalog;
+use crate::import::DataSource;
+use crate::knowledge_base::*;
+use crate::storage;
+use crate::type_checker;
+
+/// Builds a single knowledge base out of one or more source files.
+/// `include "path".` directives are resolved relative to the including file; a file that was
+/// already loaded is skipped, and including a file that is still being loaded is reported as a cycle.
+///
+/// A `module name.` declaration qualifies every predicate defined in the rest of the file as `name::predicate`.
+/// Other modules can only reference predicates listed in an `export predicate/arity.` declaration, either fully
+/// qualified (`hr::employee(x?, d?, s?)`) or unqualified after `import hr::employee/3.`. A leading `::` refers
+/// to a predicate outside of any module.
+///
+/// `:- type employee(atom, atom, int).` declares the argument types of a predicate; see `type_checker`.
+///
+/// Files ending in `.pl`, `.dl` or `.datalog` are read as Datalog instead; see `datalog`.
+#[derive(Clone)]
+pub struct Loader {
+    knowledge_base: KnowledgeBase,
+    loaded_files: HashSet<PathBuf>,
+    include_stack: Vec<PathBuf>,
+    exports: HashMap<String, HashSet<(String, usize)>>,
+    references: Vec<ModuleReference>
+}
+/// A use of a qualified predicate from outside its module, checked against the exports once everything is loaded.
+#[derive(Clone)]
+struct ModuleReference {
+    location: SourceLocation,
+    module: String,
+    name: String,
+    arity: usize
+}
+/// The module and imports in effect while loading a single file.
+#[derive(Default)]
+struct Scope {
+    module: Option<String>,
+    imports: HashMap<(String, usize), String>
+}
+impl Default for Loader {
+    fn default() -> Self {
+        Loader::new()
+    }
+}
+impl Loader {
+    pub fn new() -> Self {
+        Loader {
+            knowledge_base: KnowledgeBase::new(),
+            loaded_files: HashSet::new(),
+            include_stack: Vec::new(),
+            exports: HashMap::new(),
+            references: Vec::new()
+        }
+    }
+    pub fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        let canonical_path: PathBuf = fs::canonicalize(path).map_err(|error| format!("Failed to read file '{}': {}", path.display(), error))?;
+        if let Some(position) = self.include_stack.iter().position(|file: &PathBuf| file == &canonical_path) {
+            let cycle: Vec<String> = self.include_stack[position..].iter().chain([&canonical_path]).map(|file: &PathBuf| file.display().to_string()).collect();
+            return Err(format!("Include cycle: {}", cycle.join(" -> ")));
+        }
+        if !self.loaded_files.insert(canonical_path.clone()) { return Ok(()); }
+        if storage::is_saved_knowledge_base(path) {
+            return Err(format!("'{}' is a saved knowledge base, it can only be given as the first file", path.display()));
+        }
+        let content: String = fs::read_to_string(path).map_err(|error| format!("Failed to read file '{}': {}", path.display(), error))?;
+        self.include_stack.push(canonical_path);
+        let result: Result<(), String> = if datalog::is_datalog_file(path) {
+            self.load_datalog(&content, path)
+        } else {
+            self.load_source(&content, path)
+        };
+        self.include_stack.pop();
+        result
+    }
+    /// Adds Datalog source, see `KnowledgeBase::load_datalog`. Modules and includes are PIE only.
+    pub fn load_datalog(&mut self, content: &str, file: &Path) -> Result<(), String> {
+        self.knowledge_base.load_datalog(content, file)
+    }
+    /// Adds the facts of a CSV or JSON file, see `KnowledgeBase::import_csv` and `KnowledgeBase::import_json`.
+    pub fn load_data(&mut self, source: &DataSource) -> Result<(), String> {
+        let path: &Path = source.path();
+        let content: String = fs::read_to_string(path).map_err(|error| format!("Failed to read file '{}': {}", path.display(), error))?;
+        match source {
+            DataSource::Csv { predicate, .. } => self.knowledge_base.import_csv(predicate, &content, path)?,
+            DataSource::Json { predicate, .. } => self.knowledge_base.import_json(predicate.as_deref(), &content, path)?
+        };
+        Ok(())
+    }
+    /// A loader that adds to an existing knowledge base, e.g. to load more files into a running session.
+    pub fn with_knowledge_base(knowledge_base: KnowledgeBase) -> Self {
+        Loader { knowledge_base, ..Loader::new() }
+    }
+    /// Loads the files and then the facts of the data files, see `load_sources`, keeping the loader so that more
+    /// can be loaded later.
+    pub fn from_sources(paths: &[String], data: &[DataSource]) -> Result<Self, String> {
+        let (mut loader, paths) = match paths.split_first() {
+            Some((first, rest)) if storage::is_saved_knowledge_base(Path::new(first)) => {
+                let mut knowledge_base: KnowledgeBase = KnowledgeBase::restore(Path::new(first))?;
+                if !rest.is_empty() || !data.is_empty() {
+                    knowledge_base.clear_working_memory();
+                }
+                (Loader::with_knowledge_base(knowledge_base), rest)
+            }
+            _ => (Loader::new(), paths)
+        };
+        for path in paths {
+            loader.load_file(Path::new(path))?;
+        }
+        for source in data {
+            loader.load_data(source)?;
+        }
+        Ok(loader)
+    }
+    /// Replaces the knowledge base that is loaded into, keeping the files already loaded and the modules' exports,
+    /// e.g. to load into the current state of a session after it was changed.
+    pub fn set_knowledge_base(&mut self, knowledge_base: KnowledgeBase) {
+        self.knowledge_base = knowledge_base;
+    }
+    /// Every file read so far, including those reached through `include`, even if loading failed part way.
+    pub fn loaded_files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.loaded_files.iter()
+    }
+    pub fn load_source(&mut self, content: &str, file: &Path) -> Result<(), String> {
+        let mut scope: Scope = Scope::default();
+        for source_line in split_lines(content) {
+            let location: SourceLocation = SourceLocation::new(file.to_path_buf(), source_line.line);
+            let line: &str = &source_line.text;
+            let raw_line: &str = &source_line.raw;
+            if line.is_empty() || line.starts_with('#') { continue; }
+            if let Some(target) = line.strip_prefix("include ") {
+                let target: &str = Self::parse_include(target).map_err(|error| format!("{}: {}", location, error))?;
+                let base: &Path = file.parent().unwrap_or(Path::new("."));
+                self.load_file(&base.join(target)).map_err(|error| format!("{}\n  included from {}", error, location))?;
+            } else if let Some(name) = line.strip_prefix("module ") {
+                let name: &str = name.trim().trim_end_matches('.').trim();
+                if name.is_empty() || name.contains("::") || name.contains(char::is_whitespace) {
+                    return Err(format!("{}: Invalid module name '{}'", location, name));
+                }
+                self.exports.entry(name.to_string()).or_default();
+                scope = Scope { module: Some(name.to_string()), imports: HashMap::new() };
+            } else if let Some(list) = line.strip_prefix("export ") {
+                let Some(module) = scope.module.clone() else {
+                    return Err(format!("{}: export is only allowed inside a module", location));
+                };
+                for specification in Self::parse_list(list) {
+                    let (name, arity) = Self::parse_specification(specification).map_err(|error| format!("{}: {}", location, error))?;
+                    if name.contains("::") {
+                        return Err(format!("{}: Exported predicate '{}' must not be qualified", location, name));
+                    }
+                    self.exports.entry(module.clone()).or_default().insert((name.to_string(), arity));
+                }
+            } else if let Some(list) = line.strip_prefix("import ") {
+                for specification in Self::parse_list(list) {
+                    let (name, arity) = Self::parse_specification(specification).map_err(|error| format!("{}: {}", location, error))?;
+                    let Some((module, local_name)) = name.rsplit_once("::") else {
+                        return Err(format!("{}: Imported predicate '{}' must be qualified with its module", location, name));
+                    };
+                    self.reference(&scope, &location, name, arity);
+                    scope.imports.insert((local_name.to_string(), arity), format!("{}::{}", module, local_name));
+                }
+            } else if let Some(declaration) = line.strip_prefix(":- type ") {
+                let mut declaration: TypeDeclaration = TypeDeclaration::parse(declaration.trim().trim_end_matches('.').trim()).map_err(|error| format!("{}: {}", location, error))?;
+                declaration.name = Self::qualify_definition(&scope, &location, &declaration.name)?;
+                self.knowledge_base.add_type_declaration(declaration).map_err(|error| format!("{}: {}", location, error))?;
+            } else if line.contains("->") {
+                let parts: Vec<&str> = line.split("->").collect();
+                if parts.len() != 2 {
+                    return Err(format!("{}: Invalid rule syntax", location));
+                }
+                let head: &str = parts[1].trim().trim_end_matches('.').trim();
+                let body: &str = parts[0].trim();
+                let mut rule: Rule = Rule::parse(body, head).map_err(|error| format!("{}: {}", location, error))?;
+                if let Err(error) = rule.check_safety() {
+                    return Err(Self::unsafe_rule_diagnostic(&location, raw_line, &error));
+                }
+                self.resolve_definition(&scope, &location, &mut rule.consequent)?;
+                for index in rule.literal_indices() {
+                    if let AntecedentItem::Fact(fact) = &mut rule.antecedents[index] {
+                        self.resolve_reference(&scope, &location, fact);
+                    }
+                }
+                self.knowledge_base.add_axiomatic_rule_from(rule, location);
+            } else if line.ends_with('.') {
+                let fact: &str = line.trim_end_matches('.').trim();
+                if !fact.is_empty() {
+                    let mut fact: Fact = Fact::parse(fact).map_err(|error| format!("{}: {}", location, error))?;
+                    self.resolve_definition(&scope, &location, &mut fact)?;
+                    self.knowledge_base.add_axiomatic_fact_from(fact, location);
+                }
+            } else {
+                return Err(format!("{}: Invalid syntax - must end with period or contain implication", location));
+            }
+        }
+        Ok(())
+    }
+    /// Checks that every predicate used across module boundaries is exported and that facts and rules
+    /// match their type declarations, and returns the loaded knowledge base.
+    pub fn finish(mut self) -> Result<KnowledgeBase, String> {
+        self.take()
+    }
+    /// Like `finish`, but keeps the loader with an empty knowledge base, so that files already loaded are skipped and
+    /// the modules stay visible when more is loaded after `set_knowledge_base`.
+    pub fn take(&mut self) -> Result<KnowledgeBase, String> {
+        for reference in &self.references {
+            let Some(exports) = self.exports.get(&reference.module) else {
+                return Err(format!("{}: Unknown module '{}'", reference.location, reference.module));
+            };
+            if !exports.contains(&(reference.name.clone(), reference.arity)) {
+                return Err(format!("{}: Predicate '{}::{}/{}' is not exported by module '{}'", reference.location, reference.module, reference.name, reference.arity, reference.module));
+            }
+        }
+        type_checker::check(&self.knowledge_base)?;
+        Ok(std::mem::take(&mut self.knowledge_base))
+    }
+    /// Qualifies the name of a fact or rule head, which must belong to the current module.
+    fn resolve_definition(&self, scope: &Scope, location: &SourceLocation, fact: &mut Fact) -> Result<(), String> {
+        let Some((name, _)) = fact.signature() else { return Ok(()); };
+        let resolved_name: String = Self::qualify_definition(scope, location, name)?;
+        Self::rename(fact, resolved_name);
+        Ok(())
+    }
+    fn qualify_definition(scope: &Scope, location: &SourceLocation, name: &str) -> Result<String, String> {
+        if let Some(global_name) = name.strip_prefix("::") {
+            Ok(global_name.to_string())
+        } else if let Some((module, _)) = name.rsplit_once("::") {
+            if scope.module.as_deref() != Some(module) {
+                return Err(format!("{}: Cannot define '{}' outside of module '{}'", location, name, module));
+            }
+            Ok(name.to_string())
+        } else {
+            match &scope.module {
+                Some(module) => Ok(format!("{}::{}", module, name)),
+                None => Ok(name.to_string())
+            }
+        }
+    }
+    /// Qualifies the name of a body literal, resolving imports and remembering references to other modules.
+    fn resolve_reference(&mut self, scope: &Scope, location: &SourceLocation, fact: &mut Fact) {
+        let Some((name, arity)) = fact.signature() else { return; };
+        let resolved_name: String = if let Some(global_name) = name.strip_prefix("::") {
+            global_name.to_string()
+        } else if name.contains("::") {
+            self.reference(scope, location, name, arity);
+            name.to_string()
+        } else if let Some(imported_name) = scope.imports.get(&(name.to_string(), arity)) {
+            imported_name.clone()
+        } else {
+            match &scope.module {
+                Some(module) => format!("{}::{}", module, name),
+                None => name.to_string()
+            }
+        };
+        Self::rename(fact, resolved_name);
+    }
+    fn reference(&mut self, scope: &Scope, location: &SourceLocation, qualified_name: &str, arity: usize) {
+        let Some((module, name)) = qualified_name.rsplit_once("::") else { return; };
+        if scope.module.as_deref() == Some(module) { return; }
+        self.references.push(ModuleReference { location: location.clone(), module: module.to_string(), name: name.to_string(), arity });
+    }
+    fn rename(fact: &mut Fact, name: String) {
+        match fact {
+            Fact::Atomic(atomic) => atomic.name = name,
+            Fact::Predicate(predicate) => predicate.name = name,
+            _ => {}
+        }
+    }
+    /// Reports an unsafe rule with the column of the offending variable and a caret underneath it.
+    fn unsafe_rule_diagnostic(location: &SourceLocation, raw_line: &str, error: &SafetyError) -> String {
+        let search_start: usize = if error.in_head { raw_line.find("->").unwrap_or(0) } else { 0 };
+        let needle: String = error.variable.to_string();
+        let is_boundary = |index: usize| raw_line[..index].chars().next_back().is_none_or(|ch: char| !(ch.is_alphanumeric() || ch == '_'));
+        let Some(index) = raw_line[search_start..].match_indices(&needle).map(|(index, _)| search_start + index).find(|index: &usize| is_boundary(*index)) else {
+            return format!("{}: Unsafe rule: {}", location, error);
+        };
+        let column: usize = raw_line[..index].chars().count() + 1;
+        let indentation: String = raw_line[..index].chars().map(|ch: char| if ch == '\t' { '\t' } else { ' ' }).collect();
+        format!("{}:{}: Unsafe rule: {}\n  {}\n  {}{}", location, column, error, raw_line, indentation, "^".repeat(needle.chars().count()))
+    }
+    fn parse_include(target: &str) -> Result<&str, String> {
+        let target: &str = target.trim().trim_end_matches('.').trim();
+        if target.len() >= 2 && target.starts_with('"') && target.ends_with('"') {
+            Ok(&target[1..target.len()-1])
+        } else {
+            Err(String::from("Invalid include syntax - expected include \"path\"."))
+        }
+    }
+    fn parse_list(list: &str) -> impl Iterator<Item = &str> {
+        list.trim().trim_end_matches('.').split(',').map(str::trim).filter(|item: &&str| !item.is_empty())
+    }
+    fn parse_specification(specification: &str) -> Result<(&str, usize), String> {
+        let (name, arity) = specification.rsplit_once('/').ok_or_else(|| format!("Invalid predicate '{}' - expected name/arity", specification))?;
+        let arity: usize = arity.trim().parse().map_err(|_| format!("Invalid arity in '{}'", specification))?;
+        Ok((name.trim(), arity))
+    }
+}
+
+/// A logical line of a knowledge-base file. A statement continues on the next physical line when it ends with,
+/// or the next line starts with, `&`, `|` or `->`; comments and blank lines always stand on their own.
+pub struct SourceLine {
+    /// The 1-based number of the first physical line.
+    pub line: usize,
+    /// The trimmed statement, with continuation lines joined by a single space.
+    pub text: String,
+    /// The untrimmed physical line for single-line statements, otherwise the same as `text`.
+    pub raw: String
+}
+impl SourceLine {
+    pub fn is_comment(&self) -> bool {
+        self.text.starts_with('#')
+    }
+}
+
+pub fn split_lines(content: &str) -> Vec<SourceLine> {
+    const OPERATORS: [&str; 3] = ["&", "|", "->"];
+    let continues = |text: &str| OPERATORS.iter().any(|operator: &&str| text.ends_with(operator));
+    let is_continuation = |text: &str| OPERATORS.iter().any(|operator: &&str| text.starts_with(operator));
+    let mut lines: Vec<SourceLine> = Vec::new();
+    for (line_number, raw_line) in content.lines().enumerate() {
+        let text: &str = raw_line.trim();
+        if let Some(previous) = lines.last_mut()
+            && !text.is_empty() && !text.starts_with('#') && !previous.text.is_empty() && !previous.is_comment()
+            && (continues(&previous.text) || is_continuation(text)) {
+            previous.text = format!("{} {}", previous.text, text);
+            previous.raw = previous.text.clone();
+            continue;
+        }
+        lines.push(SourceLine { line: line_number + 1, text: text.to_string(), raw: raw_line.to_string() });
+    }
+    lines
+}
+
+pub fn load_files(paths: &[String]) -> Result<KnowledgeBase, String> {
+    load_sources(paths, &[])
+}
+
+/// Loads the files and then the facts of the data files into one knowledge base. The first path may be a knowledge
+/// base saved with `pie save`, which the other files are then added to; its derived facts are only kept when there
+/// is nothing else to load.
+pub fn load_sources(paths: &[String], data: &[DataSource]) -> Result<KnowledgeBase, String> {
+    Loader::from_sources(paths, data)?.finish()
+}
@@ -1,44 +1,60 @@
-use std::{env, fs};
+use std::{env, process};
 
-mod inference_engine;
-mod knowledge_base;
+use pie::commands::{self, CommandHelp, COMMANDS, EXIT_ERROR};
+use pie::http::{self, HttpServer};
+use pie::inference_engine::InferenceEngine;
+use pie::language_server::LanguageServer;
+use pie::knowledge_base::KnowledgeBase;
+use pie::loader::Loader;
+use pie::server::{self, Server};
 
-#[cfg(test)]
-mod tests;
-
-use crate::inference_engine::InferenceEngine;
-use crate::knowledge_base::{Fact, KnowledgeBase};
-
-const DEFAULT_FILE: &str = "./examples/default.txt";
+const DEFAULT_HTTP_PORT: u16 = 8080;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let mut file_path: Option<String> = None;
-    let mut debug: bool = false;
-    let mut query_mode: bool = false;
-    let mut prove_fact: Option<String> = None;
 
+    match args.get(1).map(String::as_str) {
+        Some(command) if commands::is_command(command) => {
+            let stdin: std::io::Stdin = std::io::stdin();
+            process::exit(commands::run(command, &args[2..], stdin.lock(), &mut std::io::stdout()));
+        }
+        Some("serve") => return serve(&args[2..]),
+        Some("lsp") => {
+            let stdin: std::io::Stdin = std::io::stdin();
+            if let Err(error) = LanguageServer::new().run(stdin.lock(), std::io::stdout()) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    // The original flags map onto the subcommands: --query is repl, --prove is prove, and otherwise infer.
+    let mut command: &str = "infer";
+    let mut command_args: Vec<String> = Vec::new();
     let mut index: usize = 1;
     while index < args.len() {
         match args[index].as_str() {
             "--file" | "-f" => {
                 if index + 1 < args.len() {
-                    file_path = Some(args[index + 1].clone());
+                    command_args.push(args[index + 1].clone());
                     index += 1;
                 } else {
                     eprintln!("Error: --file requires a path argument");
-                    return;
+                    process::exit(EXIT_ERROR);
                 }
             }
-            "--debug" | "-d" => debug = true,
-            "--query" | "-q" => query_mode = true,
+            "--debug" | "-d" => command_args.push(String::from("--debug")),
+            "--query" | "-q" => command = "repl",
             "--prove" | "-p" => {
                 if index + 1 < args.len() {
-                    prove_fact = Some(args[index + 1].clone());
+                    command = "prove";
+                    command_args.insert(0, args[index + 1].clone());
                     index += 1;
                 } else {
                     eprintln!("Error: --prove requires a fact argument");
-                    return;
+                    process::exit(EXIT_ERROR);
                 }
             }
             "--help" | "-h" => {
@@ -46,120 +62,160 @@ fn main() {
                 return;
             }
             _ => {
-                if file_path.is_none() && !args[index].starts_with("--") && !args[index].starts_with("-") {
-                    file_path = Some(args[index].clone());
+                if !args[index].starts_with('-') {
+                    command_args.push(args[index].clone());
                 } else {
                     eprintln!("Unknown argument: {}", args[index]);
                     print_help();
-                    return;
+                    process::exit(EXIT_ERROR);
                 }
             }
         }
         index += 1;
     }
-    let knowledge_base: KnowledgeBase = if let Some(path) = file_path {
-        match parse_file(&path) {
-            Ok(knowledge_base) => knowledge_base,
-            Err(error) => {
-                eprintln!("Error reading file '{}': {}", path, error);
-                return;
+    let stdin: std::io::Stdin = std::io::stdin();
+    process::exit(commands::run(command, &command_args, stdin.lock(), &mut std::io::stdout()));
+}
+
+fn serve(args: &[String]) {
+    let mut file_paths: Vec<String> = Vec::new();
+    let mut port: Option<u16> = None;
+    let mut http: bool = false;
+    let mut index: usize = 0;
+    while index < args.len() {
+        if args[index] == "--port" {
+            match args.get(index + 1).and_then(|value: &String| value.parse().ok()) {
+                Some(number) => port = Some(number),
+                None => {
+                    eprintln!("Error: --port requires a port number");
+                    process::exit(2);
+                }
             }
+            index += 1;
+        } else if args[index] == "--http" {
+            http = true;
+        } else if args[index] == "--help" || args[index] == "-h" {
+            return print_help();
+        } else {
+            file_paths.push(args[index].clone());
         }
-    } else {
-        match parse_file(DEFAULT_FILE) {
-            Ok(knowledge_base) => knowledge_base,
+        index += 1;
+    }
+    let load = |file_paths: &[String]| -> Server {
+        match Loader::from_sources(file_paths, &[]).and_then(|mut loader: Loader| loader.take().map(|knowledge_base: KnowledgeBase| (loader, knowledge_base))) {
+            Ok((loader, knowledge_base)) => Server::new(InferenceEngine::new(knowledge_base)).with_loader(loader),
             Err(error) => {
-                eprintln!("Error reading file '{}': {}", DEFAULT_FILE, error);
-                return;
+                eprintln!("Error: {}", error);
+                process::exit(2);
             }
         }
     };
-
-    let mut inference_engine: InferenceEngine = InferenceEngine::new(knowledge_base);
-    inference_engine.set_debug(debug);
-
-    if query_mode {
-        use std::io::{self, Write};
-
-        println!("PIE 0.1.0");
-        println!("Type 'quit' to exit.");
-
-        inference_engine.set_debug(false);
-        inference_engine.infer();
-
-        loop {
-            print!("> ");
-            
-            let mut input: String = String::new();
-            io::stdout().flush().unwrap();
-            io::stdin().read_line(&mut input).unwrap();
-            
-            let input: &str = input.trim();
-            if input.eq_ignore_ascii_case("quit") || input.eq_ignore_ascii_case("exit") { break; }
-            if input.is_empty() { continue; }
-            
-            match Fact::parse(input) {
-                Ok(fact) => println!("   {}", inference_engine.query(&fact).replace("\n", "\n   ")),
-                Err(error) => eprintln!("  Error: {}\n", error)
-            }
+    let result: Result<(), String> = if http {
+        // name=path,path... arguments name the knowledge bases; any other paths make up one named "default".
+        let mut http_server: HttpServer = HttpServer::new();
+        let (named, unnamed): (Vec<&String>, Vec<&String>) = file_paths.iter().partition(|path: &&String| path.contains('='));
+        for argument in named {
+            let (name, paths) = argument.split_once('=').expect("partitioned on '='");
+            http_server.add(name, load(&paths.split(',').map(str::to_string).collect::<Vec<String>>()));
         }
-    } else if let Some(fact_str) = prove_fact {
-        match Fact::parse(&fact_str) {
-            Ok(fact) => println!("{} is {}", fact, inference_engine.prove(&fact)),
-            Err(error) => eprintln!("Error parsing fact '{}': {}", fact_str, error)
+        if !unnamed.is_empty() {
+            http_server.add("default", load(&unnamed.into_iter().cloned().collect::<Vec<String>>()));
         }
+        http::listen(http_server, port.unwrap_or(DEFAULT_HTTP_PORT))
     } else {
-        inference_engine.infer();
-        if inference_engine.knowledge_base.working_memory.is_empty() {
-            println!("No new facts")
-        } else {
-            println!("New facts:");
-            for fact in &inference_engine.knowledge_base.working_memory {
-                println!("  {}", fact);
+        let mut server: Server = load(&file_paths);
+        match port {
+            Some(port) => server::listen(server, port),
+            None => {
+                let stdin: std::io::Stdin = std::io::stdin();
+                server.run(stdin.lock(), std::io::stdout())
             }
         }
+    };
+    if let Err(error) = result {
+        eprintln!("Error: {}", error);
+        process::exit(1);
     }
 }
 
-fn parse_file(file_path: &str) -> Result<KnowledgeBase, String> {
-    let content: String = fs::read_to_string(file_path).map_err(|error| format!("Failed to read file: {}", error))?;
+/// The commands `main` runs itself rather than through `commands::run`.
+const OTHER_COMMANDS: [CommandHelp; 3] = [
+    CommandHelp {
+        name: "serve",
+        usage: "serve [--port <port>] [<path>...]",
+        description: "Answer JSON-RPC requests (load, assert, retract, infer, prove, query), one per line, over stdio or on 127.0.0.1:<port>, keeping the knowledge base loaded"
+    },
+    CommandHelp {
+        name: "serve",
+        usage: "serve --http [--port <port>] [<name>=<path>[,<path>...]]... [<path>...]",
+        description: "Serve the named knowledge bases (paths without a name make up \"default\") over a REST API on 127.0.0.1:<port>, 8080 by default; see the README"
+    },
+    CommandHelp { name: "lsp", usage: "lsp", description: "Run a language server over stdio for editors" }
+];
 
-    let mut rules: Vec<(&str, &str)> = Vec::new();
-    let mut facts: Vec<&str> = Vec::new();
+/// The options of `pie --help`, with their descriptions.
+const OPTIONS: [(&str, &str); 14] = [
+    ("--file <path> or -f <path>", "Path to knowledge base file, may be repeated (optional if paths are given directly)"),
+    ("--format <format>", "Output format of the commands: text (default), json (an array of records), jsonl (one record per line) or csv; results are always sorted"),
+    ("--assume <fact>", "Run the command as if the fact were given (or removed, for !fact), may be repeated; infer then prints the facts that would be added (+) or removed (-)"),
+    ("--facts-csv <predicate>=<path>", "Add a fact of the predicate for every row of a CSV file after its header, with integers as numbers and other values as atoms; may be repeated"),
+    ("--facts-json [<predicate>=]<path>", "Add facts from a JSON array of arrays or objects, or without a predicate from an object of predicates such as {\"employee\": [...]}"),
+    ("--log <path>", "Append every change to the knowledge base to an event log, one JSON object per line, for auditing and `pie replay`"),
+    ("--log-source <tag>", "Who the logged changes are made by (default: $USER)"),
+    ("--trace <sink>", "Report each step of inference to stderr: pretty (one line per step, goals indented), jsonl (one JSON object per step) or count (totals)"),
+    ("--trace-events <kind>,...", "Only trace these steps: rule, unify, fire, done, add, remove, enter, exit"),
+    ("--profile", "Print to stderr how long each rule took and how much work it did, slowest first"),
+    ("--debug or -d", "Same as --trace pretty"),
+    ("--query or -q", "Enter interactive query mode, type 'quit' to exit"),
+    ("--prove <fact> or -p <fact>", "Prove a specific fact"),
+    ("--help or -h", "Show this help message")
+];
 
-    for (line_number, line) in content.lines().enumerate() {
-        let line: &str = line.trim();
-        if line.is_empty() || line.starts_with('#') { continue; }
-        if line.contains("->") {
-            let parts: Vec<&str> = line.split("->").collect();
-            if parts.len() != 2 {
-                return Err(format!("Line {}: Invalid rule syntax", line_number + 1));
-            }
-            let head: &str = parts[1].trim().trim_end_matches('.').trim();
-            let body: &str = parts[0].trim();
-            rules.push((body, head));
-        } else if line.ends_with('.') {
-            let fact: &str = line.trim_end_matches('.').trim();
-            if !fact.is_empty() {
-                facts.push(fact);
-            }
-        } else {
-            return Err(format!("Line {}: Invalid syntax - must end with period or contain implication", line_number + 1));
-        }
-    }
-    Ok(KnowledgeBase::from_strings(facts, rules)?)
-}
+/// The column the descriptions of `pie --help` start at, and the width they wrap at.
+const HELP_INDENT: usize = 32;
+const HELP_WIDTH: usize = 110;
 
 fn print_help() {
-    println!("Usage: pie <path> [OPTIONS]\n");
+    println!("Usage: pie <command> [<goal>] [<path>...] [--format text|json|jsonl|csv] [--trace <sink>]");
+    println!("       pie <path>... [OPTIONS]\n");
+    println!("COMMANDS:");
+    let commands: Vec<(&str, &str)> = COMMANDS.iter().chain(OTHER_COMMANDS.iter())
+        .map(|command: &CommandHelp| (command.usage, command.description))
+        .collect();
+    print_entries(&commands);
+    let names: Vec<&str> = COMMANDS.iter().map(|command: &CommandHelp| command.name).collect();
+    println!("\n{} and {} exit with 2 when the files or arguments are invalid.\n", names[..names.len() - 1].join(", "), names[names.len() - 1]);
     println!("OPTIONS:");
-    println!("  --file <path> or -f <path>    Path to knowledge base file (optional if path is first argument)");
-    println!("  --debug or -d                 Enable debug mode");
-    println!("  --query or -q                 Enter interactive query mode, type 'quit' to exit");
-    println!("  --prove <fact> or -p <fact>   Prove a specific fact");
-    println!("  --help or -h                  Show this help message\n");
-    println!("FILE FORMAT:");
+    print_entries(&OPTIONS);
+    println!("\nFILE FORMAT:");
     println!("  Comments: Lines starting with #");
     println!("  Facts:    parent(anna, bob).");
     println!("  Rules:    parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).");
+    println!("  Includes: include \"shared.txt\".   (relative to the including file)");
+    println!("  Modules:  module hr.  export employee/3.  import hr::employee/3.");
+    println!("  Types:    :- type employee(atom, atom, int).   (atom, int or any)");
+}
+
+/// Prints each usage with its description wrapped in a column beside it, or below it when the usage is too long.
+fn print_entries(entries: &[(&str, &str)]) {
+    for (usage, description) in entries {
+        let mut line: String = format!("  {}", usage);
+        if line.len() + 1 > HELP_INDENT {
+            println!("{}", line);
+            line.clear();
+        }
+        for word in description.split(' ') {
+            if line.len() > HELP_INDENT && line.len() + 1 + word.len() > HELP_WIDTH {
+                println!("{}", line);
+                line.clear();
+            }
+            if line.len() < HELP_INDENT {
+                line = format!("{:width$}{}", line, word, width = HELP_INDENT);
+            } else {
+                line.push(' ');
+                line.push_str(word);
+            }
+        }
+        println!("{}", line);
+    }
 }
@@ -0,0 +1,159 @@
+use crate::formatter::format_rule;
+use crate::inference_engine::{Explanation, QueryMatch, Reason};
+use crate::json::Json;
+use crate::knowledge_base::*;
+
+/// How a command prints its results. Every machine-readable format is a list of flat records: a JSON array,
+/// one JSON object per line, or CSV with a header row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Jsonl,
+    Csv
+}
+impl Format {
+    pub fn parse(string: &str) -> Result<Self, String> {
+        match string {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "jsonl" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("Unknown format '{}', expected text, json, jsonl or csv", string))
+        }
+    }
+}
+
+/// The result of a command: human-readable text and the same content as records for the other formats.
+pub struct Report {
+    pub text: String,
+    pub records: Vec<Json>
+}
+impl Report {
+    pub fn new(text: String, records: Vec<Json>) -> Self {
+        Report { text, records }
+    }
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.text.clone(),
+            Format::Json => format!("{}\n", Json::Array(self.records.clone())),
+            Format::Jsonl => self.records.iter().map(|record: &Json| format!("{}\n", record)).collect(),
+            Format::Csv => to_csv(&self.records)
+        }
+    }
+}
+
+/// A fact as a record: its text, predicate name and arguments. Atomic facts have no arguments.
+pub fn fact_record(fact: &Fact) -> Json {
+    Json::Object(fact_fields(fact))
+}
+
+/// The fields of `fact_record`, for records that add their own.
+pub fn fact_fields(fact: &Fact) -> Vec<(String, Json)> {
+    let (predicate, arguments): (String, Vec<Json>) = match fact {
+        Fact::Predicate(predicate) => (predicate.name.clone(), predicate.arguments.iter().map(term_value).collect()),
+        Fact::Atomic(atomic) => (atomic.name.clone(), Vec::new()),
+        _ => (fact.to_string(), Vec::new())
+    };
+    vec![
+        (String::from("fact"), Json::string(fact.to_string())),
+        (String::from("predicate"), Json::string(predicate)),
+        (String::from("negated"), Json::Bool(fact.is_negative())),
+        (String::from("arguments"), Json::Array(arguments))
+    ]
+}
+
+/// Numbers stay numbers, everything else is written as text.
+pub fn term_value(term: &Fact) -> Json {
+    match term {
+        Fact::Number(number) => Json::Number(number.value as f64),
+        _ => Json::string(term.to_string())
+    }
+}
+
+/// Query matches as records of the matching fact and the bindings of the query's variables.
+pub fn query_report(query: &Fact, matches: &[QueryMatch]) -> Report {
+    let text: String = match (matches.is_empty(), query.is_negative()) {
+        (true, true) => String::from("Yes\n"),
+        (true, false) => String::from("No\n"),
+        _ => matches.iter().map(|query_match: &QueryMatch| format!("{}\n", query_match.fact)).collect()
+    };
+    let records: Vec<Json> = matches.iter().map(|query_match: &QueryMatch| Json::object(vec![
+        ("fact", Json::string(query_match.fact.to_string())),
+        ("bindings", Json::Object(query_match.bindings.iter().map(|(name, value)| (name.clone(), term_value(value))).collect()))
+    ])).collect();
+    Report::new(text, records)
+}
+
+/// Explanation trees as one record per node, in depth-first order.
+pub fn explanation_report(explanations: &[Explanation]) -> Report {
+    fn collect(explanation: &Explanation, depth: usize, records: &mut Vec<Json>) {
+        let (reason, rule): (&str, Json) = match &explanation.reason {
+            Reason::Given => ("given", Json::Null),
+            Reason::NotKnown => ("not known", Json::Null),
+            Reason::Derived { rule, .. } => ("derived", Json::string(format_rule(rule).replace("\n    ", " "))),
+        };
+        records.push(Json::object(vec![("depth", Json::Number(depth as f64)), ("fact", Json::string(explanation.fact.to_string())), ("reason", Json::string(reason)), ("rule", rule)]));
+        if let Reason::Derived { premises, .. } = &explanation.reason {
+            for premise in premises {
+                collect(premise, depth + 1, records);
+            }
+        }
+    }
+    let mut records: Vec<Json> = Vec::new();
+    for explanation in explanations {
+        collect(explanation, 0, &mut records);
+    }
+    Report::new(explanations.iter().map(Explanation::to_string).collect(), records)
+}
+
+/// Flattens records into CSV. Columns follow the order in which keys first appear; an array becomes numbered
+/// columns (`arguments1`, `arguments2`, ...) and a nested object contributes its own keys as columns.
+fn to_csv(records: &[Json]) -> String {
+    let rows: Vec<Vec<(String, String)>> = records.iter().map(flatten).collect();
+    let mut columns: Vec<String> = Vec::new();
+    for row in &rows {
+        for (column, _) in row {
+            if !columns.contains(column) { columns.push(column.clone()); }
+        }
+    }
+    if columns.is_empty() { return String::new(); }
+    let mut csv: String = columns.iter().map(|column: &String| csv_field(column)).collect::<Vec<String>>().join(",") + "\n";
+    for row in &rows {
+        let fields: Vec<String> = columns.iter().map(|column: &String| {
+            row.iter().find(|(key, _)| key == column).map_or(String::new(), |(_, value)| csv_field(value))
+        }).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn flatten(record: &Json) -> Vec<(String, String)> {
+    let Json::Object(pairs) = record else { return vec![(String::from("value"), cell(record))]; };
+    let mut row: Vec<(String, String)> = Vec::new();
+    for (key, value) in pairs {
+        match value {
+            Json::Array(values) => row.extend(values.iter().enumerate().map(|(index, value)| (format!("{}{}", key, index + 1), cell(value)))),
+            Json::Object(nested) => row.extend(nested.iter().map(|(nested_key, value)| (nested_key.clone(), cell(value)))),
+            _ => row.push((key.clone(), cell(value)))
+        }
+    }
+    row
+}
+
+fn cell(value: &Json) -> String {
+    match value {
+        Json::Null => String::new(),
+        Json::String(string) => string.clone(),
+        _ => value.to_string()
+    }
+}
+
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,112 @@
+use std::{collections::HashMap, io::Write, time::{Duration, Instant}};
+
+use crate::knowledge_base::*;
+use crate::trace::{rule_text, TraceEvent, Tracer};
+
+/// What a rule cost across every `infer` and `prove` a `Profiler` saw.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RuleStatistics {
+    /// Substitutions for which the whole body held.
+    pub activations: usize,
+    /// Unifications of the body's literals with known facts, and of the head with goals.
+    pub unifications: usize,
+    /// Substitutions matching the body's literals that were tried.
+    pub substitutions: usize,
+    /// New facts the rule concluded.
+    pub derived: usize,
+    /// Wall time spent on the rule. When proving, this includes the subgoals proven through other rules.
+    pub time: Duration
+}
+
+/// A tracer that collects `RuleStatistics` for every rule, keyed by the rule's text. With `reporting_to`, the report
+/// is written out by `finish`. Timing uses `Instant`, which is not available on wasm32, so neither is this module.
+#[derive(Default)]
+pub struct Profiler {
+    rules: HashMap<String, RuleStatistics>,
+    /// The rules being worked on, innermost last, with when the engine started on them.
+    active: Vec<(String, Instant)>,
+    /// The rule that last concluded each fact in this pass, credited once the fact is added.
+    conclusions: HashMap<Fact, String>,
+    /// Whether the last event added a fact. The added facts of a pass come together at its end, so the next other
+    /// event starts a new pass and the conclusions of the last one are forgotten.
+    adding: bool,
+    output: Option<Box<dyn Write + Send>>
+}
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler::default()
+    }
+    pub fn reporting_to(output: impl Write + Send + 'static) -> Self {
+        let mut profiler: Profiler = Profiler::new();
+        profiler.output = Some(Box::new(output));
+        profiler
+    }
+    /// The statistics of every rule seen, hottest first: by time, then by unifications.
+    pub fn statistics(&self) -> Vec<(&str, &RuleStatistics)> {
+        let mut rules: Vec<(&str, &RuleStatistics)> = self.rules.iter().map(|(rule, statistics)| (rule.as_str(), statistics)).collect();
+        rules.sort_by(|(left_rule, left), (right_rule, right)| right.time.cmp(&left.time).then(right.unifications.cmp(&left.unifications)).then(left_rule.cmp(right_rule)));
+        rules
+    }
+    /// A table of `statistics`, one rule per line.
+    pub fn report(&self) -> String {
+        if self.rules.is_empty() { return String::from("No rules were evaluated\n"); }
+        let mut report: String = format!("{:>10}  {:>11}  {:>12}  {:>13}  {:>7}  rule\n", "time (ms)", "activations", "unifications", "substitutions", "derived");
+        for (rule, statistics) in self.statistics() {
+            report.push_str(&format!("{:>10.3}  {:>11}  {:>12}  {:>13}  {:>7}  {}\n",
+                statistics.time.as_secs_f64() * 1000.0, statistics.activations, statistics.unifications, statistics.substitutions, statistics.derived, rule));
+        }
+        report
+    }
+    fn current(&mut self) -> Option<&mut RuleStatistics> {
+        let (rule, _) = self.active.last()?;
+        self.rules.get_mut(rule)
+    }
+}
+impl Tracer for Profiler {
+    fn event(&mut self, event: &TraceEvent) {
+        let adding: bool = matches!(event, TraceEvent::FactAdded { .. });
+        if self.adding && !adding {
+            self.conclusions.clear();
+        }
+        self.adding = adding;
+        match event {
+            TraceEvent::RuleConsidered { rule } => {
+                let rule: String = rule_text(rule);
+                self.rules.entry(rule.clone()).or_default();
+                self.active.push((rule, Instant::now()));
+            }
+            TraceEvent::UnificationAttempted { .. } => {
+                if let Some(statistics) = self.current() {
+                    statistics.unifications += 1;
+                }
+            }
+            TraceEvent::ActivationFired { fact, .. } => {
+                if let Some(statistics) = self.current() {
+                    statistics.activations += 1;
+                }
+                if let Some((rule, _)) = self.active.last() {
+                    self.conclusions.insert((*fact).clone(), rule.clone());
+                }
+            }
+            TraceEvent::RuleFinished { substitutions, .. } => {
+                if let Some((rule, started)) = self.active.pop()
+                    && let Some(statistics) = self.rules.get_mut(&rule) {
+                    statistics.substitutions += substitutions;
+                    statistics.time += started.elapsed();
+                }
+            }
+            TraceEvent::FactAdded { fact } => {
+                if let Some(rule) = self.conclusions.remove(*fact)
+                    && let Some(statistics) = self.rules.get_mut(&rule) {
+                    statistics.derived += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    fn finish(&mut self) {
+        if let Some(mut output) = self.output.take() {
+            let _ = write!(output, "{}", self.report());
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::{collections::HashSet, io::{BufRead, Write}, path::{Path, PathBuf}};
+
+#[cfg(not(target_arch = "wasm32"))]
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use crate::formatter::format_rule;
+use crate::inference_engine::{InferenceEngine, QueryMatch};
+use crate::knowledge_base::*;
+use crate::import::DataSource;
+use crate::loader::Loader;
+use crate::output::{query_report, Format};
+
+const HELP: &str = "\
+  <pattern>                Query the known facts, e.g. parent(x?, osei)
+  :assert <fact>           Add a fact and show what is derived or no longer derived
+  :retract <fact>          Remove a fact and show what is derived or no longer derived
+  :rule <body> -> <head>   Add a rule
+  :load <path>...          Load more files into the session
+  :reload                  Start over from the files, dropping asserted facts and added rules
+  :facts [<predicate>]     List the known facts, optionally only those of one predicate
+  :rules                   List the rules
+  :infer                   Run inference again and show the newly derived facts
+  :reset                   Clear the derived facts (working memory)
+  :help                    Show this help
+  :quit                    Leave the REPL
+";
+
+/// Where statements typed into the REPL are recorded as coming from.
+const REPL_SOURCE: &str = "<repl>";
+
+/// An interactive session on a knowledge base. Lines starting with `:` are commands, anything else is a query.
+/// Changing the facts or rules re-runs inference from scratch so the derived facts never go stale.
+pub struct Repl {
+    inference_engine: InferenceEngine,
+    file_paths: Vec<String>,
+    data: Vec<DataSource>,
+    /// Remembers the files loaded and the modules' exports, so that loading a file again does not repeat it.
+    loader: Loader,
+    format: Format
+}
+/// What the REPL should do after a line.
+pub enum Outcome {
+    Continue(String),
+    Quit
+}
+impl Repl {
+    pub fn new(inference_engine: InferenceEngine, file_paths: Vec<String>, format: Format) -> Self {
+        let mut repl: Repl = Repl { inference_engine, file_paths, data: Vec::new(), loader: Loader::new(), format };
+        repl.inference_engine.set_tracer(None);
+        repl.inference_engine.infer();
+        repl
+    }
+    /// The CSV and JSON files the session was started with, so that `:reload` reads them again too.
+    pub fn with_data(mut self, data: Vec<DataSource>) -> Self {
+        self.data = data;
+        self
+    }
+    /// The loader the session's files were read with, see `Loader::take`.
+    pub fn with_loader(mut self, loader: Loader) -> Self {
+        self.loader = loader;
+        self
+    }
+    /// Reads lines from `input` until it ends or the user quits, writing every response to `output`.
+    pub fn run(&mut self, mut input: impl BufRead, output: &mut impl Write) -> Result<(), String> {
+        let write_error = |error: std::io::Error| format!("Failed to write output: {}", error);
+        write!(output, "{}", Self::banner()).map_err(write_error)?;
+        loop {
+            write!(output, "> ").and_then(|_| output.flush()).map_err(write_error)?;
+            let mut line: String = String::new();
+            if input.read_line(&mut line).map_err(|error| format!("Failed to read input: {}", error))? == 0 { break; }
+            match self.execute(&line) {
+                Outcome::Continue(response) => write!(output, "{}", response).map_err(write_error)?,
+                Outcome::Quit => break
+            }
+        }
+        Ok(())
+    }
+    /// Like `run`, but reads from the terminal with line editing, keeping the history in `history_path`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_interactive(&mut self, history_path: Option<&Path>) -> Result<(), String> {
+        let mut editor: DefaultEditor = DefaultEditor::new().map_err(|error| format!("Failed to start line editor: {}", error))?;
+        if let Some(history_path) = history_path {
+            let _ = editor.load_history(history_path);
+        }
+        print!("{}", Self::banner());
+        loop {
+            let line: String = match editor.readline("> ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(error) => return Err(format!("Failed to read input: {}", error))
+            };
+            if !line.trim().is_empty() {
+                let _ = editor.add_history_entry(line.as_str());
+            }
+            match self.execute(&line) {
+                Outcome::Continue(response) => print!("{}", response),
+                Outcome::Quit => break
+            }
+        }
+        if let Some(history_path) = history_path {
+            editor.save_history(history_path).map_err(|error| format!("Failed to save history to '{}': {}", history_path.display(), error))?;
+        }
+        Ok(())
+    }
+    #[cfg(target_arch = "wasm32")]
+    pub fn run_interactive(&mut self, _history_path: Option<&Path>) -> Result<(), String> {
+        Err(String::from("Line editing is not available on wasm32"))
+    }
+    fn banner() -> String {
+        String::from("PIE 0.1.0\nType ':help' for commands or 'quit' to exit.\n")
+    }
+    /// Runs one line of input and returns the response to print.
+    pub fn execute(&mut self, line: &str) -> Outcome {
+        let line: &str = line.trim();
+        if line.eq_ignore_ascii_case("quit") || line.eq_ignore_ascii_case("exit") { return Outcome::Quit; }
+        let (command, argument) = line.split_once(char::is_whitespace).map_or((line, ""), |(command, argument)| (command, argument.trim()));
+        let result: Result<String, String> = match command {
+            "" => Ok(String::new()),
+            ":quit" | ":q" | ":exit" => return Outcome::Quit,
+            ":help" | ":h" => Ok(String::from(HELP)),
+            ":assert" => self.assert(argument),
+            ":retract" => self.retract(argument),
+            ":rule" => self.add_rule(argument),
+            ":load" => self.load(argument),
+            ":reload" => self.reload(),
+            ":facts" => Ok(self.list_facts(argument)),
+            ":rules" => Ok(self.list_rules()),
+            ":infer" => Ok(self.infer()),
+            ":reset" => {
+                self.inference_engine.knowledge_base.clear_working_memory();
+                Ok(String::from("  Cleared derived facts\n"))
+            }
+            command if command.starts_with(':') => Err(format!("Unknown command '{}', type :help for the list of commands", command)),
+            _ => self.query(line)
+        };
+        Outcome::Continue(match result {
+            Ok(response) => response,
+            Err(error) => format!("  Error: {}\n\n", error)
+        })
+    }
+    fn query(&self, line: &str) -> Result<String, String> {
+        let query: Fact = Fact::parse(line)?;
+        Ok(match self.format {
+            Format::Text => format!("   {}\n", self.inference_engine.query(&query).replace('\n', "\n   ")),
+            format => {
+                let matches: Vec<QueryMatch> = self.inference_engine.query_matches(&query);
+                query_report(&query, &matches).render(format)
+            }
+        })
+    }
+    fn assert(&mut self, argument: &str) -> Result<String, String> {
+        if argument.is_empty() { return Err(String::from(":assert requires a fact")); }
+        self.load_with(|loader: &mut Loader| loader.load_source(&format!("{}.", argument.trim_end_matches('.')), Path::new(REPL_SOURCE)))
+    }
+    fn retract(&mut self, argument: &str) -> Result<String, String> {
+        let fact: Fact = Fact::parse(argument.trim_end_matches('.').trim())?;
+        if !self.inference_engine.knowledge_base.get_axiomatic_facts().any(|existing: &Fact| existing == &fact) {
+            return Err(format!("{} is not a given fact", fact));
+        }
+        self.change(|mut knowledge_base: KnowledgeBase| {
+            knowledge_base.remove_axiomatic_fact(&fact);
+            Ok(knowledge_base)
+        })
+    }
+    fn add_rule(&mut self, argument: &str) -> Result<String, String> {
+        if !argument.contains("->") { return Err(String::from(":rule requires a rule such as parent(x?, y?) -> related(x?, y?)")); }
+        self.load_with(|loader: &mut Loader| loader.load_source(argument, Path::new(REPL_SOURCE)))
+    }
+    fn load(&mut self, argument: &str) -> Result<String, String> {
+        let paths: Vec<String> = argument.split_whitespace().map(str::to_string).collect();
+        if paths.is_empty() { return Err(String::from(":load requires a file path")); }
+        let response: String = self.load_with(|loader: &mut Loader| paths.iter().try_for_each(|path: &String| loader.load_file(Path::new(path))))?;
+        self.file_paths.extend(paths);
+        Ok(response)
+    }
+    fn reload(&mut self) -> Result<String, String> {
+        let mut loader: Loader = Loader::from_sources(&self.file_paths, &self.data)?;
+        let knowledge_base: KnowledgeBase = loader.take()?;
+        let response: String = self.change(|_| Ok(knowledge_base))?;
+        self.loader = loader;
+        Ok(response)
+    }
+    /// Loads more into the session with its loader. The loader only keeps what was loaded if the change succeeds.
+    fn load_with(&mut self, load: impl FnOnce(&mut Loader) -> Result<(), String>) -> Result<String, String> {
+        let mut loader: Loader = self.loader.clone();
+        let response: String = self.change(|knowledge_base: KnowledgeBase| {
+            loader.set_knowledge_base(knowledge_base);
+            load(&mut loader)?;
+            loader.take()
+        })?;
+        self.loader = loader;
+        Ok(response)
+    }
+    /// Applies a change with `InferenceEngine::change` and reports the derived facts that appeared or disappeared.
+    fn change(&mut self, apply: impl FnOnce(KnowledgeBase) -> Result<KnowledgeBase, String>) -> Result<String, String> {
+        let changes: FactDiff = self.inference_engine.change(apply)?;
+        if changes.is_empty() { return Ok(String::from("  No change in derived facts\n")); }
+        let removed = changes.removed.iter().map(|fact: &Fact| format!("  - {}\n", fact));
+        let added = changes.added.iter().map(|fact: &Fact| format!("  + {}\n", fact));
+        Ok(removed.chain(added).collect())
+    }
+    fn infer(&mut self) -> String {
+        let previous: HashSet<Fact> = self.inference_engine.knowledge_base.working_memory().clone();
+        self.inference_engine.infer();
+        let added: Vec<Fact> = diff(&previous, self.inference_engine.knowledge_base.working_memory()).added;
+        if added.is_empty() { return String::from("  No new facts\n"); }
+        added.iter().map(|fact: &Fact| format!("  + {}\n", fact)).collect()
+    }
+    fn list_facts(&self, predicate: &str) -> String {
+        let knowledge_base: &KnowledgeBase = &self.inference_engine.knowledge_base;
+        let mut facts: Vec<(String, bool)> = knowledge_base.get_facts()
+            .filter(|fact: &&Fact| predicate.is_empty() || fact.signature().is_some_and(|(name, arity)| name == predicate || format!("{}/{}", name, arity) == predicate))
+            .map(|fact: &Fact| (fact.to_string(), knowledge_base.working_memory().contains(fact)))
+            .collect();
+        if facts.is_empty() { return String::from("  No facts\n"); }
+        facts.sort();
+        facts.iter().map(|(fact, derived)| format!("  {}{}\n", fact, if *derived { "   (derived)" } else { "" })).collect()
+    }
+    fn list_rules(&self) -> String {
+        let knowledge_base: &KnowledgeBase = &self.inference_engine.knowledge_base;
+        if knowledge_base.get_rules().next().is_none() { return String::from("  No rules\n"); }
+        knowledge_base.get_rules().enumerate().map(|(index, rule)| {
+            let source: String = knowledge_base.get_rule_source(index).map_or(String::new(), |source: &SourceLocation| format!("   # {}", source));
+            format!("  {}{}\n", format_rule(rule).replace('\n', "\n  "), source)
+        }).collect()
+    }
+}
+
+/// The history file used when none is given: `.pie_history` in the home directory.
+pub fn default_history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".pie_history"))
+}
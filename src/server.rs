@@ -0,0 +1,310 @@
+use std::{io::{BufRead, BufReader, Read, Write}, net::{TcpListener, TcpStream}, panic::{self, AssertUnwindSafe}, path::Path, sync::{Arc, Mutex}, thread};
+
+use crate::inference_engine::{Explanation, InferenceEngine, QueryMatch};
+use crate::json::Json;
+use crate::knowledge_base::*;
+use crate::loader::Loader;
+use crate::output::{explanation_report, fact_record, query_report};
+
+/// Where statements sent to the server are recorded as coming from.
+const SERVER_SOURCE: &str = "<server>";
+
+/// JSON-RPC error codes: the standard ones, and one for requests that were understood but failed, such as a fact
+/// that does not type check.
+pub const PARSE_ERROR: f64 = -32700.0;
+pub const INVALID_REQUEST: f64 = -32600.0;
+pub const METHOD_NOT_FOUND: f64 = -32601.0;
+pub const INVALID_PARAMS: f64 = -32602.0;
+pub const INTERNAL_ERROR: f64 = -32603.0;
+pub const REQUEST_FAILED: f64 = -32000.0;
+
+/// The longest request line the server reads, the same bound as on the body of an HTTP request.
+pub const MAX_LINE_LENGTH: usize = 16 * 1024 * 1024;
+
+/// A JSON-RPC 2.0 server around one inference engine that stays alive across requests, for services that embed PIE.
+/// Messages are one JSON object (or batch array) per line, over stdio with `run` or localhost TCP with `listen`.
+///
+/// | Method    | Params                                             | Result                                      |
+/// |-----------|----------------------------------------------------|---------------------------------------------|
+/// | `load`    | `{"paths": [...]}` and/or `{"source": "...", "syntax": "pie"\|"datalog"}` | `{"added": [...], "removed": [...]}` |
+/// | `assert`  | `{"facts": ["parent(ama, osei)", ...]}`            | `{"added": [...], "removed": [...]}`        |
+/// | `retract` | `{"facts": [...]}`                                 | `{"added": [...], "removed": [...]}`        |
+/// | `infer`   | none                                               | `{"facts": [<fact record>, ...]}`           |
+/// | `prove`   | `{"goal": "grandparent(ama, kofi)"}`               | `{"goal": "...", "proven": true}`           |
+/// | `query`   | `{"pattern": "parent(x?, osei)"}`                  | `{"matches": [{"fact": ..., "bindings": {...}}]}` |
+/// | `facts`   | `{"predicate": "parent"}` or `"parent/2"`, optional | `{"facts": [<fact record>, ...]}`          |
+/// | `explain` | `{"fact": "grandparent(x?, kofi)"}`                | `{"explanations": [<node record>, ...]}`    |
+///
+/// Changes are validated like files are (type declarations, rule safety) and applied all or nothing; the result lists
+/// the derived facts that appeared and disappeared, as inference runs again after every change. A request that
+/// panics gets an internal error response instead of taking the server down.
+pub struct Server {
+    inference_engine: InferenceEngine,
+    loader: Loader
+}
+impl Server {
+    pub fn new(mut inference_engine: InferenceEngine) -> Self {
+        inference_engine.set_tracer(None);
+        inference_engine.infer();
+        Server { inference_engine, loader: Loader::new() }
+    }
+    /// The loader the served files were read with, see `Loader::take`, so that loading more keeps track of the files
+    /// already loaded and of module exports.
+    pub fn with_loader(mut self, loader: Loader) -> Self {
+        self.loader = loader;
+        self
+    }
+    /// Serves requests from `input` until it ends, writing each response on its own line.
+    /// A line longer than `MAX_LINE_LENGTH` gets an invalid request response and ends the session.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> Result<(), String> {
+        loop {
+            let response: Option<Json> = match read_line(&mut input) {
+                Ok(Some(line)) => self.handle_line(&line),
+                Ok(None) => return Ok(()),
+                Err(error) => {
+                    let response: Json = error_response(&Json::Null, INVALID_REQUEST, &error);
+                    writeln!(output, "{}", response).and_then(|_| output.flush()).map_err(|error| format!("Failed to write response: {}", error))?;
+                    return Err(error);
+                }
+            };
+            if let Some(response) = response {
+                writeln!(output, "{}", response).and_then(|_| output.flush()).map_err(|error| format!("Failed to write response: {}", error))?;
+            }
+        }
+    }
+    /// Handles one line of input, returning the response to send, if any. Notifications (requests without an `id`)
+    /// and blank lines get none.
+    pub fn handle_line(&mut self, line: &str) -> Option<Json> {
+        if line.trim().is_empty() { return None; }
+        match Json::parse(line) {
+            Ok(Json::Array(requests)) if !requests.is_empty() => {
+                let responses: Vec<Json> = requests.iter().filter_map(|request: &Json| self.handle(request)).collect();
+                if responses.is_empty() { None } else { Some(Json::Array(responses)) }
+            }
+            Ok(request) => self.handle(&request),
+            Err(error) => Some(error_response(&Json::Null, PARSE_ERROR, &error))
+        }
+    }
+    /// Handles one request object.
+    pub fn handle(&mut self, request: &Json) -> Option<Json> {
+        let id: Option<&Json> = request.get("id");
+        let Some(method) = request.get("method").and_then(Json::as_str) else {
+            return Some(error_response(id.unwrap_or(&Json::Null), INVALID_REQUEST, "Expected an object with a method"));
+        };
+        let params: &Json = request.get("params").unwrap_or(&Json::Null);
+        let result: Result<Json, (f64, String)> = self.call_caught(method, params);
+        let id: &Json = id?;
+        Some(match result {
+            Ok(result) => Json::object(vec![("jsonrpc", Json::string("2.0")), ("id", id.clone()), ("result", result)]),
+            Err((code, message)) => error_response(id, code, &message)
+        })
+    }
+    /// The numbers of given facts, rules and derived facts, as the fields of a JSON object.
+    pub fn counts(&self) -> Vec<(String, Json)> {
+        let knowledge_base: &KnowledgeBase = &self.inference_engine.knowledge_base;
+        vec![
+            (String::from("facts"), Json::Number(knowledge_base.get_axiomatic_facts().count() as f64)),
+            (String::from("rules"), Json::Number(knowledge_base.get_rules().count() as f64)),
+            (String::from("derived"), Json::Number(knowledge_base.working_memory().len() as f64))
+        ]
+    }
+    /// Runs a method like `call`, but answers a panic with an internal error instead of unwinding into the caller.
+    pub fn call_caught(&mut self, method: &str, params: &Json) -> Result<Json, (f64, String)> {
+        panic::catch_unwind(AssertUnwindSafe(|| self.call(method, params))).unwrap_or_else(|payload| {
+            let message: &str = payload.downcast_ref::<&str>().copied().or(payload.downcast_ref::<String>().map(String::as_str)).unwrap_or("unknown panic");
+            Err((INTERNAL_ERROR, format!("Internal error: {}", message)))
+        })
+    }
+    /// Runs a method, returning its result or an error code and message.
+    pub fn call(&mut self, method: &str, params: &Json) -> Result<Json, (f64, String)> {
+        match method {
+            "load" => self.load(params),
+            "assert" => self.assert(params),
+            "retract" => self.retract(params),
+            "infer" => Ok(self.infer()),
+            "prove" => self.prove(params),
+            "query" => self.query(params),
+            "facts" => self.facts(params),
+            "explain" => self.explain(params),
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method)))
+        }
+    }
+    fn load(&mut self, params: &Json) -> Result<Json, (f64, String)> {
+        let paths: Vec<&str> = match params.get("paths") {
+            Some(paths) => strings(paths).ok_or((INVALID_PARAMS, String::from("paths must be an array of strings")))?,
+            None => Vec::new()
+        };
+        let source: Option<&str> = params.get("source").and_then(Json::as_str);
+        if paths.is_empty() && source.is_none() {
+            return Err((INVALID_PARAMS, String::from("load requires paths or source")));
+        }
+        let syntax: &str = params.get("syntax").and_then(Json::as_str).unwrap_or("pie");
+        if !matches!(syntax, "pie" | "datalog") {
+            return Err((INVALID_PARAMS, format!("Unknown syntax '{}', expected pie or datalog", syntax)));
+        }
+        self.load_with(|loader: &mut Loader| {
+            for path in &paths {
+                loader.load_file(Path::new(path))?;
+            }
+            match (source, syntax) {
+                (Some(source), "datalog") => loader.load_datalog(source, Path::new(SERVER_SOURCE)),
+                (Some(source), _) => loader.load_source(source, Path::new(SERVER_SOURCE)),
+                (None, _) => Ok(())
+            }
+        })
+    }
+    fn assert(&mut self, params: &Json) -> Result<Json, (f64, String)> {
+        let facts: Vec<Fact> = facts(params)?;
+        if let Some(fact) = facts.iter().find(|fact: &&Fact| fact.is_negative()) {
+            return Err((INVALID_PARAMS, format!("Cannot assert negative fact {}, retract {} instead", fact, fact.get_negated())));
+        }
+        let source: String = facts.iter().map(|fact: &Fact| format!("{}.\n", fact)).collect();
+        self.load_with(|loader: &mut Loader| loader.load_source(&source, Path::new(SERVER_SOURCE)))
+    }
+    fn retract(&mut self, params: &Json) -> Result<Json, (f64, String)> {
+        let facts: Vec<Fact> = facts(params)?;
+        if let Some(fact) = facts.iter().find(|fact: &&Fact| !self.inference_engine.knowledge_base.get_axiomatic_facts().any(|existing: &Fact| existing == *fact)) {
+            return Err((REQUEST_FAILED, format!("{} is not a given fact", fact)));
+        }
+        self.change(|mut knowledge_base: KnowledgeBase| {
+            for fact in &facts {
+                knowledge_base.remove_axiomatic_fact(fact);
+            }
+            Ok(knowledge_base)
+        })
+    }
+    fn infer(&mut self) -> Json {
+        self.inference_engine.infer();
+        let mut facts: Vec<&Fact> = self.inference_engine.knowledge_base.working_memory().iter().collect();
+        facts.sort_by_cached_key(|fact: &&Fact| fact.to_string());
+        Json::object(vec![("facts", Json::Array(facts.into_iter().map(fact_record).collect()))])
+    }
+    fn prove(&mut self, params: &Json) -> Result<Json, (f64, String)> {
+        let goal: Fact = fact_param(params, "goal")?;
+        let proven: bool = self.inference_engine.prove(&goal);
+        Ok(Json::object(vec![("goal", Json::string(goal.to_string())), ("proven", Json::Bool(proven))]))
+    }
+    fn query(&mut self, params: &Json) -> Result<Json, (f64, String)> {
+        let pattern: Fact = fact_param(params, "pattern")?;
+        let matches: Vec<QueryMatch> = self.inference_engine.query_matches(&pattern);
+        Ok(Json::object(vec![("matches", Json::Array(query_report(&pattern, &matches).records))]))
+    }
+    /// The given and derived facts, optionally of one predicate given as `name` or `name/arity`.
+    fn facts(&self, params: &Json) -> Result<Json, (f64, String)> {
+        let predicate: Option<&str> = match params.get("predicate") {
+            Some(predicate) => Some(predicate.as_str().ok_or((INVALID_PARAMS, String::from("predicate must be a string")))?),
+            None => None
+        };
+        let mut facts: Vec<&Fact> = self.inference_engine.knowledge_base.get_facts().filter(|fact: &&Fact| {
+            predicate.is_none_or(|predicate: &str| fact.signature().is_some_and(|(name, arity)| name == predicate || format!("{}/{}", name, arity) == predicate))
+        }).collect();
+        facts.sort_by_cached_key(|fact: &&Fact| fact.to_string());
+        Ok(Json::object(vec![("facts", Json::Array(facts.into_iter().map(fact_record).collect()))]))
+    }
+    /// Explanations of a fact, or of every fact matching a pattern, as the nodes of their trees in depth-first order.
+    fn explain(&mut self, params: &Json) -> Result<Json, (f64, String)> {
+        let fact: Fact = fact_param(params, "fact")?;
+        let explanations: Vec<Explanation> = self.inference_engine.explain_matches(&fact);
+        Ok(Json::object(vec![("explanations", Json::Array(explanation_report(&explanations).records))]))
+    }
+    /// Loads more with the server's loader, which only keeps what was loaded if the change succeeds.
+    fn load_with(&mut self, load: impl FnOnce(&mut Loader) -> Result<(), String>) -> Result<Json, (f64, String)> {
+        let mut loader: Loader = self.loader.clone();
+        let result: Json = self.change(|knowledge_base: KnowledgeBase| {
+            loader.set_knowledge_base(knowledge_base);
+            load(&mut loader)?;
+            loader.take()
+        })?;
+        self.loader = loader;
+        Ok(result)
+    }
+    fn change(&mut self, apply: impl FnOnce(KnowledgeBase) -> Result<KnowledgeBase, String>) -> Result<Json, (f64, String)> {
+        let changes: FactDiff = self.inference_engine.change(apply).map_err(|error| (REQUEST_FAILED, error))?;
+        let texts = |facts: &[Fact]| {
+            let mut texts: Vec<String> = facts.iter().map(Fact::to_string).collect();
+            texts.sort();
+            Json::Array(texts.into_iter().map(Json::string).collect())
+        };
+        Ok(Json::object(vec![("added", texts(&changes.added)), ("removed", texts(&changes.removed))]))
+    }
+}
+
+/// Accepts connections on 127.0.0.1 at `port` (0 picks a free one) and serves each on its own thread. Every
+/// connection talks to the same server, one request at a time. The address is printed to stderr once listening.
+pub fn listen(server: Server, port: u16) -> Result<(), String> {
+    let listener: TcpListener = TcpListener::bind(("127.0.0.1", port)).map_err(|error| format!("Failed to listen on port {}: {}", port, error))?;
+    if let Ok(address) = listener.local_addr() {
+        eprintln!("Listening on {}", address);
+    }
+    serve_connections(Arc::new(Mutex::new(server)), listener)
+}
+
+/// Serves every connection to `listener` until accepting fails.
+pub fn serve_connections(server: Arc<Mutex<Server>>, listener: TcpListener) -> Result<(), String> {
+    for stream in listener.incoming() {
+        let stream: TcpStream = stream.map_err(|error| format!("Failed to accept connection: {}", error))?;
+        let server: Arc<Mutex<Server>> = Arc::clone(&server);
+        thread::spawn(move || {
+            let Ok(mut output) = stream.try_clone() else { return; };
+            let mut input: BufReader<TcpStream> = BufReader::new(stream);
+            loop {
+                let response: Option<Json> = match read_line(&mut input) {
+                    Ok(Some(line)) => server.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).handle_line(&line),
+                    Ok(None) => return,
+                    Err(error) => {
+                        let _ = writeln!(output, "{}", error_response(&Json::Null, INVALID_REQUEST, &error));
+                        return;
+                    }
+                };
+                if let Some(response) = response && writeln!(output, "{}", response).and_then(|_| output.flush()).is_err() { return; }
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Reads one request line of at most `MAX_LINE_LENGTH` bytes, without its line ending. Returns `None` at the end of
+/// the input.
+pub fn read_line(input: &mut impl BufRead) -> Result<Option<String>, String> {
+    let mut line: Vec<u8> = Vec::new();
+    let length: usize = input.by_ref().take(MAX_LINE_LENGTH as u64 + 1).read_until(b'\n', &mut line).map_err(|error| format!("Failed to read request: {}", error))?;
+    if length == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    } else if length > MAX_LINE_LENGTH {
+        return Err(format!("Request is longer than {} bytes", MAX_LINE_LENGTH));
+    }
+    String::from_utf8(line).map(Some).map_err(|error| format!("Failed to read request: {}", error))
+}
+
+pub fn error_response(id: &Json, code: f64, message: &str) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::string("2.0")),
+        ("id", id.clone()),
+        ("error", Json::object(vec![("code", Json::Number(code)), ("message", Json::string(message))]))
+    ])
+}
+
+fn strings(json: &Json) -> Option<Vec<&str>> {
+    json.as_array()?.iter().map(Json::as_str).collect()
+}
+
+fn fact_param(params: &Json, name: &str) -> Result<Fact, (f64, String)> {
+    let text: &str = params.get(name).and_then(Json::as_str).ok_or((INVALID_PARAMS, format!("Missing parameter '{}'", name)))?;
+    Fact::parse(text.trim().trim_end_matches('.')).map_err(|error| (INVALID_PARAMS, format!("Invalid {} '{}': {}", name, text, error)))
+}
+
+/// The `facts` parameter, or a single `fact`.
+fn facts(params: &Json) -> Result<Vec<Fact>, (f64, String)> {
+    let texts: Vec<&str> = match (params.get("facts"), params.get("fact").and_then(Json::as_str)) {
+        (Some(facts), _) => strings(facts).ok_or((INVALID_PARAMS, String::from("facts must be an array of strings")))?,
+        (None, Some(fact)) => vec![fact],
+        (None, None) => return Err((INVALID_PARAMS, String::from("Missing parameter 'facts'")))
+    };
+    texts.iter().map(|text: &&str| Fact::parse(text.trim().trim_end_matches('.')).map_err(|error| (INVALID_PARAMS, format!("Invalid fact '{}': {}", text, error)))).collect()
+}
@@ -0,0 +1,274 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::knowledge_base::*;
+
+/// The first bytes of every saved knowledge base.
+pub const MAGIC: &[u8; 6] = b"PIEKB\0";
+/// Bumped whenever the layout below changes; older versions are still read where possible.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Serializes the whole knowledge base: type declarations, given facts with where they were declared, rules with
+/// their sources, and the derived facts in working memory, so a restored knowledge base does not need to be inferred
+/// again. The layout is a header (`MAGIC` and the format version as a little-endian u32) followed by those sections;
+/// numbers are little-endian, strings are a u32 byte length followed by UTF-8, and lists are a u32 count followed
+/// by their items. Facts, rules and declarations are written in sorted order so the same state always gives the
+/// same bytes.
+///
+/// Justifications are not written: the engine does not keep them, and `InferenceEngine::explain` rebuilds them from
+/// the saved facts, rules and derived facts, so a restored knowledge base explains its facts without inferring again.
+pub fn to_bytes(knowledge_base: &KnowledgeBase) -> Vec<u8> {
+    let mut writer: Writer = Writer { bytes: MAGIC.to_vec() };
+    writer.u32(FORMAT_VERSION);
+    let mut declarations: Vec<&TypeDeclaration> = knowledge_base.get_type_declarations().collect();
+    declarations.sort_by(|left, right| left.name.cmp(&right.name));
+    writer.u32(declarations.len() as u32);
+    for declaration in declarations {
+        writer.string(&declaration.name);
+        writer.u32(declaration.arguments.len() as u32);
+        for argument in &declaration.arguments {
+            writer.u8(match argument { ArgumentType::Atom => 0, ArgumentType::Int => 1, ArgumentType::Any => 2 });
+        }
+    }
+    let facts: Vec<&Fact> = sorted(knowledge_base.get_axiomatic_facts());
+    writer.u32(facts.len() as u32);
+    for fact in facts {
+        writer.fact(fact);
+        let sources: &[SourceLocation] = knowledge_base.get_fact_sources(fact);
+        writer.u32(sources.len() as u32);
+        for source in sources {
+            writer.source(source);
+        }
+    }
+    let rules: Vec<&Rule> = knowledge_base.get_rules().collect();
+    writer.u32(rules.len() as u32);
+    for (index, rule) in rules.into_iter().enumerate() {
+        writer.u32(rule.antecedents.len() as u32);
+        for item in &rule.antecedents {
+            match item {
+                AntecedentItem::Fact(fact) => {
+                    writer.u8(0);
+                    writer.fact(fact);
+                }
+                operator => writer.u8(operator_tag(operator))
+            }
+        }
+        writer.fact(&rule.consequent);
+        match knowledge_base.get_rule_source(index) {
+            Some(source) => {
+                writer.u8(1);
+                writer.source(source);
+            }
+            None => writer.u8(0)
+        }
+    }
+    let derived: Vec<&Fact> = sorted(knowledge_base.working_memory().iter());
+    writer.u32(derived.len() as u32);
+    for fact in derived {
+        writer.fact(fact);
+    }
+    writer.bytes
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<KnowledgeBase, String> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(String::from("Not a saved knowledge base"));
+    }
+    let mut reader: Reader = Reader { bytes, position: MAGIC.len() };
+    let version: u32 = reader.u32()?;
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(format!("Unsupported knowledge base format version {} (this build reads up to {})", version, FORMAT_VERSION));
+    }
+    let mut knowledge_base: KnowledgeBase = KnowledgeBase::new();
+    for _ in 0..reader.u32()? {
+        let name: String = reader.string()?;
+        let mut arguments: Vec<ArgumentType> = Vec::new();
+        for _ in 0..reader.u32()? {
+            arguments.push(match reader.u8()? {
+                0 => ArgumentType::Atom,
+                1 => ArgumentType::Int,
+                2 => ArgumentType::Any,
+                tag => return Err(format!("Invalid argument type tag {}", tag))
+            });
+        }
+        knowledge_base.add_type_declaration(TypeDeclaration::new(name, arguments))?;
+    }
+    for _ in 0..reader.u32()? {
+        let fact: Fact = reader.fact()?;
+        for _ in 0..reader.u32()? {
+            knowledge_base.add_axiomatic_fact_from(fact.clone(), reader.source()?);
+        }
+        knowledge_base.add_axiomatic_fact(fact);
+    }
+    for _ in 0..reader.u32()? {
+        let mut antecedents: Vec<AntecedentItem> = Vec::new();
+        for _ in 0..reader.u32()? {
+            antecedents.push(match reader.u8()? {
+                0 => AntecedentItem::Fact(reader.fact()?),
+                tag => tag_operator(tag)?
+            });
+        }
+        let rule: Rule = Rule::new(antecedents, reader.fact()?);
+        rule.check_postfix().map_err(|error| format!("Corrupt knowledge base file: {}", error))?;
+        rule.check_safety().map_err(|error| format!("Corrupt knowledge base file: unsafe rule {}: {}", rule, error))?;
+        match reader.u8()? {
+            0 => knowledge_base.add_axiomatic_rule(rule),
+            _ => knowledge_base.add_axiomatic_rule_from(rule, reader.source()?)
+        }
+    }
+    for _ in 0..reader.u32()? {
+        knowledge_base.add_fact(reader.fact()?);
+    }
+    if reader.position != bytes.len() {
+        return Err(String::from("Unexpected data after the end of the knowledge base"));
+    }
+    Ok(knowledge_base)
+}
+
+/// Whether a file starts with the header of a saved knowledge base.
+pub fn is_saved_knowledge_base(path: &Path) -> bool {
+    use std::io::Read;
+    let mut header: [u8; 6] = [0; 6];
+    fs::File::open(path).and_then(|mut file: fs::File| file.read_exact(&mut header)).is_ok() && &header == MAGIC
+}
+
+impl KnowledgeBase {
+    /// Saves the knowledge base to a file. The file is written next to its destination first and then renamed,
+    /// so an interrupted checkpoint never leaves a truncated file behind.
+    pub fn checkpoint(&self, path: &Path) -> Result<(), String> {
+        let mut temporary: PathBuf = path.to_path_buf();
+        temporary.as_mut_os_string().push(".tmp");
+        fs::write(&temporary, to_bytes(self)).map_err(|error| format!("Failed to write '{}': {}", temporary.display(), error))?;
+        fs::rename(&temporary, path).map_err(|error| format!("Failed to write '{}': {}", path.display(), error))
+    }
+    pub fn restore(path: &Path) -> Result<Self, String> {
+        let bytes: Vec<u8> = fs::read(path).map_err(|error| format!("Failed to read file '{}': {}", path.display(), error))?;
+        from_bytes(&bytes).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+}
+
+fn sorted<'f>(facts: impl Iterator<Item = &'f Fact>) -> Vec<&'f Fact> {
+    let mut facts: Vec<&Fact> = facts.collect();
+    facts.sort_by_cached_key(|fact: &&Fact| fact.to_string());
+    facts
+}
+
+fn operator_tag(operator: &AntecedentItem) -> u8 {
+    match operator {
+        AntecedentItem::Fact(_) => 0,
+        AntecedentItem::And => 1,
+        AntecedentItem::Or => 2,
+        AntecedentItem::Equals => 3,
+        AntecedentItem::NotEquals => 4,
+        AntecedentItem::GreaterThan => 5,
+        AntecedentItem::GreaterOrEquals => 6,
+        AntecedentItem::LesserThan => 7,
+        AntecedentItem::LesserOrEquals => 8
+    }
+}
+
+fn tag_operator(tag: u8) -> Result<AntecedentItem, String> {
+    Ok(match tag {
+        1 => AntecedentItem::And,
+        2 => AntecedentItem::Or,
+        3 => AntecedentItem::Equals,
+        4 => AntecedentItem::NotEquals,
+        5 => AntecedentItem::GreaterThan,
+        6 => AntecedentItem::GreaterOrEquals,
+        7 => AntecedentItem::LesserThan,
+        8 => AntecedentItem::LesserOrEquals,
+        _ => return Err(format!("Invalid operator tag {}", tag))
+    })
+}
+
+struct Writer {
+    bytes: Vec<u8>
+}
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+    fn source(&mut self, source: &SourceLocation) {
+        self.string(&source.file.to_string_lossy());
+        self.u32(source.line as u32);
+    }
+    /// A tag byte (0 number, 1 atom, 2 predicate, 3 variable), then the value, the polarity and name, or the name.
+    fn fact(&mut self, fact: &Fact) {
+        match fact {
+            Fact::Number(number) => {
+                self.u8(0);
+                self.bytes.extend_from_slice(&number.value.to_le_bytes());
+            }
+            Fact::Atomic(atomic) => {
+                self.u8(1);
+                self.u8(atomic.positive as u8);
+                self.string(&atomic.name);
+            }
+            Fact::Predicate(predicate) => {
+                self.u8(2);
+                self.u8(predicate.positive as u8);
+                self.string(&predicate.name);
+                self.u32(predicate.arguments.len() as u32);
+                for argument in &predicate.arguments {
+                    self.fact(argument);
+                }
+            }
+            Fact::Variable(variable) => {
+                self.u8(3);
+                self.string(&variable.name);
+            }
+        }
+    }
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    position: usize
+}
+impl<'b> Reader<'b> {
+    fn take(&mut self, length: usize) -> Result<&'b [u8], String> {
+        let end: usize = self.position.checked_add(length).filter(|end: &usize| *end <= self.bytes.len()).ok_or("Unexpected end of data, the file is truncated")?;
+        let bytes: &[u8] = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("four bytes")))
+    }
+    fn string(&mut self) -> Result<String, String> {
+        let length: usize = self.u32()? as usize;
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| String::from("Invalid UTF-8 in string"))
+    }
+    fn source(&mut self) -> Result<SourceLocation, String> {
+        Ok(SourceLocation::new(PathBuf::from(self.string()?), self.u32()? as usize))
+    }
+    fn fact(&mut self) -> Result<Fact, String> {
+        Ok(match self.u8()? {
+            0 => Fact::Number(NumericFact::new(i32::from_le_bytes(self.take(4)?.try_into().expect("four bytes")))),
+            1 => {
+                let positive: bool = self.u8()? != 0;
+                Fact::Atomic(AtomicFact::new(positive, self.string()?))
+            }
+            2 => {
+                let positive: bool = self.u8()? != 0;
+                let name: String = self.string()?;
+                let mut arguments: Vec<Fact> = Vec::new();
+                for _ in 0..self.u32()? {
+                    arguments.push(self.fact()?);
+                }
+                Fact::Predicate(PredicateFact::new(positive, name, arguments))
+            }
+            3 => Fact::Variable(Variable::new(self.string()?)),
+            tag => return Err(format!("Invalid fact tag {}", tag))
+        })
+    }
+}
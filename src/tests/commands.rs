@@ -0,0 +1,42 @@
+use super::{write_test_files, TestDirectory};
+use crate::*;
+#[test]
+fn command_output_test() {
+    let directory: TestDirectory = write_test_files("command_output", &[
+        ("family.txt", "parent(kofi, ama).\nparent(ama, osei).\nparent(osei, kwame).\nparent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).\n"),
+        ("broken.txt", "parent(kofi, ama)\n"),
+        ("unused.txt", "age(kofi,30).\n")
+    ]);
+    let path: String = directory.path("family.txt");
+    let run = |args: &[&str]| {
+        let args: Vec<String> = args.iter().map(|arg: &&str| arg.replace("{path}", &path)).collect();
+        let mut output: Vec<u8> = Vec::new();
+        let status: i32 = commands::run(&args[0], &args[1..], std::io::empty(), &mut output);
+        (status, String::from_utf8(output).expect("Impossible"))
+    };
+    assert_eq!(run(&["infer", "{path}"]), (0, String::from("New facts:\n  grandparent(ama, kwame)\n  grandparent(kofi, osei)\n")));
+    assert_eq!(run(&["infer", "{path}", "--format", "csv"]).1, "fact,predicate,negated,arguments1,arguments2\n\"grandparent(ama, kwame)\",grandparent,false,ama,kwame\n\"grandparent(kofi, osei)\",grandparent,false,kofi,osei\n");
+    assert_eq!(run(&["query", "grandparent(kofi, x?)", "{path}", "--format", "jsonl"]), (0, String::from("{\"fact\":\"grandparent(kofi, osei)\",\"bindings\":{\"x\":\"osei\"}}\n")));
+    assert_eq!(run(&["query", "grandparent(osei, x?)", "-f", "{path}", "--format", "json"]), (1, String::from("[]\n")));
+    assert_eq!(run(&["prove", "parent(kofi, ama)", "{path}", "--format", "json"]), (0, String::from("[{\"goal\":\"parent(kofi, ama)\",\"proven\":true}]\n")));
+    assert_eq!(run(&["prove", "parent(ama, kofi)", "{path}"]), (1, String::from("parent(ama, kofi) is false\n")));
+    assert_eq!(run(&["check", directory.join("broken.txt").to_str().expect("Impossible"), "--format", "jsonl"]).0, 1);
+    assert_eq!(run(&["infer", "{path}", "--format", "xml"]).0, 2);
+    assert_eq!(run(&["lint", "{path}", "--format", "json"]), (0, String::from("[]\n")));
+    let broken: String = directory.path("broken.txt");
+    assert_eq!(run(&["lint", &broken]).0, 2);
+    assert_eq!(run(&["lint"]).0, 2);
+    assert_eq!(run(&["lint", &directory.path("unused.txt"), "--format", "jsonl"]), (1, format!("{{\"location\":\"{}:1\",\"code\":\"unused-predicate\",\"message\":\"age/2 is given by facts but never used in a rule\"}}\n", directory.path("unused.txt"))));
+    assert_eq!(run(&["fmt", "--check", &directory.path("unused.txt")]), (1, format!("Would reformat {}\n", directory.path("unused.txt"))));
+    assert_eq!(run(&["fmt", "--check", "{path}", &broken]).0, 2);
+    assert_eq!(run(&["watch", "{path}", "--format", "json"]).0, 2);
+    let (status, help): (i32, String) = run(&["convert", "--help"]);
+    assert!(status == 0 && help.starts_with("Usage: pie convert [--to pie|datalog]"));
+}
+#[test]
+fn commands_documented_test() {
+    let readme: &str = include_str!("../../README.md");
+    for command in &commands::COMMANDS {
+        assert!(readme.contains(&format!("pie {}", command.usage)) || readme.contains(&format!("pie {} ", command.name)), "{} is not in the README", command.name);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, KnowledgeBase};
+#[test]
+fn datalog_test() {
+    let source: &str = "% family\n:- type parent(atom, atom).\nparent(ama, osei).\nparent(osei, kofi).\nage(kofi, 20).\n\
+        /* rules */\ngrandparent(X, Z) :- parent(X, Y), parent(Y, Z).\n\
+        minor(X) :- age(X, Age), \\+ adult(X), (Age < 18 ; Age =< 20, Age \\= 19).\nadult(X) :- age(X, A), A >= 21.\n";
+    let mut knowledge_base: KnowledgeBase = KnowledgeBase::new();
+    knowledge_base.load_datalog(source, std::path::Path::new("family.pl")).expect("Impossible");
+    assert_eq!(knowledge_base.get_fact_source(&Fact::parse("age(kofi, 20)").expect("Impossible")).map(|location| location.line), Some(5));
+    let rules: Vec<String> = knowledge_base.get_rules().map(formatter::format_rule).collect();
+    assert_eq!(rules, vec![
+        "parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).",
+        "age(x?, age?) & !adult(x?) & [age? < 18 | age? <= 20 & age? != 19] -> minor(x?).",
+        "age(x?, a?) & a? >= 21 -> adult(x?)."
+    ]);
+    let written: String = knowledge_base.to_datalog();
+    assert!(written.starts_with(":- type parent(atom, atom).\n\nage(kofi, 20).\n"));
+    assert!(written.contains("minor(X) :- age(X, Age), \\+ adult(X), (Age < 18 ; Age =< 20, Age \\= 19).\n"));
+    let mut reloaded: KnowledgeBase = KnowledgeBase::new();
+    reloaded.load_datalog(&written, std::path::Path::new("written.pl")).expect("Impossible");
+    assert_eq!(reloaded.get_rules().map(formatter::format_rule).collect::<Vec<String>>(), rules);
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(reloaded);
+    inference_engine.infer();
+    assert!(inference_engine.knowledge_base.has_fact(&Fact::parse("minor(kofi)").expect("Impossible")));
+    let mut loader: loader::Loader = loader::Loader::new();
+    loader.load_source(&formatter::format_knowledge_base(&knowledge_base), std::path::Path::new("family.txt")).expect("Impossible");
+    assert_eq!(loader.finish().expect("Impossible").to_datalog(), written);
+    let mut constants: loader::Loader = loader::Loader::new();
+    constants.load_source("person(bob).\nperson(ama).\nperson(x?) & x? != bob -> notbob(x?).\n", std::path::Path::new("constants.txt")).expect("Impossible");
+    let constants: KnowledgeBase = constants.finish().expect("Impossible");
+    let written: String = constants.to_datalog();
+    assert!(written.contains("notbob(X) :- person(X), X \\= bob.\n"));
+    let mut reloaded: KnowledgeBase = KnowledgeBase::new();
+    reloaded.load_datalog(&written, std::path::Path::new("constants.pl")).expect("Impossible");
+    assert_eq!(reloaded.get_rules().map(formatter::format_rule).collect::<Vec<String>>(), constants.get_rules().map(formatter::format_rule).collect::<Vec<String>>());
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(reloaded);
+    inference_engine.infer();
+    assert!(inference_engine.knowledge_base.has_fact(&Fact::parse("notbob(ama)").expect("Impossible")));
+    assert!(!inference_engine.knowledge_base.has_fact(&Fact::parse("notbob(bob)").expect("Impossible")));
+    assert!(KnowledgeBase::new().load_datalog("p(X) :- q(X), X > bob.", std::path::Path::new("ordered.pl")).is_ok());
+    let mut anonymous: KnowledgeBase = KnowledgeBase::new();
+    anonymous.load_datalog("has_grandparent(X) :- parent(_1, X), parent(_, _1).", std::path::Path::new("anonymous.pl")).expect("Impossible");
+    assert_eq!(anonymous.get_rules().map(formatter::format_rule).collect::<Vec<String>>(), vec!["parent(_1?, x?) & parent(_2?, _1?) -> has_grandparent(x?)."]);
+    for (invalid, error) in [("p([1]).", "Lists are not supported"), ("p(X) :- q(Y), X is Y.", "'is'"), (":- dynamic p/1.", "directives"), ("p(X) :- \\+ q(X).", "Unsafe"),
+        ("p(3000000000).", "3000000000 does not fit in a 32-bit integer"), ("p(4.5).", "write '4.5'")] {
+        assert!(matches!(KnowledgeBase::new().load_datalog(invalid, std::path::Path::new("bad.pl")), Err(message) if message.contains(error)), "{}", invalid);
+    }
+}
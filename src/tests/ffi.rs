@@ -0,0 +1,60 @@
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, KnowledgeBase};
+#[test]
+fn ffi_test() {
+    use std::ffi::{c_char, CStr, CString};
+    let text = |pointer: *const c_char| unsafe { CStr::from_ptr(pointer) }.to_str().expect("Impossible").to_string();
+    let source: CString = CString::new("parent(ama, osei).\nparent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).\n").expect("Impossible");
+    let knowledge_base: *mut KnowledgeBase = ffi::pie_kb_new();
+    unsafe {
+        assert_eq!(ffi::pie_kb_load_text(knowledge_base, source.as_ptr(), std::ptr::null()), ffi::PIE_OK);
+        let malformed: CString = CString::new("a & -> b.").expect("Impossible");
+        assert_eq!(ffi::pie_kb_load_text(knowledge_base, malformed.as_ptr(), std::ptr::null()), ffi::PIE_ERROR_INVALID);
+        assert_eq!(text(ffi::pie_last_error()), "<text>:1: Invalid postfix expression: not enough operands");
+        let engine: *mut InferenceEngine = ffi::pie_engine_new(knowledge_base);
+        let fact: CString = CString::new("parent(osei, kofi)").expect("Impossible");
+        assert_eq!(ffi::pie_kb_add_fact(ffi::pie_engine_knowledge_base(engine), fact.as_ptr()), ffi::PIE_OK);
+        assert_eq!(ffi::pie_engine_infer(engine), ffi::PIE_OK);
+        let mut proven: bool = false;
+        let goal: CString = CString::new("grandparent(ama, kofi)").expect("Impossible");
+        assert_eq!(ffi::pie_engine_prove(engine, goal.as_ptr(), &mut proven), ffi::PIE_OK);
+        assert!(proven);
+        let pattern: CString = CString::new("parent(x?, y?)").expect("Impossible");
+        let mut query: *mut ffi::PieQuery = std::ptr::null_mut();
+        assert_eq!(ffi::pie_engine_query(engine, pattern.as_ptr(), &mut query), ffi::PIE_OK);
+        let (mut current, mut name, mut value): (*const c_char, *const c_char, *const c_char) = (std::ptr::null(), std::ptr::null(), std::ptr::null());
+        let mut matches: Vec<String> = Vec::new();
+        while ffi::pie_query_next(query, &mut current) == ffi::PIE_OK {
+            assert_eq!(ffi::pie_query_binding_count(query), 2);
+            assert_eq!(ffi::pie_query_binding(query, 1, &mut name, &mut value), ffi::PIE_OK);
+            matches.push(format!("{} {}={}", text(current), text(name), text(value)));
+        }
+        assert_eq!(matches, vec!["parent(ama, osei) y=osei", "parent(osei, kofi) y=kofi"]);
+        assert_eq!(ffi::pie_query_next(query, &mut current), ffi::PIE_DONE);
+        ffi::pie_query_free(query);
+        assert_eq!(ffi::pie_kb_remove_fact(ffi::pie_engine_knowledge_base(engine), fact.as_ptr()), ffi::PIE_OK);
+        assert_eq!(ffi::pie_kb_remove_fact(ffi::pie_engine_knowledge_base(engine), fact.as_ptr()), ffi::PIE_ERROR_NOT_FOUND);
+        assert_eq!(ffi::pie_engine_prove(engine, goal.as_ptr(), std::ptr::null_mut()), ffi::PIE_ERROR_NULL_ARGUMENT);
+        assert_eq!(text(ffi::pie_last_error()), "proven is NULL");
+        ffi::pie_engine_free(engine);
+    }
+}
+#[test]
+fn ffi_import_json_test() {
+    use std::ffi::CString;
+    let knowledge_base: *mut KnowledgeBase = ffi::pie_kb_new();
+    let declaration: CString = CString::new(":- type employee(atom, atom, int).").expect("Impossible");
+    let records: CString = CString::new("[{\"name\": \"John\", \"department\": \"Human Resources\", \"salary\": 60000}]").expect("Impossible");
+    let predicates: CString = CString::new("{\"employee\": [[\"bob\", 50000, \"sales\"]]}").expect("Impossible");
+    let predicate: CString = CString::new("employee").expect("Impossible");
+    unsafe {
+        assert_eq!(ffi::pie_kb_load_text(knowledge_base, declaration.as_ptr(), std::ptr::null()), ffi::PIE_OK);
+        assert_eq!(ffi::pie_kb_import_json(knowledge_base, records.as_ptr(), predicate.as_ptr()), ffi::PIE_OK);
+        assert_eq!(ffi::pie_kb_import_json(knowledge_base, predicates.as_ptr(), std::ptr::null()), ffi::PIE_ERROR_INVALID);
+        assert_eq!(ffi::pie_kb_import_json(knowledge_base, records.as_ptr(), std::ptr::null()), ffi::PIE_ERROR_INVALID);
+        let facts: Vec<String> = (*knowledge_base).get_axiomatic_facts().map(Fact::to_string).collect();
+        assert_eq!(facts, vec!["employee(John, Human_Resources, 60000)"]);
+        ffi::pie_kb_free(knowledge_base);
+    }
+}
@@ -0,0 +1,42 @@
+use std::fs;
+use super::{write_test_files, TestDirectory};
+use crate::*;
+use crate::knowledge_base::KnowledgeBase;
+use crate::loader::load_files;
+#[test]
+fn format_test() {
+    let source: &str = "# Facts\nperson(kofi,male).\n\n\nmother(ama, osei).\nperson(ama,   female).\nmother(kofi, ama).\nmother(x?,y?)->parent(x?, y?)\n[a(x?) & b(x?)] | [c(x?) & [d(x?) | e(x?)]] -> f(x?).\n:- type  person(atom,atom).\n";
+    let expected: &str = "# Facts\nperson(kofi, male).\n\nmother(ama, osei).\nmother(kofi, ama).\nperson(ama, female).\n\nmother(x?, y?) -> parent(x?, y?).\na(x?) & b(x?) | c(x?) & [d(x?) | e(x?)] -> f(x?).\n:- type person(atom, atom).\n";
+    let formatted: String = formatter::format_source(source).expect("Impossible");
+    assert_eq!(formatted, expected);
+    assert_eq!(formatter::format_source(&formatted).expect("Impossible"), formatted);
+}
+#[test]
+fn wrapped_rule_test() {
+    let rule: &str = "siblings(x?, y?) & parent(z?, x?) & !parent(z?, y?) & parent(w?, y?) & spouses(z?, w?) -> stepsiblings(x?, y?).\n";
+    let formatted: String = formatter::format_source(rule).expect("Impossible");
+    assert_eq!(formatted, "siblings(x?, y?)\n    & parent(z?, x?)\n    & !parent(z?, y?)\n    & parent(w?, y?)\n    & spouses(z?, w?)\n    -> stepsiblings(x?, y?).\n");
+    let directory: TestDirectory = write_test_files("wrapped_rule", &[("wrapped.txt", &formatted)]);
+    let knowledge_base: KnowledgeBase = load_files(&[directory.path("wrapped.txt")]).expect("Impossible");
+    assert_eq!(knowledge_base.get_rules().count(), 1);
+    assert_eq!(formatter::format_rule(knowledge_base.get_rules().next().expect("Impossible")), formatted.trim_end());
+}
+#[test]
+fn format_files_test() {
+    let directory: TestDirectory = write_test_files("format_files", &[
+        ("broken.txt", "mother(ama, osei)
+"),
+        ("messy.txt", "mother(ama,osei).
+"),
+        ("clean.txt", "mother(ama, osei).
+")
+    ]);
+    let paths: Vec<String> = ["broken.txt", "messy.txt", "clean.txt"].iter().map(|name: &&str| directory.path(name)).collect();
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    let checked: formatter::FormattedFiles = formatter::format_files(&paths, true);
+    assert_eq!((checked.changed, checked.errors.len()), (vec![paths[1].to_string()], 1));
+    assert_eq!(fs::read_to_string(paths[1]).expect("Impossible"), "mother(ama,osei).\n");
+    let formatted: formatter::FormattedFiles = formatter::format_files(&paths, false);
+    assert!(formatted.errors[0].contains("broken.txt"));
+    assert_eq!(fs::read_to_string(paths[1]).expect("Impossible"), "mother(ama, osei).\n");
+}
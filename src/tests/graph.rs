@@ -0,0 +1,22 @@
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, KnowledgeBase};
+#[test]
+fn graph_test() {
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(KnowledgeBase::from_strings(
+        vec!["parent(ama, osei)", "parent(osei, kofi)", "adopted(kofi)"],
+        vec![("parent(x?, y?) & parent(y?, z?)", "grandparent(x?, z?)"), ("parent(x?, y?) & !adopted(y?)", "birth_parent(x?, y?)")]
+    ).expect("Impossible"));
+    let dependencies: String = graph::dependency_graph(&inference_engine.knowledge_base);
+    assert!(dependencies.contains("\"parent/2\" [shape=box];") && dependencies.contains("\"grandparent/2\" [shape=ellipse];"));
+    assert!(dependencies.contains("\"parent/2\" -> \"grandparent/2\";\n"));
+    assert!(dependencies.contains("\"adopted/1\" -> \"birth_parent/2\" [style=dashed, color=red, label=\"not\"];"));
+    inference_engine.infer();
+    let explanation: inference_engine::Explanation = inference_engine.explain(&Fact::parse("birth_parent(ama, osei)").expect("Impossible")).expect("Impossible");
+    let derivation: String = graph::derivation_graph(&[explanation]);
+    assert!(derivation.starts_with("digraph derivation {") && derivation.ends_with("}\n"));
+    assert!(derivation.contains("fact0 [label=\"birth_parent(ama, osei)\", shape=ellipse];"));
+    assert!(derivation.contains("[label=\"parent(x?, y?) & !adopted(y?) -> birth_parent(x?, y?)\", shape=diamond];"));
+    assert!(derivation.contains("[label=\"!adopted(osei)\", shape=box, style=dashed];"));
+    assert_eq!(derivation.matches(" -> rule0;").count(), 2);
+}
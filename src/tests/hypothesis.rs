@@ -0,0 +1,26 @@
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, FactDiff, KnowledgeBase};
+#[test]
+fn hypothesis_test() {
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(
+        KnowledgeBase::from_strings(
+            vec!["visible(zergling_1)", "visible(mutalisk_1)", "cloaked(mutalisk_1)"],
+            vec![
+                ("visible(unit?) & detected(unit?)", "can_attack(unit?)"),
+                ("visible(unit?) & !cloaked(unit?)", "can_target(unit?)")
+            ]
+        ).expect("Impossible")
+    );
+    inference_engine.infer();
+    let mut hypothesis: hypothesis::Hypothesis = inference_engine.assume([Fact::parse("detected(zergling_1)").expect("Impossible"), Fact::parse("!cloaked(mutalisk_1)").expect("Impossible")]);
+    hypothesis.infer();
+    assert!(hypothesis.prove(&Fact::parse("can_attack(zergling_1)").expect("Impossible")));
+    let changes: FactDiff = hypothesis.diff();
+    let text = |facts: &[Fact]| facts.iter().map(Fact::to_string).collect::<Vec<String>>();
+    assert_eq!(text(&changes.added), vec!["can_attack(zergling_1)", "can_target(mutalisk_1)", "detected(zergling_1)"]);
+    assert_eq!(text(&changes.removed), vec!["cloaked(mutalisk_1)"]);
+    hypothesis.discard();
+    assert!(!inference_engine.knowledge_base.has_fact(&Fact::parse("can_attack(zergling_1)").expect("Impossible")));
+    assert!(inference_engine.knowledge_base.has_fact(&Fact::parse("cloaked(mutalisk_1)").expect("Impossible")));
+}
@@ -0,0 +1,52 @@
+use super::{write_test_files, TestDirectory};
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, KnowledgeBase};
+#[test]
+fn import_test() {
+    let fact = |text: &str| Fact::parse(text).expect("Impossible");
+    let mut knowledge_base: KnowledgeBase = KnowledgeBase::new();
+    let csv: &str = "name,department,salary\r\nJohn Smith,Engineering,60000\r\n\"Ama \"\"AJ\"\" Mensah\",\"Human\nResources\",45000\r\n\r\n";
+    assert_eq!(knowledge_base.import_csv("employee", csv, std::path::Path::new("staff.csv")), Ok(2));
+    assert!(knowledge_base.has_fact(&fact("employee(John_Smith, Engineering, 60000)")));
+    assert!(knowledge_base.has_fact(&fact("employee(Ama_AJ_Mensah, Human_Resources, 45000)")));
+    assert_eq!(knowledge_base.get_fact_source(&fact("employee(John_Smith, Engineering, 60000)")).map(|source| source.line), Some(2));
+    assert!(matches!(knowledge_base.import_csv("employee", "a,b\n1\n", std::path::Path::new("short.csv")), Err(error) if error.starts_with("short.csv:2: Expected 2 column(s)")));
+    assert!(matches!(knowledge_base.import_csv("employee", "a,b\nama,\n", std::path::Path::new("empty.csv")), Err(error) if error == "empty.csv:2: Empty value; every argument of a fact needs one"));
+    assert!(matches!(knowledge_base.import_csv("employee", "a,b\nama,3000000000\n", std::path::Path::new("large.csv")), Err(error) if error.contains("3000000000 does not fit in a 32-bit integer")));
+    let json: &str = r#"{"manager": [{"boss": "John Smith", "report": "ama_aj_mensah"}], "rating": [["Ama", 4.5, true], ["ama", -4.5, "+3"]]}"#;
+    assert_eq!(knowledge_base.import_json(None, json, std::path::Path::new("org.json")), Ok(3));
+    assert!(knowledge_base.has_fact(&fact("manager(John_Smith, ama_aj_mensah)")) && knowledge_base.has_fact(&fact("rating(Ama, 4.5, true)")));
+    assert!(knowledge_base.has_fact(&fact("rating(ama, -4.5, 3)")) && !knowledge_base.has_fact(&fact("rating(ama, 4.5, true)")));
+    assert!(knowledge_base.import_json(None, "[[1, 2]]", std::path::Path::new("list.json")).is_err());
+    assert!(knowledge_base.import_json(Some("pair"), "[[1, 2], {\"a\": 1}]", std::path::Path::new("mixed.json")).is_err());
+    let directory: TestDirectory = write_test_files("import", &[
+        ("types.txt", ":- type employee(atom, atom, int).\n"),
+        ("staff.csv", "name,department,salary\njohn,engineering,lots\n")
+    ]);
+    let data: Vec<import::DataSource> = vec![import::DataSource::parse_csv(&format!("employee={}", directory.path("staff.csv"))).expect("Impossible")];
+    assert!(matches!(loader::load_sources(&[directory.path("types.txt")], &data), Err(error) if error.contains("should be int")));
+}
+#[test]
+fn export_test() {
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(KnowledgeBase::from_strings(
+        vec!["employee(john, engineering, 60000)", "employee(ama, sales, 40000)", "edge(a, b)", "edge(b, c, 2)"],
+        vec![("employee(e?, d?, s?) & s? > 50000", "bonus(e?)"), ("edge(x?, y?)", "linked"), ("edge(x?, y?, w?)", "weighted(x?, w?)")]
+    ).expect("Impossible"));
+    inference_engine.infer();
+    let knowledge_base: &KnowledgeBase = &inference_engine.knowledge_base;
+    assert_eq!(knowledge_base.export(false).to_pie(), "# bonus/1\nbonus(john).\n\n# linked/0\nlinked.\n\n# weighted/2\nweighted(b, 2).\n");
+    assert_eq!(knowledge_base.export(false).to_json().to_string(), r#"{"bonus":[["john"]],"linked":[[]],"weighted":[["b",2]]}"#);
+    let files: Vec<(String, String)> = knowledge_base.export(true).only("edge").to_csv_files();
+    assert_eq!(files, vec![
+        (String::from("edge_2.csv"), String::from("argument1,argument2\na,b\n")),
+        (String::from("edge_3.csv"), String::from("argument1,argument2,argument3\nb,c,2\n"))
+    ]);
+    let mut reloaded: KnowledgeBase = KnowledgeBase::new();
+    reloaded.import_json(None, &knowledge_base.export(true).to_json().to_string(), std::path::Path::new("export.json")).expect("Impossible");
+    let facts = |knowledge_base: &KnowledgeBase| knowledge_base.get_facts().cloned().collect::<std::collections::HashSet<Fact>>();
+    assert_eq!(facts(&reloaded), facts(knowledge_base));
+    let mut loader: loader::Loader = loader::Loader::new();
+    loader.load_source(&knowledge_base.export(true).to_pie(), std::path::Path::new("export.txt")).expect("Impossible");
+    assert_eq!(facts(&loader.finish().expect("Impossible")), facts(knowledge_base));
+}
@@ -0,0 +1,41 @@
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, KnowledgeBase};
+#[test]
+fn explain_test() {
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(
+        KnowledgeBase::from_strings(
+            vec!["employee(tom, engineering, 60000)", "manager(tom, john)", "employee(john, engineering, 50000)"],
+            vec![
+                ("manager(m?, e?) & employee(e?, d?, s?)", "manages(m?, e?, d?)"),
+                ("employee(e?, d?, s?) & s? > 50000", "senior(e?)"),
+                ("manages(m?, e?, d?) & senior(m?) & !senior(e?)", "mentor(m?, e?)")
+            ]
+        ).expect("Impossible")
+    );
+    inference_engine.infer();
+    let explanation: inference_engine::Explanation = inference_engine.explain(&Fact::parse("mentor(tom, john)").expect("Impossible")).expect("Impossible");
+    assert_eq!(explanation.to_string().lines().map(str::trim).collect::<Vec<&str>>(), vec![
+        "mentor(tom, john) by IF ((manages(m?, e?, d?) & senior(m?)) & !senior(e?)) → mentor(m?, e?)",
+        "manages(tom, john, engineering) by IF (manager(m?, e?) & employee(e?, d?, s?)) → manages(m?, e?, d?)",
+        "manager(tom, john) (given)",
+        "employee(john, engineering, 50000) (given)",
+        "senior(tom) by IF (employee(e?, d?, s?) & (s? > 50000)) → senior(e?)",
+        "employee(tom, engineering, 60000) (given)",
+        "60000 > 50000",
+        "!senior(john) (not known)"
+    ]);
+    assert!(inference_engine.explain(&Fact::parse("senior(john)").expect("Impossible")).is_none());
+}
+#[test]
+fn change_unwind_test() {
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(
+        KnowledgeBase::from_strings(vec!["parent(ama, osei)"], vec![("parent(x?, y?)", "related(x?, y?)")]).expect("Impossible")
+    );
+    inference_engine.infer();
+    let unwound: std::thread::Result<Result<knowledge_base::FactDiff, String>> = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| inference_engine.change(|_| panic!("change failed"))));
+    assert!(unwound.is_err());
+    assert!(inference_engine.knowledge_base.has_fact(&Fact::parse("parent(ama, osei)").expect("Impossible")));
+    assert!(inference_engine.knowledge_base.has_fact(&Fact::parse("related(ama, osei)").expect("Impossible")));
+    assert_eq!(inference_engine.knowledge_base.get_rules().count(), 1);
+}
@@ -0,0 +1,12 @@
+use crate::*;
+#[test]
+fn json_test() {
+    let source: &str = r#"{"name":"pie","values":[1,-2.5,true,null],"text":"line\n\"quoted\" é😀"}"#;
+    let value: json::Json = json::Json::parse(source).expect("Impossible");
+    assert_eq!(value.get_path(&["name"]).and_then(json::Json::as_str), Some("pie"));
+    assert_eq!(value.get("text").and_then(json::Json::as_str), Some("line\n\"quoted\" é😀"));
+    assert_eq!(json::Json::parse(&value.to_string()).expect("Impossible"), value);
+    assert!(json::Json::parse("{\"unterminated\": [1, 2}").is_err());
+    assert!(json::Json::parse(&format!("{}{}", "[".repeat(128), "]".repeat(128))).is_ok());
+    assert_eq!(json::Json::parse(&"[".repeat(200_000)), Err(String::from("JSON nested too deeply")));
+}
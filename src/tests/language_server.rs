@@ -0,0 +1,28 @@
+use crate::*;
+#[test]
+fn language_server_test() {
+    use json::Json;
+    let mut server: language_server::LanguageServer = language_server::LanguageServer::new();
+    let uri: &str = "file:///tmp/pie_language_server.txt";
+    let request = |method: &str, params: Json| Json::object(vec![("jsonrpc", Json::string("2.0")), ("id", Json::Number(1.0)), ("method", Json::string(method)), ("params", params)]);
+    let position = |line: f64, character: f64| Json::object(vec![
+        ("textDocument", Json::object(vec![("uri", Json::string(uri))])),
+        ("position", Json::object(vec![("line", Json::Number(line)), ("character", Json::Number(character))]))
+    ]);
+    let initialized: Vec<Json> = server.handle(&request("initialize", Json::object(vec![])));
+    assert_eq!(initialized[0].get_path(&["result", "capabilities", "hoverProvider"]), Some(&Json::Bool(true)));
+    let text: &str = "parent(ama, kofi).\nparent(kofi, osei).\nparent(x?, y?) & parent(y?, z?) & alive(x?) -> grandparent(x?, z?).\n";
+    let opened: Vec<Json> = server.handle(&Json::object(vec![
+        ("jsonrpc", Json::string("2.0")),
+        ("method", Json::string("textDocument/didOpen")),
+        ("params", Json::object(vec![("textDocument", Json::object(vec![("uri", Json::string(uri)), ("text", Json::string(text))]))]))
+    ]));
+    let diagnostics: String = opened[0].get_path(&["params", "diagnostics"]).expect("Impossible").to_string();
+    assert!(diagnostics.contains("undefined-predicate") && diagnostics.contains("\"line\":2"));
+    let definition: String = server.handle(&request("textDocument/definition", position(2.0, 20.0)))[0].to_string();
+    assert!(definition.contains("\"uri\":\"file:///tmp/pie_language_server.txt\"") && definition.contains("\"line\":0") && definition.contains("\"line\":1"));
+    let hover: String = server.handle(&request("textDocument/hover", position(2.0, 2.0)))[0].to_string();
+    assert!(hover.contains("parent/2") && hover.contains("Known facts (2)") && hover.contains("parent(ama, kofi)"));
+    let completion: String = server.handle(&request("textDocument/completion", position(0.0, 0.0)))[0].to_string();
+    assert!(completion.contains("\"label\":\"grandparent\"") && completion.contains("\"detail\":\"parent/2\""));
+}
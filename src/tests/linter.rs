@@ -0,0 +1,19 @@
+use super::{write_test_files, TestDirectory};
+use crate::loader::load_files;
+use crate::linter::{lint, LintWarning};
+#[test]
+fn lint_test() {
+    let directory: TestDirectory = write_test_files("lint", &[
+        ("lint.txt", "has_symptom(p1, fever).\nhas_symptom(p1, fever).\ndisease(flu).\nhas_symptom(p?, fever) & severe_symptoms(p?) -> recommend_doctor(p?).\nhas_symptom(p?, s?) & has_symptom(p?, Rash) -> rash(p?).\n")
+    ]);
+    let warnings: Vec<LintWarning> = lint(&load_files(&[directory.path("lint.txt")]).expect("Impossible"));
+    let found: Vec<(usize, &str)> = warnings.iter().map(|warning: &LintWarning| (warning.location.as_ref().expect("Impossible").line, warning.code)).collect();
+    assert_eq!(found, vec![
+        (2, "duplicate-fact"),
+        (3, "unused-predicate"),
+        (4, "undefined-predicate"),
+        (4, "unreachable-rule"),
+        (5, "capitalised-constant"),
+        (5, "singleton-variable")
+    ]);
+}
@@ -0,0 +1,65 @@
+use super::{write_test_files, TestDirectory};
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, KnowledgeBase};
+use crate::loader::load_files;
+#[test]
+fn include_test() {
+    let directory: TestDirectory = write_test_files("include", &[
+        ("shared.txt", "mother(x?, y?) -> parent(x?, y?).\n"),
+        ("main.txt", "include \"shared.txt\".\nmother(ama, osei).\n")
+    ]);
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(
+        load_files(&[directory.path("main.txt")]).expect("Impossible")
+    );
+    inference_engine.infer();
+    assert!(inference_engine.knowledge_base.has_fact(&Fact::parse("parent(ama, osei)").expect("Impossible")));
+}
+#[test]
+fn include_cycle_test() {
+    let directory: TestDirectory = write_test_files("include_cycle", &[
+        ("a.txt", "include \"b.txt\".\n"),
+        ("b.txt", "include \"a.txt\".\n")
+    ]);
+    let error: String = load_files(&[directory.path("a.txt")]).err().expect("Impossible");
+    assert!(error.contains("Include cycle"));
+}
+#[test]
+fn multiple_files_test() {
+    let directory: TestDirectory = write_test_files("multiple_files", &[
+        ("facts.txt", "# facts\nmother(ama, osei).\n"),
+        ("rules.txt", "mother(x?, y?) -> parent(x?, y?).\n")
+    ]);
+    let knowledge_base: KnowledgeBase = load_files(&[
+        directory.path("facts.txt"),
+        directory.path("rules.txt")
+    ]).expect("Impossible");
+    let source: &knowledge_base::SourceLocation = knowledge_base.get_fact_source(&Fact::parse("mother(ama, osei)").expect("Impossible")).expect("Impossible");
+    assert_eq!(source.file, directory.join("facts.txt"));
+    assert_eq!(source.line, 2);
+    assert_eq!(knowledge_base.get_rule_source(0).expect("Impossible").file, directory.join("rules.txt"));
+}
+#[test]
+fn module_test() {
+    let directory: TestDirectory = write_test_files("module", &[
+        ("hr.txt", "module hr.\nexport employee/2.\nemployee(john, engineering).\nperson(john).\n"),
+        ("payroll.txt", "module payroll.\nimport hr::employee/2.\nemployee(x?, engineering) -> person(x?).\n")
+    ]);
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(
+        load_files(&[directory.path("hr.txt"), directory.path("payroll.txt")]).expect("Impossible")
+    );
+    inference_engine.infer();
+    assert!(inference_engine.knowledge_base.has_fact(&Fact::parse("hr::person(john)").expect("Impossible")));
+    assert!(inference_engine.knowledge_base.has_fact(&Fact::parse("payroll::person(john)").expect("Impossible")));
+    assert!(!inference_engine.knowledge_base.has_fact(&Fact::parse("person(john)").expect("Impossible")));
+}
+#[test]
+fn module_export_test() {
+    let directory: TestDirectory = write_test_files("module_export", &[
+        ("hr.txt", "module hr.\nexport employee/2.\nsalary(john, 50000).\n"),
+        ("payroll.txt", "hr::salary(x?, s?) -> paid(x?).\n")
+    ]);
+    let error: String = load_files(&[directory.path("hr.txt"), directory.path("payroll.txt")]).err().expect("Impossible");
+    assert!(error.contains("payroll.txt:1"));
+    assert!(error.contains("not exported"));
+}
@@ -1,4 +1,24 @@
-use super::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, KnowledgeBase};
+mod commands;
+mod datalog;
+mod ffi;
+mod formatter;
+mod graph;
+mod hypothesis;
+mod import;
+mod inference_engine;
+mod json;
+mod language_server;
+mod linter;
+mod loader;
+mod repl;
+mod server;
+mod storage;
+mod trace;
+mod transaction;
+mod types;
+mod watcher;
 #[test]
 fn atomic_fact_test() {
     let mut inference_engine: InferenceEngine = InferenceEngine::new(
@@ -129,4 +149,30 @@ fn nested_negation_test() {
     let zergling_attackable: Fact = Fact::parse("can_attack(zergling_1)").expect("Impossible");
     inference_engine.infer();
     assert!(!inference_engine.knowledge_base.has_fact(&zergling_attackable));
-}
\ No newline at end of file
+}
+/// A temporary directory of test files, removed when the test ends.
+struct TestDirectory(std::path::PathBuf);
+impl std::ops::Deref for TestDirectory {
+    type Target = std::path::Path;
+    fn deref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+impl TestDirectory {
+    fn path(&self, file_name: &str) -> String {
+        self.join(file_name).display().to_string()
+    }
+}
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+fn write_test_files(name: &str, files: &[(&str, &str)]) -> TestDirectory {
+    let directory: TestDirectory = TestDirectory(std::env::temp_dir().join(format!("pie_{}_{}", name, std::process::id())));
+    std::fs::create_dir_all(&*directory).expect("Impossible");
+    for (file_name, content) in files {
+        std::fs::write(directory.join(file_name), content).expect("Impossible");
+    }
+    directory
+}
@@ -0,0 +1,45 @@
+use super::{write_test_files, TestDirectory};
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::loader::load_files;
+#[test]
+fn repl_test() {
+    let directory: TestDirectory = write_test_files("repl", &[
+        ("units.txt", "visible(zergling_1).\nvisible(u?) & !cloaked(u?) -> targetable(u?).\n"),
+        ("more.txt", "visible(mutalisk_1).\n")
+    ]);
+    let path: String = directory.path("units.txt");
+    let mut repl: repl::Repl = repl::Repl::new(InferenceEngine::new(load_files(std::slice::from_ref(&path)).expect("Impossible")), vec![path], output::Format::Text);
+    let mut execute = |line: &str| match repl.execute(line) {
+        repl::Outcome::Continue(response) => response,
+        repl::Outcome::Quit => String::from("quit")
+    };
+    assert_eq!(execute("targetable(x?)"), "   targetable(zergling_1)\n");
+    assert_eq!(execute(":assert cloaked(zergling_1)"), "  - targetable(zergling_1)\n");
+    assert_eq!(execute(&format!(":load {}", directory.path("more.txt"))), "  + targetable(mutalisk_1)\n");
+    assert_eq!(execute(":retract cloaked(zergling_1)"), "  + targetable(zergling_1)\n");
+    assert_eq!(execute(":rule targetable(u?) -> attack(u?)"), "  + attack(mutalisk_1)\n  + attack(zergling_1)\n");
+    assert!(execute(":rule visible(u?) -> attack(other?)").contains("Unsafe rule"));
+    assert_eq!(execute(":facts attack/1"), "  attack(mutalisk_1)   (derived)\n  attack(zergling_1)   (derived)\n");
+    assert_eq!(execute(":reset"), "  Cleared derived facts\n");
+    assert_eq!(execute(":facts attack"), "  No facts\n");
+    assert_eq!(execute(":infer").lines().count(), 4);
+    assert_eq!(execute(":reload"), "  - attack(mutalisk_1)\n  - attack(zergling_1)\n");
+    assert_eq!(execute(":quit"), "quit");
+}
+#[test]
+fn repl_loader_test() {
+    let directory: TestDirectory = write_test_files("repl_loader", &[
+        ("units.txt", "visible(zergling_1).\nvisible(u?) -> targetable(u?).\n"),
+        ("hr.txt", "module hr.\nexport employee/1.\nemployee(ama).\n"),
+        ("payroll.txt", "hr::employee(x?) -> paid(x?).\n")
+    ]);
+    let input: String = format!(":load {}\n:load {}\n:load {}\n:load {}\n:rules\n", directory.path("hr.txt"), directory.path("hr.txt"), directory.path("units.txt"), directory.path("payroll.txt"));
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(commands::run("repl", &[directory.path("units.txt")], input.as_bytes(), &mut output), 0);
+    let output: String = String::from_utf8(output).expect("Impossible");
+    // Files already loaded, at startup or earlier in the session, are not loaded again, and modules stay visible.
+    assert_eq!(output.matches("No change in derived facts").count(), 3);
+    assert!(output.contains("+ paid(ama)"));
+    assert_eq!(output.matches("   # ").count(), 2);
+}
@@ -0,0 +1,76 @@
+use super::{write_test_files, TestDirectory};
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::KnowledgeBase;
+#[test]
+fn server_test() {
+    use std::io::{BufRead, Write};
+    let mut server: server::Server = server::Server::new(InferenceEngine::new(KnowledgeBase::new()));
+    let mut request = |line: &str| server.handle_line(line).map(|response: json::Json| response.to_string());
+    assert_eq!(request(r#"{"jsonrpc":"2.0","id":1,"method":"load","params":{"source":"parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).\nparent(ama, osei)."}}"#).expect("Impossible"),
+        r#"{"jsonrpc":"2.0","id":1,"result":{"added":[],"removed":[]}}"#);
+    assert_eq!(request(r#"{"jsonrpc":"2.0","id":2,"method":"assert","params":{"facts":["parent(osei, kofi)"]}}"#).expect("Impossible"),
+        r#"{"jsonrpc":"2.0","id":2,"result":{"added":["grandparent(ama, kofi)"],"removed":[]}}"#);
+    assert_eq!(request(r#"{"jsonrpc":"2.0","id":"q","method":"query","params":{"pattern":"grandparent(x?, kofi)"}}"#).expect("Impossible"),
+        r#"{"jsonrpc":"2.0","id":"q","result":{"matches":[{"fact":"grandparent(ama, kofi)","bindings":{"x":"ama"}}]}}"#);
+    assert!(request(r#"{"jsonrpc":"2.0","id":3,"method":"retract","params":{"fact":"parent(ama, kofi)"}}"#).expect("Impossible").contains(r#""error":{"code":-32000,"message":"parent(ama, kofi) is not a given fact"}"#));
+    assert!(request(r#"{"jsonrpc":"2.0","id":4,"method":"forget"}"#).expect("Impossible").contains("-32601"));
+    assert!(request("{").expect("Impossible").contains("-32700"));
+    assert_eq!(request(r#"{"jsonrpc":"2.0","method":"retract","params":{"fact":"parent(osei, kofi)"}}"#), None);
+    assert_eq!(request(r#"{"jsonrpc":"2.0","id":6,"method":"load","params":{"source":"age(kofi, old).\nage(x?, a?) & a? > 18 -> adult(x?)."}}"#).expect("Impossible"),
+        r#"{"jsonrpc":"2.0","id":6,"result":{"added":[],"removed":[]}}"#);
+    let directory: TestDirectory = write_test_files("server", &[("rules.txt", "parent(x?, y?) -> related(x?, y?).\n")]);
+    let load: String = format!(r#"{{"jsonrpc":"2.0","id":7,"method":"load","params":{{"paths":["{}"]}}}}"#, directory.path("rules.txt"));
+    assert!(request(&load).expect("Impossible").contains(r#""added":["related(ama, osei)"]"#));
+    assert!(request(&load).expect("Impossible").contains(r#""result":{"added":[],"removed":[]}"#));
+    assert!(server.counts().contains(&(String::from("rules"), json::Json::Number(3.0))));
+    let mut lines: &[u8] = b"{\"jsonrpc\":\"2.0\",\"id\":8,\"method\":\"infer\"}\r\n";
+    assert_eq!(server::read_line(&mut lines), Ok(Some(String::from(r#"{"jsonrpc":"2.0","id":8,"method":"infer"}"#))));
+    assert_eq!(server::read_line(&mut lines), Ok(None));
+    let long: Vec<u8> = vec![b'['; server::MAX_LINE_LENGTH + 1];
+    assert!(server::read_line(&mut long.as_slice()).expect_err("Impossible").contains("longer than"));
+    let listener: std::net::TcpListener = std::net::TcpListener::bind("127.0.0.1:0").expect("Impossible");
+    let address: std::net::SocketAddr = listener.local_addr().expect("Impossible");
+    let server: std::sync::Arc<std::sync::Mutex<server::Server>> = std::sync::Arc::new(std::sync::Mutex::new(server));
+    std::thread::spawn(move || server::serve_connections(server, listener));
+    let mut stream: std::net::TcpStream = std::net::TcpStream::connect(address).expect("Impossible");
+    writeln!(stream, r#"{{"jsonrpc":"2.0","id":5,"method":"prove","params":{{"goal":"grandparent(ama, kofi)"}}}}"#).expect("Impossible");
+    let mut response: String = String::new();
+    std::io::BufReader::new(stream).read_line(&mut response).expect("Impossible");
+    assert_eq!(response, "{\"jsonrpc\":\"2.0\",\"id\":5,\"result\":{\"goal\":\"grandparent(ama, kofi)\",\"proven\":false}}\n");
+}
+#[test]
+fn http_test() {
+    use std::io::{Read, Write};
+    let mut http_server: http::HttpServer = http::HttpServer::new();
+    http_server.add("family", server::Server::new(InferenceEngine::new(KnowledgeBase::from_strings(
+        vec!["parent(ama, osei)"], vec![("parent(x?, y?) & parent(y?, z?)", "grandparent(x?, z?)")]
+    ).expect("Impossible"))));
+    http_server.add("empty", server::Server::new(InferenceEngine::new(KnowledgeBase::new())));
+    let listener: std::net::TcpListener = std::net::TcpListener::bind("127.0.0.1:0").expect("Impossible");
+    let address: std::net::SocketAddr = listener.local_addr().expect("Impossible");
+    std::thread::spawn(move || http::serve_connections(std::sync::Arc::new(http_server), listener));
+    let request = |method: &str, target: &str, body: &str| {
+        let mut stream: std::net::TcpStream = std::net::TcpStream::connect(address).expect("Impossible");
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}", method, target, body.len(), body).expect("Impossible");
+        let mut response: String = String::new();
+        stream.read_to_string(&mut response).expect("Impossible");
+        let (head, body) = response.split_once("\r\n\r\n").expect("Impossible");
+        (head.lines().next().expect("Impossible").to_string(), body.trim_end().to_string())
+    };
+    assert_eq!(request("GET", "/kb", ""), (String::from("HTTP/1.1 200 OK"),
+        String::from(r#"[{"name":"empty","facts":0,"rules":0,"derived":0},{"name":"family","facts":1,"rules":1,"derived":0}]"#)));
+    assert_eq!(request("POST", "/kb/family/facts", r#"{"facts": ["parent(osei, kofi)"]}"#).1, r#"{"added":["grandparent(ama, kofi)"],"removed":[]}"#);
+    assert_eq!(request("GET", "/kb/family/query?q=grandparent(x%3F,+kofi)", "").1, r#"{"matches":[{"fact":"grandparent(ama, kofi)","bindings":{"x":"ama"}}]}"#);
+    let (status, facts) = request("GET", "/kb/family/facts?predicate=parent", "");
+    assert_eq!((status.as_str(), facts.matches("\"predicate\":\"parent\"").count()), ("HTTP/1.1 200 OK", 2));
+    assert!(request("GET", "/kb/family/explain?fact=grandparent(ama,%20kofi)", "").1.contains(r#"{"depth":1,"fact":"parent(ama, osei)","reason":"given","rule":null}"#));
+    assert_eq!(request("GET", "/kb/empty/facts", "").1, r#"{"facts":[]}"#);
+    assert_eq!(request("GET", "/kb/other/facts", "").0, "HTTP/1.1 404 Not Found");
+    assert_eq!(request("DELETE", "/kb/family/facts", "").0, "HTTP/1.1 405 Method Not Allowed");
+    assert_eq!(request("POST", "/kb/family/facts", r#"{"facts": ["!parent(ama, osei)"]}"#).0, "HTTP/1.1 400 Bad Request");
+    assert_eq!(request("GET", "/kb/family/query", "").0, "HTTP/1.1 400 Bad Request");
+    assert_eq!(request("GET", "/kb/fam%+1ly/facts", "").1, r#"{"error":"No knowledge base named 'fam% 1ly'"}"#);
+    let head: String = format!("GET /kb HTTP/1.1\r\nCookie: {}\r\n\r\n", "x".repeat(http::MAX_HEAD_LENGTH));
+    assert!(matches!(http::read_request(&mut head.as_bytes()), Err(error) if error.starts_with("Request head is larger than")));
+}
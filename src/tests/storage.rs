@@ -0,0 +1,65 @@
+use super::{write_test_files, TestDirectory};
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, KnowledgeBase, TypeDeclaration};
+use crate::loader::load_files;
+#[test]
+fn storage_test() {
+    let directory: TestDirectory = write_test_files("storage", &[
+        ("family.txt", ":- type parent(atom, atom).\nparent(ama, osei).\nparent(osei, kofi).\nparent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).\n"),
+        ("more.txt", "parent(kofi, esi).\n")
+    ]);
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(load_files(&[directory.path("family.txt")]).expect("Impossible"));
+    inference_engine.infer();
+    inference_engine.knowledge_base.checkpoint(&directory.join("family.piekb")).expect("Impossible");
+    let restored: KnowledgeBase = KnowledgeBase::restore(&directory.join("family.piekb")).expect("Impossible");
+    assert_eq!(storage::to_bytes(&restored), storage::to_bytes(&inference_engine.knowledge_base));
+    assert!(restored.working_memory().contains(&Fact::parse("grandparent(ama, kofi)").expect("Impossible")));
+    assert_eq!(restored.get_fact_source(&Fact::parse("parent(osei, kofi)").expect("Impossible")).map(|source| source.line), Some(3));
+    assert!(restored.get_type_declaration("parent").is_some());
+    let explanation: String = InferenceEngine::new(restored.clone()).explain(&Fact::parse("grandparent(ama, kofi)").expect("Impossible")).expect("Impossible").to_string();
+    assert!(explanation.contains("parent(ama, osei) (given)") && explanation.contains("parent(osei, kofi) (given)"));
+    let extended: KnowledgeBase = load_files(&[directory.path("family.piekb"), directory.path("more.txt")]).expect("Impossible");
+    assert!(extended.has_fact(&Fact::parse("parent(kofi, esi)").expect("Impossible")) && extended.working_memory().is_empty());
+    assert!(matches!(load_files(&[directory.path("more.txt"), directory.path("family.piekb")]), Err(error) if error.contains("can only be given as the first file")));
+    let mut bytes: Vec<u8> = storage::to_bytes(&restored);
+    assert!(matches!(storage::from_bytes(&bytes[..bytes.len() - 3]), Err(error) if error.contains("truncated")));
+    bytes[storage::MAGIC.len()] = 99;
+    assert!(matches!(storage::from_bytes(&bytes), Err(error) if error.contains("Unsupported knowledge base format version 99")));
+    let fact = |text: &str| knowledge_base::AntecedentItem::Fact(Fact::parse(text).expect("Impossible"));
+    for antecedents in [vec![fact("p(x?)"), fact("q(x?)")], vec![fact("p(y?)")]] {
+        let mut corrupt: KnowledgeBase = KnowledgeBase::new();
+        corrupt.add_axiomatic_rule(knowledge_base::Rule::new(antecedents, Fact::parse("r(x?)").expect("Impossible")));
+        assert!(matches!(storage::from_bytes(&storage::to_bytes(&corrupt)), Err(error) if error.starts_with("Corrupt knowledge base file: ")));
+    }
+}
+#[test]
+fn event_log_test() {
+    let directory: TestDirectory = write_test_files("event_log", &[]);
+    let path: std::path::PathBuf = directory.join("events.log");
+    let _ = std::fs::remove_file(&path);
+    let fact = |text: &str| Fact::parse(text).expect("Impossible");
+    let mut knowledge_base: KnowledgeBase = KnowledgeBase::from_strings(vec!["reading(sensor_1, 40)"], vec![("reading(s?, t?) & t? > 30", "overheated(s?)")]).expect("Impossible");
+    knowledge_base.add_type_declaration(TypeDeclaration::parse("reading(atom, int)").expect("Impossible")).expect("Impossible");
+    knowledge_base.attach_log(event_log::EventLog::open(&path, "operator").expect("Impossible"));
+    let mut transaction: transaction::Transaction = knowledge_base.begin();
+    transaction.add_fact(fact("reading(sensor_2, hot)")).expect("Impossible");
+    assert!(transaction.commit().is_err());
+    let mut transaction: transaction::Transaction = knowledge_base.begin();
+    transaction.add_fact(fact("reading(sensor_2, 35)")).expect("Impossible");
+    transaction.remove_fact(fact("reading(sensor_1, 40)"));
+    transaction.commit_and_infer().expect("Impossible");
+    assert!(!knowledge_base.remove_axiomatic_fact(&fact("reading(sensor_9, 20)")));
+    let entries: Vec<event_log::LogEntry> = event_log::read_log(std::io::BufReader::new(std::fs::File::open(&path).expect("Impossible"))).expect("Impossible");
+    assert!(entries.iter().all(|entry: &event_log::LogEntry| entry.source == "operator" && entry.time.len() == 24));
+    assert!(!entries.iter().any(|entry: &event_log::LogEntry| entry.event == event_log::Event::Assert(fact("reading(sensor_2, hot)"), None)));
+    assert!(!entries.iter().any(|entry: &event_log::LogEntry| entry.event == event_log::Event::Retract(fact("reading(sensor_9, 20)"))));
+    let replayed: KnowledgeBase = event_log::replay(&entries).expect("Impossible");
+    assert_eq!(storage::to_bytes(&replayed), storage::to_bytes(&knowledge_base));
+    let attached: usize = entries.iter().position(|entry: &event_log::LogEntry| matches!(entry.event, event_log::Event::AddRule(..))).expect("Impossible") + 1;
+    let before: KnowledgeBase = event_log::replay(&entries[..attached]).expect("Impossible");
+    assert!(before.has_fact(&fact("reading(sensor_1, 40)")) && !before.has_fact(&fact("reading(sensor_2, 35)")));
+    assert!(replayed.has_fact(&fact("overheated(sensor_2)")) && !replayed.has_fact(&fact("overheated(sensor_1)")));
+    let time: std::time::SystemTime = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1_700_000_000_123);
+    assert_eq!(event_log::format_time(time), "2023-11-14T22:13:20.123Z");
+}
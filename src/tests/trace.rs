@@ -0,0 +1,96 @@
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, FactDiff, KnowledgeBase};
+#[test]
+fn trace_test() {
+    use std::sync::{Arc, Mutex};
+    use crate::trace::{CountingTracer, FilteredTracer, TraceEvent, Tracer};
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+    impl Tracer for Recorder {
+        fn event(&mut self, event: &TraceEvent) {
+            self.0.lock().expect("Impossible").push(event.to_string());
+        }
+    }
+    let knowledge_base: KnowledgeBase = KnowledgeBase::from_strings(
+        vec!["parent(ama, osei)", "parent(osei, kofi)"],
+        vec![("parent(x?, y?) & parent(y?, z?)", "grandparent(x?, z?)")]
+    ).expect("Impossible");
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(knowledge_base.clone());
+    let events: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    inference_engine.set_tracer(Some(Box::new(FilteredTracer::new(vec!["enter", "exit", "fire", "add"], Recorder(Arc::clone(&events))))));
+    assert!(inference_engine.prove(&Fact::parse("grandparent(ama, kofi)").expect("Impossible")));
+    assert_eq!(*events.lock().expect("Impossible"), vec![
+        "goal grandparent(ama, kofi)",
+        "goal parent(ama, osei)",
+        "proven parent(ama, osei)",
+        "goal parent(osei, kofi)",
+        "proven parent(osei, kofi)",
+        "fire parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?) => grandparent(ama, kofi)",
+        "proven grandparent(ama, kofi)",
+        "add grandparent(ama, kofi)"
+    ]);
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(knowledge_base);
+    let counter: Arc<Mutex<CountingTracer>> = Arc::new(Mutex::new(CountingTracer::new()));
+    inference_engine.set_tracer(Some(Box::new(Arc::clone(&counter))));
+    inference_engine.infer();
+    let changes: FactDiff = inference_engine.change(|mut knowledge_base: KnowledgeBase| {
+        knowledge_base.remove_axiomatic_fact(&Fact::parse("parent(osei, kofi)").expect("Impossible"));
+        Ok(knowledge_base)
+    }).expect("Impossible");
+    assert_eq!(changes.removed.len(), 1);
+    let counter = counter.lock().expect("Impossible");
+    assert_eq!((counter.count("rule"), counter.count("fire"), counter.count("add"), counter.count("remove")), (3, 2, 1, 1));
+    assert!(counter.count("unify") > 0);
+    assert!(inference_engine.set_tracer(None).is_some());
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+    impl std::io::Write for Shared {
+        fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().expect("Impossible").write(buffer)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let written: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+    let mut reporting: CountingTracer = CountingTracer::reporting_to(Shared(Arc::clone(&written)));
+    reporting.event(&TraceEvent::FactAdded { fact: &Fact::parse("grandparent(ama, kofi)").expect("Impossible") });
+    assert!(written.lock().expect("Impossible").is_empty());
+    reporting.finish();
+    reporting.finish();
+    assert_eq!(String::from_utf8(written.lock().expect("Impossible").clone()).expect("Impossible").matches("add     1\n").count(), 1);
+}
+#[test]
+fn profile_test() {
+    use std::sync::{Arc, Mutex};
+    use crate::profile::{Profiler, RuleStatistics};
+    use crate::trace::{CountingTracer, Tracer};
+    let knowledge_base: KnowledgeBase = KnowledgeBase::from_strings(
+        vec!["parent(ama, osei)", "parent(osei, kofi)", "parent(kofi, esi)", "female(esi)"],
+        vec![("parent(x?, y?) & parent(y?, z?)", "grandparent(x?, z?)"), ("grandparent(x?, y?) & female(y?)", "granddaughter(y?, x?)")]
+    ).expect("Impossible");
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(knowledge_base.clone());
+    let profiler: Arc<Mutex<Profiler>> = Arc::new(Mutex::new(Profiler::new()));
+    let counter: Arc<Mutex<CountingTracer>> = Arc::new(Mutex::new(CountingTracer::new()));
+    let tracers: Vec<Box<dyn Tracer>> = vec![Box::new(Arc::clone(&profiler)), Box::new(Arc::clone(&counter))];
+    inference_engine.set_tracer(Some(Box::new(tracers)));
+    inference_engine.infer();
+    let profiler = profiler.lock().expect("Impossible");
+    let statistics: Vec<(&str, &RuleStatistics)> = profiler.statistics();
+    assert_eq!(statistics.len(), 2);
+    let grandparent: &RuleStatistics = statistics.iter().find(|(rule, _)| rule.ends_with("grandparent(x?, z?)")).expect("Impossible").1;
+    // Every pass fires a rule again for what it already concluded, but only new facts count as derived.
+    assert_eq!((grandparent.activations, grandparent.substitutions, grandparent.derived), (6, 6, 2));
+    assert!(grandparent.unifications > grandparent.substitutions);
+    let granddaughter: &RuleStatistics = statistics.iter().find(|(rule, _)| rule.ends_with("granddaughter(y?, x?)")).expect("Impossible").1;
+    assert_eq!((granddaughter.activations, granddaughter.derived), (2, 1));
+    assert_eq!(statistics.iter().map(|(_, statistics)| statistics.unifications).sum::<usize>(), counter.lock().expect("Impossible").count("unify"));
+    assert!(profiler.report().lines().nth(1).expect("Impossible").ends_with(statistics[0].0));
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(knowledge_base);
+    let profiler: Arc<Mutex<Profiler>> = Arc::new(Mutex::new(Profiler::new()));
+    inference_engine.set_tracer(Some(Box::new(Arc::clone(&profiler))));
+    assert!(inference_engine.prove(&Fact::parse("grandparent(osei, esi)").expect("Impossible")));
+    let profiler = profiler.lock().expect("Impossible");
+    let statistics: Vec<(&str, &RuleStatistics)> = profiler.statistics();
+    assert_eq!(statistics.len(), 1);
+    assert_eq!((statistics[0].0, statistics[0].1.activations, statistics[0].1.derived), ("parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?)", 1, 1));
+    assert_eq!(Profiler::new().report(), "No rules were evaluated\n");
+}
@@ -0,0 +1,38 @@
+use crate::*;
+use crate::knowledge_base::{Fact, KnowledgeBase, TypeDeclaration};
+#[test]
+fn transaction_test() {
+    let mut knowledge_base: KnowledgeBase = KnowledgeBase::from_strings(vec!["reading(sensor_1, 20)"], vec![("reading(s?, t?) & t? > 30", "overheated(s?)")]).expect("Impossible");
+    knowledge_base.add_type_declaration(TypeDeclaration::parse("reading(atom, int)").expect("Impossible")).expect("Impossible");
+    let reading = |text: &str| Fact::parse(text).expect("Impossible");
+    let mut transaction: transaction::Transaction = knowledge_base.begin();
+    transaction.add_fact(reading("reading(sensor_2, 35)")).expect("Impossible");
+    transaction.add_fact(reading("reading(sensor_3, hot)")).expect("Impossible");
+    assert!(transaction.commit().expect_err("Impossible").contains("should be int"));
+    assert!(!knowledge_base.has_fact(&reading("reading(sensor_2, 35)")));
+    let mut transaction: transaction::Transaction = knowledge_base.begin();
+    transaction.add_fact(reading("reading(sensor_2, 35)")).expect("Impossible");
+    let outer: transaction::Savepoint = transaction.savepoint();
+    transaction.remove_fact(reading("reading(sensor_1, 20)"));
+    let inner: transaction::Savepoint = transaction.savepoint();
+    transaction.add_fact(reading("reading(sensor_4, 50)")).expect("Impossible");
+    transaction.rollback_to(outer).expect("Impossible");
+    let again: transaction::Savepoint = transaction.savepoint();
+    transaction.add_fact(reading("reading(sensor_5, 10)")).expect("Impossible");
+    let later: transaction::Savepoint = transaction.savepoint();
+    // The rolled back savepoint is not taken for the one that now has its place.
+    assert!(transaction.rollback_to(inner).is_err());
+    transaction.release(again).expect("Impossible");
+    assert!(transaction.rollback_to(later).is_err());
+    assert!(transaction.add_fact(reading("!reading(sensor_1, 20)")).is_err());
+    assert!(transaction.add_rule(knowledge_base::Rule::parse("reading(s?, t?)", "alarm(other?)").expect("Impossible")).is_err());
+    let summary: transaction::CommitSummary = transaction.commit_and_infer().expect("Impossible");
+    assert_eq!(summary, transaction::CommitSummary { added_facts: 2, removed_facts: 0, added_rules: 0 });
+    assert!(knowledge_base.has_fact(&reading("reading(sensor_1, 20)")) && !knowledge_base.has_fact(&reading("reading(sensor_4, 50)")));
+    assert!(knowledge_base.has_fact(&reading("reading(sensor_5, 10)")));
+    assert!(knowledge_base.has_fact(&reading("overheated(sensor_2)")));
+    let mut transaction: transaction::Transaction = knowledge_base.begin();
+    transaction.remove_fact(reading("reading(sensor_2, 35)"));
+    transaction.rollback();
+    assert!(knowledge_base.has_fact(&reading("reading(sensor_2, 35)")));
+}
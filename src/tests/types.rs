@@ -0,0 +1,54 @@
+use super::{write_test_files, TestDirectory};
+use crate::*;
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::{Fact, KnowledgeBase};
+use crate::loader::load_files;
+#[test]
+fn type_declaration_test() {
+    let directory: TestDirectory = write_test_files("type_declaration", &[
+        ("valid.txt", ":- type employee(atom, atom, int).\nemployee(john, engineering, 50000).\nemployee(e?, d?, s?) & s? > 40000 -> senior(e?).\n"),
+        ("swapped.txt", ":- type employee(atom, atom, int).\nemployee(john, 50000, engineering).\n"),
+        ("arity.txt", ":- type employee(atom, atom, int).\nemployee(e?, d?) -> staff(e?).\n"),
+        ("comparison.txt", ":- type employee(atom, atom, int).\nemployee(e?, d?, s?) & d? > 5 -> odd(e?).\n")
+    ]);
+    let load = |file_name: &str| load_files(&[directory.path(file_name)]);
+    assert!(load("valid.txt").is_ok());
+    let error: String = load("swapped.txt").err().expect("Impossible");
+    assert!(error.contains("swapped.txt:2") && error.contains("argument 2 of employee(john, 50000, engineering) should be atom"));
+    assert!(load("arity.txt").err().expect("Impossible").contains("Arity mismatch"));
+    assert!(load("comparison.txt").err().expect("Impossible").contains("d? is atom but > needs int operands"));
+}
+#[test]
+fn untyped_comparison_test() {
+    let mut inference_engine: InferenceEngine = InferenceEngine::new(
+        KnowledgeBase::from_strings(
+            vec!["age(kofi, old)", "age(ama, 30)"],
+            vec![("age(x?, a?) & a? > 18", "adult(x?)"), ("age(x?, a?) & a? <= 18", "minor(x?)")]
+        ).expect("Impossible")
+    );
+    inference_engine.infer();
+    assert!(inference_engine.knowledge_base.has_fact(&Fact::parse("adult(ama)").expect("Impossible")));
+    assert!(!inference_engine.knowledge_base.has_fact(&Fact::parse("adult(kofi)").expect("Impossible")));
+    assert!(!inference_engine.knowledge_base.has_fact(&Fact::parse("minor(kofi)").expect("Impossible")));
+    assert!(!inference_engine.prove(&Fact::parse("adult(kofi)").expect("Impossible")));
+    assert!(inference_engine.explain(&Fact::parse("adult(kofi)").expect("Impossible")).is_none());
+}
+#[test]
+fn rule_safety_test() {
+    let unsafe_variable = |antecedents: &str, consequent: &str| knowledge_base::Rule::parse(antecedents, consequent).expect("Impossible").check_safety().err().map(|error| error.variable.name);
+    assert_eq!(unsafe_variable("visible(unit?)", "can_attack(other?)"), Some(String::from("other")));
+    assert_eq!(unsafe_variable("visible(unit?) & !has_ability(other?, cloak)", "can_target(unit?)"), Some(String::from("other")));
+    assert_eq!(unsafe_variable("visible(unit?) & other? != unit?", "can_target(unit?)"), Some(String::from("other")));
+    assert_eq!(unsafe_variable("visible(unit?) | detected(other?)", "seen(other?)"), Some(String::from("other")));
+    assert_eq!(unsafe_variable("visible(unit?) & !has_ability(unit?, cloak)", "can_target(unit?)"), None);
+    assert_eq!(unsafe_variable("[visible(unit?) | detected(unit?)] & unit? != zergling_1", "seen(unit?)"), None);
+}
+#[test]
+fn unsafe_rule_diagnostic_test() {
+    let directory: TestDirectory = write_test_files("unsafe_rule", &[
+        ("unsafe.txt", "visible(zergling_1).\nvisible(unit?) -> can_attack(other?).\n")
+    ]);
+    let error: String = load_files(&[directory.path("unsafe.txt")]).err().expect("Impossible");
+    assert!(error.contains("unsafe.txt:2:30: Unsafe rule: variable other? in the head is not bound by a positive body literal"));
+    assert!(error.ends_with("\n                               ^^^^^^"));
+}
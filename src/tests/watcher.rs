@@ -0,0 +1,20 @@
+use std::fs;
+use super::{write_test_files, TestDirectory};
+use crate::*;
+#[test]
+fn watch_test() {
+    let directory: TestDirectory = write_test_files("watch", &[
+        ("rules.txt", "parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).\n"),
+        ("family.txt", "include \"rules.txt\".\nparent(ama, kofi).\nparent(kofi, osei).\n")
+    ]);
+    let mut watcher: watcher::Watcher = watcher::Watcher::new(vec![directory.path("family.txt")]);
+    let mut output: Vec<u8> = Vec::new();
+    watcher.reload(&mut output).expect("Impossible");
+    assert_eq!(String::from_utf8_lossy(&output), "Loaded 1 derived fact(s)\n+ grandparent(ama, osei)\n");
+    assert!(!watcher.has_changed());
+    fs::write(directory.join("rules.txt"), "parent(x?, y?) -> ancestor(x?, y?).\n").expect("Impossible");
+    assert!(watcher.has_changed());
+    output.clear();
+    watcher.reload(&mut output).expect("Impossible");
+    assert_eq!(String::from_utf8_lossy(&output), "- grandparent(ama, osei)\n+ ancestor(ama, kofi)\n+ ancestor(kofi, osei)\n");
+}
@@ -0,0 +1,243 @@
+use std::{collections::BTreeMap, fmt, io::{self, Write}, sync::{Arc, Mutex}};
+
+use crate::formatter::format_antecedents;
+use crate::json::Json;
+use crate::knowledge_base::*;
+
+/// The kinds of trace events, as named by `TraceEvent::kind` and `--trace-events`.
+pub const EVENT_KINDS: [&str; 8] = ["rule", "unify", "fire", "done", "add", "remove", "enter", "exit"];
+
+/// A step of inference reported to a `Tracer`, see `InferenceEngine::set_tracer`.
+pub enum TraceEvent<'e> {
+    /// Forward chaining starts matching the body of a rule, or backward chaining tries a rule for a goal.
+    RuleConsidered { rule: &'e Rule },
+    /// A literal of a rule body was unified with a known fact, or a rule head with a goal.
+    UnificationAttempted { pattern: &'e Fact, fact: &'e Fact, unified: bool },
+    /// The body of a rule held for one substitution, concluding `fact`, which may already be known.
+    ActivationFired { rule: &'e Rule, fact: &'e Fact },
+    /// The engine is done with a rule after trying `substitutions` substitutions that match its body's literals.
+    /// Every `RuleConsidered` has one; when proving, the events of the subgoals come in between.
+    RuleFinished { rule: &'e Rule, substitutions: usize },
+    /// A derived fact was added to working memory.
+    FactAdded { fact: &'e Fact },
+    /// A derived fact no longer holds after `InferenceEngine::change`. Facts removed by editing the engine's
+    /// `knowledge_base` directly, e.g. with `remove_fact` or `clear_working_memory`, are not reported.
+    FactRemoved { fact: &'e Fact },
+    /// Backward chaining starts proving a goal; `depth` is 0 for the goal given to `prove`.
+    GoalEntered { goal: &'e Fact, depth: usize },
+    GoalExited { goal: &'e Fact, depth: usize, proven: bool }
+}
+impl TraceEvent<'_> {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TraceEvent::RuleConsidered { .. } => "rule",
+            TraceEvent::UnificationAttempted { .. } => "unify",
+            TraceEvent::ActivationFired { .. } => "fire",
+            TraceEvent::RuleFinished { .. } => "done",
+            TraceEvent::FactAdded { .. } => "add",
+            TraceEvent::FactRemoved { .. } => "remove",
+            TraceEvent::GoalEntered { .. } => "enter",
+            TraceEvent::GoalExited { .. } => "exit"
+        }
+    }
+    /// The event as an object with its kind under `event`, e.g. `{"event": "add", "fact": "grandparent(ama, kofi)"}`.
+    pub fn to_json(&self) -> Json {
+        let fact = |fact: &Fact| Json::string(fact.to_string());
+        let mut pairs: Vec<(&str, Json)> = vec![("event", Json::string(self.kind()))];
+        match self {
+            TraceEvent::RuleConsidered { rule } => pairs.push(("rule", Json::string(rule_text(rule)))),
+            TraceEvent::UnificationAttempted { pattern, fact: known, unified } => pairs.extend([("pattern", fact(pattern)), ("fact", fact(known)), ("unified", Json::Bool(*unified))]),
+            TraceEvent::ActivationFired { rule, fact: conclusion } => pairs.extend([("rule", Json::string(rule_text(rule))), ("fact", fact(conclusion))]),
+            TraceEvent::RuleFinished { rule, substitutions } => pairs.extend([("rule", Json::string(rule_text(rule))), ("substitutions", Json::Number(*substitutions as f64))]),
+            TraceEvent::FactAdded { fact: added } => pairs.push(("fact", fact(added))),
+            TraceEvent::FactRemoved { fact: removed } => pairs.push(("fact", fact(removed))),
+            TraceEvent::GoalEntered { goal, depth } => pairs.extend([("goal", fact(goal)), ("depth", Json::Number(*depth as f64))]),
+            TraceEvent::GoalExited { goal, depth, proven } => pairs.extend([("goal", fact(goal)), ("depth", Json::Number(*depth as f64)), ("proven", Json::Bool(*proven))])
+        }
+        Json::object(pairs)
+    }
+}
+impl fmt::Display for TraceEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::RuleConsidered { rule } => write!(f, "rule {}", rule_text(rule)),
+            TraceEvent::UnificationAttempted { pattern, fact, unified } => write!(f, "unify {} with {}: {}", pattern, fact, if *unified { "ok" } else { "failed" }),
+            TraceEvent::ActivationFired { rule, fact } => write!(f, "fire {} => {}", rule_text(rule), fact),
+            TraceEvent::RuleFinished { rule, substitutions } => write!(f, "done {} ({} substitution(s))", rule_text(rule), substitutions),
+            TraceEvent::FactAdded { fact } => write!(f, "add {}", fact),
+            TraceEvent::FactRemoved { fact } => write!(f, "remove {}", fact),
+            TraceEvent::GoalEntered { goal, .. } => write!(f, "goal {}", goal),
+            TraceEvent::GoalExited { goal, proven, .. } => write!(f, "{} {}", if *proven { "proven" } else { "failed" }, goal)
+        }
+    }
+}
+
+/// A rule on one line, as written in trace output and profiles.
+pub fn rule_text(rule: &Rule) -> String {
+    format!("{} -> {}", format_antecedents(rule), rule.consequent)
+}
+
+/// Receives the steps of inference as they happen. Tracers only observe: they cannot change the outcome.
+pub trait Tracer: Send {
+    fn event(&mut self, event: &TraceEvent);
+    /// Called by whoever set up the tracer once there is nothing more to trace, for tracers that report totals.
+    fn finish(&mut self) {}
+}
+/// A shared tracer, so its results can be read after handing it to an engine.
+impl<T: Tracer + ?Sized> Tracer for Arc<Mutex<T>> {
+    fn event(&mut self, event: &TraceEvent) {
+        self.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).event(event);
+    }
+    fn finish(&mut self) {
+        self.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).finish();
+    }
+}
+impl<T: Tracer + ?Sized> Tracer for Box<T> {
+    fn event(&mut self, event: &TraceEvent) {
+        (**self).event(event);
+    }
+    fn finish(&mut self) {
+        (**self).finish();
+    }
+}
+/// Several tracers, each receiving every event in turn.
+impl<T: Tracer> Tracer for Vec<T> {
+    fn event(&mut self, event: &TraceEvent) {
+        for tracer in self.iter_mut() {
+            tracer.event(event);
+        }
+    }
+    fn finish(&mut self) {
+        for tracer in self.iter_mut() {
+            tracer.finish();
+        }
+    }
+}
+
+/// Writes one line of text per event, indenting the steps of backward chaining by the depth of their goal.
+pub struct PrettyTracer<W: Write + Send> {
+    output: W,
+    depth: usize
+}
+impl<W: Write + Send> PrettyTracer<W> {
+    pub fn new(output: W) -> Self {
+        PrettyTracer { output, depth: 0 }
+    }
+}
+impl<W: Write + Send> Tracer for PrettyTracer<W> {
+    fn event(&mut self, event: &TraceEvent) {
+        if let TraceEvent::GoalExited { depth, .. } = event {
+            self.depth = *depth;
+        }
+        let _ = writeln!(self.output, "{}{}", "  ".repeat(self.depth), event);
+        if let TraceEvent::GoalEntered { depth, .. } = event {
+            self.depth = depth + 1;
+        }
+    }
+}
+
+/// Writes each event as a JSON object on its own line, see `TraceEvent::to_json`.
+pub struct JsonTracer<W: Write + Send> {
+    output: W
+}
+impl<W: Write + Send> JsonTracer<W> {
+    pub fn new(output: W) -> Self {
+        JsonTracer { output }
+    }
+}
+impl<W: Write + Send> Tracer for JsonTracer<W> {
+    fn event(&mut self, event: &TraceEvent) {
+        let _ = writeln!(self.output, "{}", event.to_json());
+    }
+}
+
+/// Counts the events of each kind. With `reporting_to`, the totals are written out by `finish`.
+#[derive(Default)]
+pub struct CountingTracer {
+    counts: BTreeMap<&'static str, usize>,
+    output: Option<Box<dyn Write + Send>>
+}
+impl CountingTracer {
+    pub fn new() -> Self {
+        CountingTracer::default()
+    }
+    pub fn reporting_to(output: impl Write + Send + 'static) -> Self {
+        CountingTracer { counts: BTreeMap::new(), output: Some(Box::new(output)) }
+    }
+    pub fn count(&self, kind: &str) -> usize {
+        self.counts.get(kind).copied().unwrap_or(0)
+    }
+    /// One line per kind of event, in the order of `EVENT_KINDS`.
+    pub fn summary(&self) -> String {
+        EVENT_KINDS.iter().map(|kind: &&str| format!("{:<8}{}\n", kind, self.count(kind))).collect()
+    }
+}
+impl Tracer for CountingTracer {
+    fn event(&mut self, event: &TraceEvent) {
+        *self.counts.entry(event.kind()).or_insert(0) += 1;
+    }
+    fn finish(&mut self) {
+        if let Some(mut output) = self.output.take() {
+            let _ = write!(output, "{}", self.summary());
+        }
+    }
+}
+
+/// Passes on only the events of the given kinds.
+pub struct FilteredTracer<T: Tracer> {
+    kinds: Vec<&'static str>,
+    inner: T
+}
+impl<T: Tracer> FilteredTracer<T> {
+    pub fn new(kinds: Vec<&'static str>, inner: T) -> Self {
+        FilteredTracer { kinds, inner }
+    }
+}
+impl<T: Tracer> Tracer for FilteredTracer<T> {
+    fn event(&mut self, event: &TraceEvent) {
+        if self.kinds.contains(&event.kind()) {
+            self.inner.event(event);
+        }
+    }
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+}
+
+/// The built-in tracers selectable with `--trace`, all writing to stderr so that they do not mix with results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sink {
+    Pretty,
+    Jsonl,
+    Count
+}
+impl Sink {
+    pub fn parse(string: &str) -> Result<Self, String> {
+        match string {
+            "pretty" => Ok(Sink::Pretty),
+            "jsonl" => Ok(Sink::Jsonl),
+            "count" => Ok(Sink::Count),
+            _ => Err(format!("Unknown trace sink '{}' - expected pretty, jsonl or count", string))
+        }
+    }
+    /// A tracer for this sink, keeping only the events of `kinds` if given.
+    pub fn tracer(self, kinds: Option<Vec<&'static str>>) -> Box<dyn Tracer> {
+        let tracer: Box<dyn Tracer> = match self {
+            Sink::Pretty => Box::new(PrettyTracer::new(io::stderr())),
+            Sink::Jsonl => Box::new(JsonTracer::new(io::stderr())),
+            Sink::Count => Box::new(CountingTracer::reporting_to(io::stderr()))
+        };
+        match kinds {
+            Some(kinds) => Box::new(FilteredTracer::new(kinds, tracer)),
+            None => tracer
+        }
+    }
+}
+
+/// Parses a comma-separated list of event kinds such as `rule,fire`.
+pub fn parse_kinds(list: &str) -> Result<Vec<&'static str>, String> {
+    list.split(',').map(|kind: &str| {
+        let kind: &str = kind.trim();
+        EVENT_KINDS.iter().copied().find(|known: &&str| *known == kind).ok_or(format!("Unknown trace event '{}' - expected {}", kind, EVENT_KINDS.join(", ")))
+    }).collect()
+}
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::*;
+use crate::type_checker;
+
+/// A batch of changes to the axiomatic facts and rules of a knowledge base that is applied all at once or not at all.
+/// Changes are only buffered until `commit`, which validates the result (rule safety and type declarations) before
+/// replacing the knowledge base. Dropping the transaction without committing discards it, like `rollback`.
+///
+/// Savepoints nest: rolling back to a savepoint discards the changes made after it, including any savepoints taken
+/// later, while releasing one keeps its changes and only forgets the savepoint.
+pub struct Transaction<'k> {
+    knowledge_base: &'k mut KnowledgeBase,
+    changes: Vec<Change>,
+    /// The live savepoints, oldest first, with the number of changes made before each.
+    savepoints: Vec<(Savepoint, usize)>,
+    next_savepoint: usize
+}
+enum Change {
+    AddFact(Fact),
+    RemoveFact(Fact),
+    AddRule(Rule)
+}
+/// A point in a transaction to roll back to, see `Transaction::savepoint`. Every savepoint of a transaction is
+/// numbered differently, so one that was rolled back or released is never mistaken for a later one.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Savepoint(usize);
+
+/// What a committed transaction changed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CommitSummary {
+    pub added_facts: usize,
+    pub removed_facts: usize,
+    pub added_rules: usize
+}
+
+impl KnowledgeBase {
+    pub fn begin(&mut self) -> Transaction<'_> {
+        Transaction { knowledge_base: self, changes: Vec::new(), savepoints: Vec::new(), next_savepoint: 0 }
+    }
+}
+impl<'k> Transaction<'k> {
+    /// Adds a given fact on commit. Negative facts cannot be given; remove the positive fact instead.
+    pub fn add_fact(&mut self, fact: Fact) -> Result<(), String> {
+        if fact.is_negative() {
+            return Err(format!("Cannot add negative fact {}, remove {} instead", fact, fact.get_negated()));
+        }
+        self.changes.push(Change::AddFact(fact));
+        Ok(())
+    }
+    /// Removes a given fact on commit. Removing a fact that is not there is not an error.
+    pub fn remove_fact(&mut self, fact: Fact) {
+        self.changes.push(Change::RemoveFact(fact));
+    }
+    pub fn add_rule(&mut self, rule: Rule) -> Result<(), String> {
+        rule.check_safety().map_err(|error| format!("Unsafe rule {}: {}", rule, error))?;
+        self.changes.push(Change::AddRule(rule));
+        Ok(())
+    }
+    pub fn savepoint(&mut self) -> Savepoint {
+        self.next_savepoint += 1;
+        self.savepoints.push((Savepoint(self.next_savepoint), self.changes.len()));
+        Savepoint(self.next_savepoint)
+    }
+    /// Discards the changes made since the savepoint. The savepoint and any later ones can no longer be used.
+    pub fn rollback_to(&mut self, savepoint: Savepoint) -> Result<(), String> {
+        let index: usize = self.position(&savepoint)?;
+        self.changes.truncate(self.savepoints[index].1);
+        self.savepoints.truncate(index);
+        Ok(())
+    }
+    /// Keeps the changes made since the savepoint and forgets it, along with any later savepoints.
+    pub fn release(&mut self, savepoint: Savepoint) -> Result<(), String> {
+        let index: usize = self.position(&savepoint)?;
+        self.savepoints.truncate(index);
+        Ok(())
+    }
+    fn position(&self, savepoint: &Savepoint) -> Result<usize, String> {
+        self.savepoints.iter().position(|(live, _)| live == savepoint).ok_or(String::from("Savepoint was already released or rolled back"))
+    }
+    pub fn rollback(self) {}
+    /// Applies every change, or none of them if the result does not type check. Derived facts are left as they are;
+    /// use `commit_and_infer` to bring them up to date.
+    pub fn commit(self) -> Result<CommitSummary, String> {
+        let (knowledge_base, updated, summary) = self.prepare()?;
+        *knowledge_base = updated;
+        Ok(summary)
+    }
+    /// Commits and then brings the derived facts up to date. When the transaction only added facts and rules and no
+    /// rule uses negation, everything derived before still holds and inference continues from it; otherwise working
+    /// memory is rebuilt from scratch.
+    pub fn commit_and_infer(self) -> Result<CommitSummary, String> {
+        let (knowledge_base, mut updated, summary) = self.prepare()?;
+        let uses_negation: bool = updated.get_rules().any(|rule: &Rule| rule.antecedents.iter().any(|item: &AntecedentItem| matches!(item, AntecedentItem::Fact(fact) if fact.is_negative())));
+        if summary.removed_facts > 0 || uses_negation {
+            updated.clear_working_memory();
+        }
+        let mut inference_engine: InferenceEngine = InferenceEngine::new(updated);
+        inference_engine.infer();
+        *knowledge_base = inference_engine.knowledge_base;
+        Ok(summary)
+    }
+    /// Applies the changes to a copy of the knowledge base and validates it. The copy is detached from the event
+    /// log until it passes, so a failed commit leaves no trace in the log.
+    fn prepare(self) -> Result<(&'k mut KnowledgeBase, KnowledgeBase, CommitSummary), String> {
+        let mut updated: KnowledgeBase = self.knowledge_base.clone();
+        let event_log = updated.set_event_log(None);
+        let mut added_rules: usize = 0;
+        for change in self.changes {
+            match change {
+                Change::AddFact(fact) => updated.add_axiomatic_fact(fact),
+                Change::RemoveFact(fact) => { updated.remove_axiomatic_fact(&fact); }
+                Change::AddRule(rule) => {
+                    updated.add_axiomatic_rule(rule);
+                    added_rules += 1;
+                }
+            }
+        }
+        type_checker::check(&updated)?;
+        updated.set_event_log(event_log);
+        updated.record_changes(self.knowledge_base);
+        let facts = |knowledge_base: &KnowledgeBase| knowledge_base.get_axiomatic_facts().cloned().collect::<HashSet<Fact>>();
+        let changes: FactDiff = diff(&facts(self.knowledge_base), &facts(&updated));
+        Ok((self.knowledge_base, updated, CommitSummary { added_facts: changes.added.len(), removed_facts: changes.removed.len(), added_rules }))
+    }
+}
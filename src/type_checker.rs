@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use crate::knowledge_base::*;
+
+/// Checks facts and rules against the `:- type` declarations of a knowledge base.
+/// Predicates without a declaration are not checked, but ordering comparisons (`>`, `>=`, `<`, `<=`)
+/// always require numbers, since the inference engine cannot compare anything else.
+pub fn check(knowledge_base: &KnowledgeBase) -> Result<(), String> {
+    let mut errors: Vec<(Option<&SourceLocation>, String)> = Vec::new();
+    for fact in knowledge_base.get_axiomatic_facts() {
+        let mut variable_types: HashMap<String, ArgumentType> = HashMap::new();
+        if let Err(error) = check_fact(knowledge_base, fact, &mut variable_types) {
+            errors.push((knowledge_base.get_fact_source(fact), error));
+        }
+    }
+    for (index, rule) in knowledge_base.get_rules().enumerate() {
+        if let Err(error) = check_rule(knowledge_base, rule) {
+            errors.push((knowledge_base.get_rule_source(index), error));
+        }
+    }
+    if errors.is_empty() { return Ok(()); }
+    errors.sort();
+    let messages: Vec<String> = errors.into_iter().map(|(source, error)| match source {
+        Some(source) => format!("{}: {}", source, error),
+        None => error
+    }).collect();
+    Err(messages.join("\n"))
+}
+
+fn check_rule(knowledge_base: &KnowledgeBase, rule: &Rule) -> Result<(), String> {
+    let mut variable_types: HashMap<String, ArgumentType> = HashMap::new();
+    for index in rule.literal_indices() {
+        if let AntecedentItem::Fact(fact) = &rule.antecedents[index] {
+            check_fact(knowledge_base, fact, &mut variable_types)?;
+        }
+    }
+    check_fact(knowledge_base, &rule.consequent, &mut variable_types)?;
+    let mut stack: Vec<Option<&Fact>> = Vec::new();
+    for item in &rule.antecedents {
+        match item {
+            AntecedentItem::Fact(fact) => stack.push(Some(fact)),
+            AntecedentItem::And | AntecedentItem::Or => {
+                stack.pop();
+                stack.pop();
+                stack.push(None);
+            }
+            AntecedentItem::Equals | AntecedentItem::NotEquals => {
+                let right: Option<&Fact> = stack.pop().flatten();
+                let left: Option<&Fact> = stack.pop().flatten();
+                if let (Some(left), Some(right)) = (left, right)
+                    && let (Some(left_type), Some(right_type)) = (type_of(left, &variable_types), type_of(right, &variable_types))
+                    && left_type != ArgumentType::Any && right_type != ArgumentType::Any && left_type != right_type {
+                    return Err(format!("Type error: cannot compare {} ({}) {} {} ({})", left, left_type, item, right, right_type));
+                }
+                stack.push(None);
+            }
+            _ => {
+                let right: Option<&Fact> = stack.pop().flatten();
+                let left: Option<&Fact> = stack.pop().flatten();
+                for operand in [left, right].into_iter().flatten() {
+                    if let Some(operand_type) = type_of(operand, &variable_types)
+                        && operand_type != ArgumentType::Int && operand_type != ArgumentType::Any {
+                        return Err(format!("Type error: {} is {} but {} needs int operands", operand, operand_type, item));
+                    }
+                }
+                stack.push(None);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks a single fact or literal, recording the type every variable argument takes on.
+fn check_fact(knowledge_base: &KnowledgeBase, fact: &Fact, variable_types: &mut HashMap<String, ArgumentType>) -> Result<(), String> {
+    let Some((name, arity)) = fact.signature() else { return Ok(()); };
+    let Some(declaration) = knowledge_base.get_type_declaration(name) else { return Ok(()); };
+    if declaration.arguments.len() != arity {
+        return Err(format!("Arity mismatch: {} is declared with {} arguments but {} has {}", name, declaration.arguments.len(), fact, arity));
+    }
+    let Fact::Predicate(predicate) = fact else { return Ok(()); };
+    for (position, (argument, argument_type)) in predicate.arguments.iter().zip(&declaration.arguments).enumerate() {
+        match argument {
+            Fact::Variable(variable) => {
+                if *argument_type == ArgumentType::Any { continue; }
+                match variable_types.get(&variable.name) {
+                    Some(existing) if existing != argument_type => {
+                        return Err(format!("Type error: {} is used as {} and as {} (argument {} of {})", argument, existing, argument_type, position + 1, fact));
+                    }
+                    _ => { variable_types.insert(variable.name.clone(), *argument_type); }
+                }
+            }
+            _ => {
+                if !argument_type.accepts(argument) {
+                    return Err(format!("Type error: argument {} of {} should be {}, found {}", position + 1, fact, argument_type, argument));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn type_of(fact: &Fact, variable_types: &HashMap<String, ArgumentType>) -> Option<ArgumentType> {
+    match fact {
+        Fact::Variable(variable) => variable_types.get(&variable.name).copied(),
+        Fact::Number(_) => Some(ArgumentType::Int),
+        Fact::Atomic(_) => Some(ArgumentType::Atom),
+        Fact::Predicate(_) => None
+    }
+}
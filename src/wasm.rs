@@ -0,0 +1,79 @@
+use std::{mem, ptr, slice};
+
+use crate::inference_engine::InferenceEngine;
+use crate::json::Json;
+use crate::knowledge_base::KnowledgeBase;
+use crate::server::{self, Server};
+
+/// The exports of the wasm32 build, wrapped for JavaScript by `wasm/pie.js`. A session is a `Server` without its
+/// transport: JavaScript writes a JSON-RPC request into memory from `pie_alloc`, calls `pie_session_call` and reads
+/// the response from `pie_session_response`. See `Server` for the methods; knowledge bases can only be loaded from
+/// `source`, as there is no filesystem.
+///
+/// A panic cannot be caught on wasm32-unknown-unknown: it traps, and the instance is unusable afterwards. Requests
+/// are answered with JSON-RPC errors instead, including calls with a NULL request, and `wasm/pie.js` reports a trap
+/// as an internal error.
+pub struct Session {
+    server: Server,
+    response: String
+}
+
+/// Allocates `length` bytes for the caller to fill and give back to `pie_dealloc`.
+#[unsafe(no_mangle)]
+pub extern "C" fn pie_alloc(length: usize) -> *mut u8 {
+    let mut buffer: Vec<u8> = Vec::with_capacity(length);
+    let pointer: *mut u8 = buffer.as_mut_ptr();
+    mem::forget(buffer);
+    pointer
+}
+
+/// # Safety
+/// `pointer` and `length` must come from one call to `pie_alloc`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_dealloc(pointer: *mut u8, length: usize) {
+    drop(unsafe { Vec::from_raw_parts(pointer, 0, length) });
+}
+
+/// A session with an empty knowledge base.
+#[unsafe(no_mangle)]
+pub extern "C" fn pie_session_new() -> *mut Session {
+    let server: Server = Server::new(InferenceEngine::new(KnowledgeBase::new()));
+    Box::into_raw(Box::new(Session { server, response: String::new() }))
+}
+
+/// # Safety
+/// `session` must be NULL or a live session from `pie_session_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_session_free(session: *mut Session) {
+    if !session.is_null() {
+        drop(unsafe { Box::from_raw(session) });
+    }
+}
+
+/// Handles the JSON-RPC request of `length` UTF-8 bytes at `request` and returns the length of the response, which
+/// stays readable at `pie_session_response` until the next call. Notifications get an empty response, and so does
+/// a NULL session, which has nowhere to keep one.
+///
+/// # Safety
+/// `session` must be NULL or a live session, and `request` NULL or point to `length` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_session_call(session: *mut Session, request: *const u8, length: usize) -> usize {
+    let Some(session) = (unsafe { session.as_mut() }) else { return 0; };
+    let response: Option<Json> = if request.is_null() {
+        Some(server::error_response(&Json::Null, server::INVALID_REQUEST, "request is NULL"))
+    } else {
+        let request: &[u8] = unsafe { slice::from_raw_parts(request, length) };
+        session.server.handle_line(&String::from_utf8_lossy(request))
+    };
+    session.response = response.map(|response: Json| response.to_string()).unwrap_or_default();
+    session.response.len()
+}
+
+/// The response of the last call, or NULL for a NULL session.
+///
+/// # Safety
+/// `session` must be NULL or a live session.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pie_session_response(session: *const Session) -> *const u8 {
+    unsafe { session.as_ref() }.map_or(ptr::null(), |session: &Session| session.response.as_ptr())
+}
@@ -0,0 +1,71 @@
+use std::{collections::{HashMap, HashSet}, fs, io::Write, path::{Path, PathBuf}, thread, time::{Duration, SystemTime}};
+
+use crate::inference_engine::InferenceEngine;
+use crate::knowledge_base::*;
+use crate::loader::Loader;
+
+/// Re-loads a set of knowledge-base files whenever one of them changes, re-runs inference and reports which derived
+/// facts appeared or disappeared since the previous successful run. Files reached through `include` are watched too.
+pub struct Watcher {
+    paths: Vec<String>,
+    stamps: HashMap<PathBuf, Option<(SystemTime, u64)>>,
+    previous: Option<HashSet<Fact>>
+}
+impl Watcher {
+    pub fn new(paths: Vec<String>) -> Self {
+        Watcher { paths, stamps: HashMap::new(), previous: None }
+    }
+    /// Loads the files once, then polls them every `interval` until the process is stopped.
+    pub fn run(&mut self, interval: Duration, output: &mut impl Write) -> Result<(), String> {
+        self.reload(output)?;
+        loop {
+            thread::sleep(interval);
+            if self.has_changed() {
+                self.reload(output)?;
+            }
+        }
+    }
+    /// Whether any watched file was modified, created or deleted since the last reload.
+    pub fn has_changed(&self) -> bool {
+        self.stamps.iter().any(|(path, stamp)| file_stamp(path) != *stamp)
+    }
+    /// Loads and infers again, printing either the load error or the difference in derived facts.
+    /// A failed load keeps the previous result, so the next successful run is compared against the last good one.
+    pub fn reload(&mut self, output: &mut impl Write) -> Result<(), String> {
+        let mut loader: Loader = Loader::new();
+        let loaded: Result<(), String> = self.paths.iter().try_for_each(|path: &String| loader.load_file(Path::new(path)));
+        self.stamps = self.paths.iter().map(PathBuf::from).chain(loader.loaded_files().cloned())
+            .map(|path: PathBuf| { let stamp: Option<(SystemTime, u64)> = file_stamp(&path); (path, stamp) })
+            .collect();
+        let report: String = match loaded.and_then(|_| loader.finish()) {
+            Ok(knowledge_base) => {
+                let mut inference_engine: InferenceEngine = InferenceEngine::new(knowledge_base);
+                inference_engine.infer();
+                let current: HashSet<Fact> = inference_engine.knowledge_base.working_memory().clone();
+                let report: String = match &self.previous {
+                    None => format!("Loaded {} derived fact(s)\n{}", current.len(), format_diff(&diff(&HashSet::new(), &current))),
+                    Some(previous) => {
+                        let changes: FactDiff = diff(previous, &current);
+                        if changes.is_empty() { String::from("No changes in derived facts\n") } else { format_diff(&changes) }
+                    }
+                };
+                self.previous = Some(current);
+                report
+            }
+            Err(error) => format!("Error: {}\n", error)
+        };
+        write!(output, "{}", report).and_then(|_| output.flush()).map_err(|error| format!("Failed to write output: {}", error))
+    }
+}
+
+fn format_diff(changes: &FactDiff) -> String {
+    let added = changes.added.iter().map(|fact: &Fact| format!("+ {}\n", fact));
+    let removed = changes.removed.iter().map(|fact: &Fact| format!("- {}\n", fact));
+    removed.chain(added).collect()
+}
+
+/// The modification time and size of a file; the size catches edits within the timestamp resolution.
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata: fs::Metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
@@ -0,0 +1,128 @@
+// JavaScript API of the PIE inference engine compiled to WebAssembly. Build the module with
+//
+//     cargo build --lib --release --target wasm32-unknown-unknown
+//
+// and load target/wasm32-unknown-unknown/release/pie.wasm:
+//
+//     const pie = await Pie.load(fetch("pie.wasm"));
+//     const engine = pie.engine();
+//     engine.loadText("parent(ama, osei).\nparent(osei, kofi).\nparent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).");
+//     engine.query("grandparent(x?, y?)");   // [{fact: "grandparent(ama, kofi)", bindings: {x: "ama", y: "kofi"}}]
+//
+// Each engine keeps its knowledge base in wasm memory until `free` is called. Failed calls throw a PieError with the
+// JSON-RPC error code of the request.
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+export class PieError extends Error {
+    constructor(code, message) {
+        super(message);
+        this.name = "PieError";
+        this.code = code;
+    }
+}
+
+export class Pie {
+    constructor(instance) {
+        this.exports = instance.exports;
+    }
+
+    // Instantiates the module from a URL fetch (a Response or a promise of one), bytes or a WebAssembly.Module.
+    static async load(source) {
+        source = await source;
+        let instance;
+        if (source instanceof WebAssembly.Module) {
+            instance = await WebAssembly.instantiate(source, {});
+        } else if (typeof Response !== "undefined" && source instanceof Response) {
+            instance = (await WebAssembly.instantiate(await source.arrayBuffer(), {})).instance;
+        } else {
+            instance = (await WebAssembly.instantiate(source, {})).instance;
+        }
+        return new Pie(instance);
+    }
+
+    // A new engine with an empty knowledge base.
+    engine() {
+        return new Engine(this.exports);
+    }
+}
+
+export class Engine {
+    constructor(exports) {
+        this.exports = exports;
+        this.session = exports.pie_session_new();
+        this.nextId = 1;
+    }
+
+    // Loads knowledge-base text in "pie" or "datalog" syntax. Returns the derived facts that were added and removed.
+    loadText(source, syntax = "pie") {
+        return this.call("load", { source, syntax });
+    }
+
+    // Adds given facts such as "parent(ama, osei)". Returns the derived facts that were added and removed.
+    assert(...facts) {
+        return this.call("assert", { facts });
+    }
+
+    retract(...facts) {
+        return this.call("retract", { facts });
+    }
+
+    // Derives every fact that follows and returns the derived facts as {fact, predicate, negated, arguments} records.
+    infer() {
+        return this.call("infer").facts;
+    }
+
+    // The known facts matching a pattern such as "parent(x?, osei)", with the values of its variables.
+    query(pattern) {
+        return this.call("query", { pattern }).matches;
+    }
+
+    // Whether a goal such as "grandparent(ama, kofi)" can be proven by backward chaining.
+    prove(goal) {
+        return this.call("prove", { goal }).proven;
+    }
+
+    // How a fact, or every fact matching a pattern, was derived, as {depth, fact, reason, rule} nodes in depth-first
+    // order, where depth 0 starts a new explanation.
+    explain(fact) {
+        return this.call("explain", { fact }).explanations;
+    }
+
+    // The given and derived facts, optionally only those of a predicate such as "parent" or "parent/2".
+    facts(predicate) {
+        return this.call("facts", predicate === undefined ? {} : { predicate }).facts;
+    }
+
+    free() {
+        this.exports.pie_session_free(this.session);
+        this.session = 0;
+    }
+
+    call(method, params = {}) {
+        if (!this.session) {
+            throw new PieError(-32600, "The engine has been freed");
+        }
+        const request = encoder.encode(JSON.stringify({ jsonrpc: "2.0", id: this.nextId++, method, params }));
+        const pointer = this.exports.pie_alloc(request.length);
+        new Uint8Array(this.exports.memory.buffer, pointer, request.length).set(request);
+        let length;
+        try {
+            length = this.exports.pie_session_call(this.session, pointer, request.length);
+        } catch (error) {
+            if (error instanceof WebAssembly.RuntimeError) {
+                throw new PieError(-32603, `Internal error: ${error.message}; load the module again to continue`);
+            }
+            throw error;
+        } finally {
+            this.exports.pie_dealloc(pointer, request.length);
+        }
+        const response = this.exports.pie_session_response(this.session);
+        const { result, error } = JSON.parse(decoder.decode(new Uint8Array(this.exports.memory.buffer, response, length)));
+        if (error) {
+            throw new PieError(error.code, error.message);
+        }
+        return result;
+    }
+}
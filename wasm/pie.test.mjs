@@ -0,0 +1,68 @@
+// Run with `node --test wasm/` after `cargo build --lib --release --target wasm32-unknown-unknown`.
+import assert from "node:assert/strict";
+import { readFile } from "node:fs/promises";
+import { test } from "node:test";
+
+import { Pie, PieError } from "./pie.js";
+
+const pie = await Pie.load(readFile(new URL("../target/wasm32-unknown-unknown/release/pie.wasm", import.meta.url)));
+
+const FAMILY = `
+parent(ama, osei).
+parent(osei, kofi).
+parent(x?, y?) & parent(y?, z?) -> grandparent(x?, z?).
+`;
+
+test("load, infer and query", () => {
+    const engine = pie.engine();
+    assert.deepEqual(engine.loadText(FAMILY), { added: ["grandparent(ama, kofi)"], removed: [] });
+    assert.deepEqual(engine.infer().map((record) => record.fact), ["grandparent(ama, kofi)"]);
+    assert.deepEqual(engine.query("grandparent(x?, y?)"), [{ fact: "grandparent(ama, kofi)", bindings: { x: "ama", y: "kofi" } }]);
+    assert.equal(engine.facts("parent/2").length, 2);
+    engine.free();
+});
+
+test("prove and explain", () => {
+    const engine = pie.engine();
+    engine.loadText(FAMILY);
+    assert.equal(engine.prove("grandparent(ama, kofi)"), true);
+    assert.equal(engine.prove("grandparent(kofi, ama)"), false);
+    const explanation = engine.explain("grandparent(ama, kofi)");
+    assert.deepEqual(explanation.map((node) => [node.depth, node.fact, node.reason]), [
+        [0, "grandparent(ama, kofi)", "derived"],
+        [1, "parent(ama, osei)", "given"],
+        [1, "parent(osei, kofi)", "given"]
+    ]);
+    engine.free();
+});
+
+test("datalog and changes", () => {
+    const engine = pie.engine();
+    engine.loadText("parent(ama, osei).\nancestor(X, Y) :- parent(X, Y).", "datalog");
+    assert.deepEqual(engine.assert("parent(osei, kofi)"), { added: ["ancestor(osei, kofi)"], removed: [] });
+    assert.deepEqual(engine.retract("parent(ama, osei)"), { added: [], removed: ["ancestor(ama, osei)"] });
+    engine.free();
+});
+
+test("errors", () => {
+    const engine = pie.engine();
+    assert.throws(() => engine.loadText("a & -> b."), (error) => error instanceof PieError && error.code === -32000);
+    assert.throws(() => engine.loadText("include \"other.txt\"."), /Failed to read file/);
+    assert.throws(() => engine.call("unknown"), (error) => error.code === -32601);
+    engine.free();
+    assert.throws(() => engine.infer(), PieError);
+    assert.equal(pie.exports.pie_session_call(0, 0, 0), 0);
+    assert.equal(pie.exports.pie_session_response(0), 0);
+});
+
+test("comparisons on atoms", () => {
+    const engine = pie.engine();
+    assert.deepEqual(engine.loadText("age(kofi, old).\nage(x?, a?) & a? > 18 -> adult(x?)."), { added: [], removed: [] });
+    assert.deepEqual(engine.infer(), []);
+    const session = pie.exports.pie_session_new();
+    const length = pie.exports.pie_session_call(session, 0, 5);
+    const response = new Uint8Array(pie.exports.memory.buffer, pie.exports.pie_session_response(session), length);
+    assert.equal(JSON.parse(new TextDecoder().decode(response)).error.message, "request is NULL");
+    pie.exports.pie_session_free(session);
+    engine.free();
+});